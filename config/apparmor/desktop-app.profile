@@ -0,0 +1,30 @@
+# ملف AppArmor مولد من قالب desktop-app بواسطة xBitOS
+# لتغيير الوضع: xbitos apparmor mode {{name}} complain|enforce
+# الإضافات المحلية توضع في /etc/apparmor.d/local/{{name}}
+abi <abi/3.0>,
+include <tunables/global>
+
+profile {{name}} {{exec}} {{flags}}{
+  include <abstractions/base>
+  include <abstractions/audio>
+  include <abstractions/dri-common>
+  include <abstractions/fonts>
+  include <abstractions/freedesktop.org>
+  include <abstractions/mesa>
+  include <abstractions/nameservice>
+  include <abstractions/vulkan>
+  include <abstractions/wayland>
+
+  {{exec}} mr,
+  /usr/lib/** mr,
+  /usr/share/** r,
+  /etc/{{config_dir}}/** r,
+
+  owner @{HOME}/.config/{{config_dir}}/** rwk,
+  owner @{HOME}/.cache/{{config_dir}}/** rwk,
+  owner @{HOME}/.local/share/{{config_dir}}/** rwk,
+  owner @{PROC}/@{pid}/{stat,status,cmdline,mountinfo} r,
+  owner /dev/shm/** rw,
+{{rules}}
+  include if exists <local/{{name}}>
+}
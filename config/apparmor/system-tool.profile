@@ -0,0 +1,127 @@
+# ملف AppArmor مولد من قالب system-tool بواسطة xBitOS
+# لتغيير الوضع: xbitos apparmor mode {{name}} complain|enforce
+# الإضافات المحلية توضع في /etc/apparmor.d/local/{{name}}
+abi <abi/3.0>,
+include <tunables/global>
+
+profile {{name}} {{exec}} {{flags}}{
+  include <abstractions/base>
+  include <abstractions/dbus-strict>
+  include <abstractions/nameservice>
+  include <abstractions/ssl_certs>
+
+  # mac_admin لتحميل ملفات AppArmor نفسها، ولا sys_module أو sys_rawio
+  capability chown dac_override dac_read_search fowner fsetid kill setgid setuid,
+  capability sys_admin sys_chroot sys_resource net_admin mac_admin audit_write,
+
+  network inet stream,
+  network inet6 stream,
+  network inet dgram,
+  network inet6 dgram,
+  network netlink raw,
+  signal (send),
+  dbus bus=system,
+  unix,
+  ptrace (read),
+
+  {{exec}} mr,
+  /usr/lib/** mr,
+  /usr/share/** r,
+  @{PROC}/** r,
+  @{PROC}/sys/** w,
+  /sys/** r,
+  /sys/firmware/efi/efivars/** rw,
+  /sys/kernel/security/apparmor/** rw,
+
+  # ملفات الإعداد والحالة التي يديرها xbitos
+  /etc/** rwk,
+  /boot/** rw,
+  /efi/** rw,
+  /mnt/** rw,
+  /var/lib/xbitos{,-local-repo}/** rwk,
+  /var/cache/xbitos/** rwk,
+  /var/log/xbitos/** rw,
+  /var/log/pacman.log r,
+  /var/lib/pacman/** r,
+  /tmp/** rwk,
+  /run/xbitos/** rwk,
+  /run/systemd/private rw,
+  owner /run/user/*/gnupg/** rwk,
+  /usr/share/{dbus-1,polkit-1,wayland-sessions,doc/xbitos}/** rw,
+  /usr/local/bin/* rw,
+  /dev/{sd,vd,nvme,mmcblk,loop,dm-}* rw,
+  /dev/mapper/** rw,
+
+  # أدوات الحزم والتثبيت تكتب في /usr وجذور البناء فلها ملف فرعي
+  /usr/bin/{pacman,pacman-key,pacstrap,arch-chroot,genfstab,makepkg,mkarchiso,mkinitcpio,systemd-nspawn,bwrap,bootctl} Cx -> packages,
+
+  # النسخ الاحتياطي يقرأ المنازل ولا يكتب إلا في مستودعه
+  /usr/bin/{borg,snapper,btrfs} Cx -> backup,
+
+  # بقية الأدوات المستدعاة ترث قيود هذا الملف
+  /usr/bin/{bash,sh,cp,chmod,mount,lsblk,systemctl,sysctl,nft,firewall-cmd,python3*} ix,
+  /usr/bin/{cryptsetup,parted,sbsign,sbverify,openssl,cert-to-efi-sig-list,sign-efi-sig-list} ix,
+  /usr/bin/{gpg,gpg-agent,gpgconf,git,curl,ssh-keygen,sshd,visudo,apparmor_parser,chpasswd,sudo} ix,
+  /usr/bin/mkfs.* ix,
+  /usr/lib/git-core/* ix,
+
+  profile packages {{flags}}{
+    include <abstractions/base>
+    include <abstractions/dbus-strict>
+    include <abstractions/nameservice>
+    include <abstractions/ssl_certs>
+
+    capability chown dac_override dac_read_search fowner fsetid kill mknod setgid setuid,
+    capability sys_admin sys_chroot sys_resource audit_write,
+
+    network inet stream,
+    network inet6 stream,
+    network inet dgram,
+    network inet6 dgram,
+    network netlink raw,
+    mount,
+    umount,
+    pivot_root,
+    signal,
+    dbus bus=system,
+    unix,
+
+    # خطافات pacman وسكربتات التثبيت تعمل بقيود هذا الملف الفرعي
+    / r,
+    /usr/** rwlk,
+    /usr/{bin,lib}/** mix,
+    /etc/** rwlk,
+    /var/** rwlk,
+    /boot/** rwl,
+    /efi/** rwl,
+    /mnt/** rwlk,
+    /tmp/** rwlk,
+    /run/** rwk,
+    /dev/** rw,
+    @{PROC}/** r,
+    /sys/** r,
+  }
+
+  profile backup {{flags}}{
+    include <abstractions/base>
+    include <abstractions/nameservice>
+    include <abstractions/python>
+    include <abstractions/ssl_certs>
+
+    capability dac_read_search fowner sys_admin,
+
+    network inet stream,
+    network inet6 stream,
+    dbus bus=system,
+    unix,
+
+    /** r,
+    /usr/bin/{python3*,ssh} ix,
+    /var/lib/xbitos/backups/** rwk,
+    /{,home/*/}.snapshots/** rw,
+    /{root,home/*}/.{cache,config}/borg/** rwk,
+    /tmp/** rwk,
+  }
+{{rules}}
+  include if exists <local/{{name}}>
+}
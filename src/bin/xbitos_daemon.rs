@@ -0,0 +1,39 @@
+use anyhow::Result;
+use log::info;
+use xbitos::system::daemon::{self, Authorization, BusAddress};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // تهيئة نظام التسجيل
+    env_logger::init();
+
+    let mut address = BusAddress::System;
+    let mut authorization = Authorization::Polkit;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--session" => address = BusAddress::Session,
+            "--address" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--address requires a value"))?;
+                address = BusAddress::Custom(value);
+            }
+            // يستخدم فقط مع dbus-daemon خاص أثناء الاختبار، انظر daemon::TEST_BUS_ENV
+            "--no-polkit" => authorization = Authorization::AllowAll,
+            "--install" => {
+                daemon::DaemonInstaller::new().install()?;
+                return Ok(());
+            }
+            _ => return Err(anyhow::anyhow!("Unknown argument: {}", arg)),
+        }
+    }
+
+    let _connection = daemon::serve(&address, authorization).await?;
+
+    tokio::signal::ctrl_c().await?;
+    info!("xbitos-daemon shutting down");
+
+    Ok(())
+}
@@ -1,8 +1,24 @@
-mod system;
-
 use anyhow::Result;
 use log::{info, error};
-use crate::system::{
+use std::path::{Path, PathBuf};
+use xbitos::system::local_db::{DependencyTree, LocalDatabase};
+use xbitos::system::packaging::aur::AurClient;
+use xbitos::system::packaging::builder::PackageBuilder;
+use xbitos::system::packaging::batch::BatchStatus;
+use xbitos::system::packaging::lint::{PackageLinter, Severity};
+use xbitos::system::packaging::recipes::RecipeStore;
+use xbitos::system::packaging::reproducible;
+use xbitos::system::packaging::signing::KeyManager;
+use xbitos::system::security::SecurityManager;
+use xbitos::system::security::advisories::{affected_packages, AdvisoryFeed};
+use xbitos::system::security::apparmor::{AppArmorManager, ProfileMode};
+use xbitos::system::security::secure_boot::SecureBootManager;
+use xbitos::system::security::ssh::{validate_config, SshManager};
+use xbitos::system::security::sudo::{check_sudoers, SudoManager};
+use xbitos::system::security::hardening::{HardeningManager, KernelProfile};
+use xbitos::system::security::firewall::{check_nftables, FirewallBackend, FirewallManager};
+use xbitos::system::security::integrity::IntegrityChecker;
+use xbitos::system::{
     package_manager::PackageManager,
     display::DisplayManager,
     services::ServiceManager,
@@ -113,10 +129,789 @@ async fn setup_system() -> Result<()> {
     Ok(())
 }
 
+fn run_package_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos package <info|files|rdeps|why|optdeps|owner> <name|path> [--json]\n       xbitos package verify [name] [--reinstall] [--json]";
+
+    let json = args.iter().any(|arg| arg == "--json");
+
+    if args.first().map(|c| c.as_str()) == Some("verify") {
+        return run_verify_command(&args[1..], json);
+    }
+
+    let (Some(command), Some(target)) = (args.first(), args.get(1)) else {
+        return Err(anyhow::anyhow!(usage));
+    };
+
+    let db = LocalDatabase::new()?;
+
+    match command.as_str() {
+        "info" => {
+            let details = db.get_details(target)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&details)?);
+                return Ok(());
+            }
+
+            println!("Name         : {}", details.package.name);
+            println!("Version      : {}", details.package.version);
+            println!("Description  : {}", details.package.description);
+            println!("Depends On   : {}", details.package.depends.join(" "));
+            println!("Required By  : {}", details.required_by.join(" "));
+            println!("Why Installed: {}", format_chain(&details.install_chain));
+            println!("Files        : {}", details.files.len());
+        }
+        "files" => {
+            let files = db.get_files(target)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&files)?);
+                return Ok(());
+            }
+
+            for file in files {
+                println!("{}", file.display());
+            }
+        }
+        "rdeps" => {
+            let tree = db.reverse_dependency_tree(target)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tree)?);
+            } else {
+                print_tree(&tree, 0);
+            }
+        }
+        "why" => {
+            let chain = db.explain_installation(target)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&chain)?);
+            } else {
+                println!("{}", format_chain(&chain));
+            }
+        }
+        "optdeps" => {
+            let optdeps = db.optional_dependencies(target)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&optdeps)?);
+                return Ok(());
+            }
+
+            for optdep in optdeps {
+                let mark = if optdep.installed { "[installed]" } else { "" };
+                println!("{}: {} {}", optdep.name, optdep.description, mark);
+            }
+        }
+        "owner" => match db.find_owner(Path::new(target))? {
+            Some(owner) if json => {
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "path": target, "owner": owner }))?);
+            }
+            Some(owner) => println!("{} is owned by {}", target, owner),
+            None => return Err(anyhow::anyhow!("No package owns {}", target)),
+        },
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_verify_command(args: &[String], json: bool) -> Result<()> {
+    let checker = IntegrityChecker::new();
+    let reinstall = args.iter().any(|arg| arg == "--reinstall");
+
+    let reports = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(name) => vec![checker.check(name)?],
+        None => checker.check_all()?,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            for issue in &report.issues {
+                println!("{}: {} ({:?}: {})", report.package, issue.path.display(), issue.kind, issue.details);
+            }
+            for warning in &report.warnings {
+                println!("{}: warning: {}", report.package, warning);
+            }
+        }
+
+        let damaged = reports.iter().filter(|r| r.is_damaged()).count();
+        println!("{} packages checked, {} damaged", reports.len(), damaged);
+    }
+
+    if reinstall {
+        checker.reinstall_damaged(&reports)?;
+    }
+
+    Ok(())
+}
+
+fn run_key_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos key <generate NAME EMAIL|list|export KEY FILE|import FILE|use KEY|sign FILE...> [--homedir DIR]";
+
+    // --homedir يسمح باستخدام حلقة مفاتيح مؤقتة بدلاً من /etc/xbitos/gnupg
+    let mut positional = Vec::new();
+    let mut homedir = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--homedir" {
+            homedir = iter.next().map(Path::new);
+        } else {
+            positional.push(arg.as_str());
+        }
+    }
+
+    let keys = match homedir {
+        Some(dir) => KeyManager::with_paths(dir, &dir.join("signing.json")),
+        None => KeyManager::new(),
+    };
+
+    match positional.as_slice() {
+        ["generate", name, email] => {
+            let fingerprint = keys.generate_key(name, email)?;
+            println!("{}", fingerprint);
+        }
+        ["list"] => {
+            let active = keys.get_config()?.map(|config| config.key_id);
+            for key in keys.list_keys()? {
+                let mark = if active.as_deref() == Some(key.fingerprint.as_str()) { "*" } else { " " };
+                let secret = if key.has_secret { "sec" } else { "pub" };
+                println!("{} {} {} {}", mark, secret, key.fingerprint, key.user_ids.join(", "));
+            }
+        }
+        ["export", key, file] => keys.export_public_key(key, Path::new(file))?,
+        ["import", file] => keys.import_key(Path::new(file))?,
+        ["use", key] => {
+            let config = keys.set_signing_key(key)?;
+            println!("Signing key: {}", config.key_id);
+        }
+        ["sign", files @ ..] if !files.is_empty() => {
+            let signer = keys
+                .configured_signer()?
+                .ok_or_else(|| anyhow::anyhow!("No signing key configured, run: xbitos key use KEY"))?;
+            for file in files {
+                println!("{}", signer.sign(Path::new(file))?.display());
+            }
+        }
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_aur_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos aur <diff|review|resolve|build> NAME [--aur-url URL]";
+
+    let mut positional = Vec::new();
+    let mut aur_url = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--aur-url" {
+            aur_url = iter.next();
+        } else {
+            positional.push(arg.as_str());
+        }
+    }
+
+    let mut builder = PackageBuilder::new();
+    if let Some(url) = aur_url {
+        let aur = AurClient::new(builder.get_aur_client().get_cache_path()).with_base_url(url);
+        builder = builder.with_aur(aur);
+    }
+    let aur = builder.get_aur_client();
+
+    match positional.as_slice() {
+        ["diff", name] => {
+            let base = aur.require_base(name)?;
+            aur.fetch(&base)?;
+            let diff = aur.review_diff(&base)?;
+            if diff.is_reviewed() {
+                println!("{} is already reviewed at {}", base, diff.head);
+            } else {
+                println!("{}", diff.diff);
+            }
+        }
+        ["review", name] => {
+            // المراجعة تسجل الالتزام الحالي الذي عرض فرقه بأمر diff
+            let base = aur.require_base(name)?;
+            let commit = aur.mark_reviewed(&base)?;
+            println!("{} reviewed at {}", base, commit);
+        }
+        ["resolve", name] => {
+            for package in aur.resolve(name)? {
+                let reviewed = aur.review_diff(&package.base)?.is_reviewed();
+                println!("{} {}", package.base, if reviewed { "" } else { "(needs review)" });
+            }
+        }
+        ["build", name] => {
+            for result in builder.build_aur_package(name)? {
+                for artifact in &result.artifacts {
+                    println!("{}", artifact.display());
+                }
+            }
+        }
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_recipe_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos recipe <list|validate|build NAME...|build --all|rebuild|bump NAME [VERSION]|repro NAME> [--recipes DIR]";
+
+    let mut positional = Vec::new();
+    let mut recipes_dir = None;
+    let mut all = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--recipes" => recipes_dir = iter.next().map(Path::new),
+            "--all" => all = true,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let store = match recipes_dir {
+        Some(dir) => RecipeStore::with_paths(dir, Path::new("/var/lib/xbitos/recipes.json")),
+        None => RecipeStore::new(),
+    };
+
+    let configs = match positional.as_slice() {
+        ["list"] => {
+            for config in store.load_all()? {
+                println!("{} {}-{}  {}", config.name, config.version, config.release, config.description);
+            }
+            return Ok(());
+        }
+        ["validate"] => {
+            let configs = store.load_all()?;
+            println!("{} recipes valid in {}", configs.len(), store.get_recipes_path().display());
+            return Ok(());
+        }
+        ["bump", name] => {
+            store.bump(name, None)?;
+            return Ok(());
+        }
+        ["bump", name, version] => {
+            store.bump(name, Some(version))?;
+            return Ok(());
+        }
+        ["repro", name] => {
+            let config = store.load(name)?;
+            let epoch = chrono::Local::now().timestamp();
+            for report in PackageBuilder::new().check_reproducible(&config, epoch)? {
+                println!("{}: {}", report.package, if report.reproducible { "reproducible" } else { "not reproducible" });
+            }
+            return Ok(());
+        }
+        ["build"] if all => store.load_all()?,
+        ["build", names @ ..] if !names.is_empty() => {
+            names.iter().map(|name| store.load(name)).collect::<Result<Vec<_>>>()?
+        }
+        ["rebuild"] => store.changed()?,
+        _ => return Err(anyhow::anyhow!(usage)),
+    };
+
+    if configs.is_empty() {
+        println!("Nothing to build");
+        return Ok(());
+    }
+
+    let report = PackageBuilder::new().build_batch(&configs)?;
+    for entry in &report.entries {
+        if matches!(entry.status, BatchStatus::Built) {
+            if let Some(config) = configs.iter().find(|c| c.name == entry.package) {
+                store.record_built(config)?;
+            }
+        }
+    }
+
+    println!("{}", report.summary());
+    if !report.is_success() {
+        return Err(anyhow::anyhow!("Some packages failed to build"));
+    }
+
+    Ok(())
+}
+
+fn run_lint_command(args: &[String]) -> Result<()> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let files: Vec<PathBuf> = args.iter().filter(|arg| !arg.starts_with("--")).map(PathBuf::from).collect();
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("Usage: xbitos lint PACKAGE_FILE... [--json]"));
+    }
+
+    let reports = PackageLinter::new().lint_all(&files)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            for finding in &report.findings {
+                let level = match finding.severity {
+                    Severity::Warning => "W",
+                    Severity::Error => "E",
+                };
+                let path = finding.path.as_deref().map(|p| format!(" {}", p)).unwrap_or_default();
+                println!("{} {}: {}{}: {}", report.package, level, finding.check, path, finding.message);
+            }
+        }
+    }
+
+    if reports.iter().any(|report| report.has_errors()) {
+        return Err(anyhow::anyhow!("Lint errors found"));
+    }
+
+    Ok(())
+}
+
+fn run_repro_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos repro <compare FIRST SECOND|status> [--json]";
+    let json = args.iter().any(|arg| arg == "--json");
+    let positional: Vec<&str> = args.iter().filter(|arg| !arg.starts_with("--")).map(|arg| arg.as_str()).collect();
+
+    match positional.as_slice() {
+        ["compare", first, second] => {
+            let differences = reproducible::compare_packages(Path::new(first), Path::new(second))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&differences)?);
+            } else {
+                for difference in &differences {
+                    let field = difference.field.as_deref().map(|f| format!(" [{}]", f)).unwrap_or_default();
+                    println!("{} {:?}{}", difference.path, difference.kind, field);
+                    println!("  - {}", difference.first.replace('\n', "\n  - "));
+                    println!("  + {}", difference.second.replace('\n', "\n  + "));
+                }
+                println!("{}", if differences.is_empty() { "identical" } else { "not reproducible" });
+            }
+        }
+        ["status"] => {
+            let reports = PackageBuilder::new().get_reproducibility_reports()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&reports)?);
+            } else {
+                for report in reports {
+                    let state = if report.reproducible { "reproducible" } else { "unreproducible" };
+                    println!("{} {} ({} differences, {})", report.package, state, report.differences.len(), report.checked_at);
+                }
+            }
+        }
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_firewall_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos firewall <render|check|apply> [--policy FILE] [--backend nftables|firewalld] [--root DIR]";
+
+    let mut positional = Vec::new();
+    let mut policy_path = None;
+    let mut backend = None;
+    let mut root = Path::new("/");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--policy" => policy_path = Some(iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?),
+            "--backend" => {
+                backend = match iter.next().map(|b| b.as_str()) {
+                    Some("nftables") => Some(FirewallBackend::Nftables),
+                    Some("firewalld") => Some(FirewallBackend::Firewalld),
+                    _ => return Err(anyhow::anyhow!(usage)),
+                }
+            }
+            // تطبيق السياسة على نظام مثبت في مجلد آخر
+            "--root" => root = iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let firewall = match policy_path {
+        Some(path) => FirewallManager::with_policy_path(path),
+        None => FirewallManager::new(),
+    };
+    let mut policy = firewall.load_policy()?;
+    if let Some(backend) = backend {
+        policy.backend = backend;
+    }
+
+    match positional.as_slice() {
+        ["render"] => match policy.backend {
+            FirewallBackend::Nftables => print!("{}", policy.render_nftables()?),
+            FirewallBackend::Firewalld => {
+                for (name, xml) in policy.render_firewalld()? {
+                    println!("# etc/firewalld/zones/{}.xml", name);
+                    print!("{}", xml);
+                }
+            }
+        },
+        ["check"] => {
+            policy.validate()?;
+            if policy.backend == FirewallBackend::Nftables {
+                check_nftables(&policy.render_nftables()?, root)?;
+            }
+            println!("policy is valid");
+        }
+        ["apply"] => firewall.apply(&policy, root)?,
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_apparmor_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos apparmor <list|templates|render NAME|install|mode NAME complain|enforce|create NAME TEMPLATE EXEC [KEY=VALUE...]> [--root DIR]";
+
+    let mut positional = Vec::new();
+    let mut root = Path::new("/");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--root" => root = iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let apparmor = AppArmorManager::new();
+
+    match positional.as_slice() {
+        ["list"] => {
+            let loaded = apparmor.loaded_profiles()?;
+            for profile in apparmor.profiles()? {
+                let state = loaded.get(&profile.name).map(|mode| mode.as_str()).unwrap_or("not loaded");
+                println!("{} {:?} [{}] ({})", profile.name, profile.mode, profile.template, state);
+            }
+        }
+        ["templates"] => {
+            for template in apparmor.template_names() {
+                println!("{}", template);
+            }
+        }
+        ["render", name] => print!("{}", apparmor.get_profile(name)?.render()?),
+        ["install"] => apparmor.install_profiles(root)?,
+        ["mode", name, mode] => {
+            let mode = match *mode {
+                "complain" => ProfileMode::Complain,
+                "enforce" => ProfileMode::Enforce,
+                _ => return Err(anyhow::anyhow!(usage)),
+            };
+            apparmor.set_mode(name, mode, root)?;
+        }
+        ["create", name, template, exec, variables @ ..] => {
+            let mut values = std::collections::BTreeMap::new();
+            values.insert("exec".to_string(), exec.to_string());
+            for variable in variables {
+                let (key, value) = variable.split_once('=').ok_or_else(|| anyhow::anyhow!(usage))?;
+                values.insert(key.to_string(), value.to_string());
+            }
+            apparmor.create_profile(name, template, values)?;
+        }
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_audit_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos audit [--json] [--root DIR]";
+
+    let mut json = false;
+    let mut root = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--root" => root = Some(iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?),
+            _ => return Err(anyhow::anyhow!(usage)),
+        }
+    }
+
+    let security = SecurityManager::new();
+    let report = match root {
+        Some(root) => security.audit_root(root),
+        None => security.audit(),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for finding in &report.findings {
+            let location = finding.location.as_deref().map(|l| format!(" ({})", l)).unwrap_or_default();
+            println!("[{:?}] {}: {}{}", finding.severity, finding.check, finding.message, location);
+        }
+        println!("{}", report.summary());
+    }
+
+    Ok(())
+}
+
+fn run_advisories_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos advisories [--refresh|--source FILE|URL] [--json]";
+
+    let mut refresh = false;
+    let mut json = false;
+    let mut source = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--refresh" => refresh = true,
+            "--json" => json = true,
+            // ملف مصدر محلي للأجهزة دون اتصال
+            "--source" => source = Some(iter.next().ok_or_else(|| anyhow::anyhow!(usage))?),
+            _ => return Err(anyhow::anyhow!(usage)),
+        }
+    }
+
+    // المصدر المحدد يقرأ كما هو وتظهر أخطاؤه، ولا يستبدل النسخة المحلية للنظام
+    let advisories = match source {
+        Some(_) if refresh => return Err(anyhow::anyhow!(usage)),
+        Some(source) => AdvisoryFeed::new().with_source(source).fetch()?,
+        None => AdvisoryFeed::new().load(refresh)?,
+    };
+    let affected = affected_packages(&advisories, &LocalDatabase::new()?);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&affected)?);
+    } else {
+        xbitos::system::gui::software_center::SoftwareCenter::new().show_advisories(&affected)?;
+    }
+
+    Ok(())
+}
+
+fn run_software_command() -> Result<()> {
+    let mut backend = xbitos::system::software::SoftwareCenter::new()?;
+    xbitos::system::gui::software_center::SoftwareCenter::new().run(&mut backend)
+}
+
+fn run_secureboot_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos secureboot <status|keys NAME|sign FILE [--output FILE]|verify FILE...|sign-all|hook|enroll> [--keys DIR] [--esp DIR]";
+
+    let mut positional = Vec::new();
+    let mut output = None;
+    let mut manager = SecureBootManager::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--output" => output = Some(Path::new(iter.next().ok_or_else(|| anyhow::anyhow!(usage))?)),
+            // مجلد مفاتيح مؤقت لتجربة التوقيع على ملفات عادية
+            "--keys" => manager = manager.with_keys_path(Path::new(iter.next().ok_or_else(|| anyhow::anyhow!(usage))?)),
+            "--esp" => {
+                let esp = Path::new(iter.next().ok_or_else(|| anyhow::anyhow!(usage))?);
+                manager = manager.with_esp_path(esp, esp);
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    match positional.as_slice() {
+        ["status"] => {
+            let status = manager.status();
+            let yes_no = |value: bool| if value { "yes" } else { "no" };
+            println!("EFI system:     {}", yes_no(status.efi));
+            println!("Secure Boot:    {}", yes_no(status.enabled));
+            println!("Setup mode:     {}", yes_no(status.setup_mode));
+            println!("xBitOS keys:    {}", yes_no(status.keys_present));
+        }
+        ["keys", name] => {
+            manager.generate_keys(name)?;
+            println!("{}", manager.enrollment_instructions());
+        }
+        ["sign", file] => {
+            let signed = manager.sign(Path::new(file), output)?;
+            let state = if signed.already_signed { "already signed" } else { "signed" };
+            println!("{} {}", signed.path.display(), state);
+        }
+        ["verify", files @ ..] if !files.is_empty() => {
+            let mut failed = false;
+            for file in files {
+                let valid = manager.verify(Path::new(file))?;
+                println!("{} {}", file, if valid { "valid" } else { "NOT signed with xBitOS db key" });
+                failed |= !valid;
+            }
+            if failed {
+                return Err(anyhow::anyhow!("Some files are not signed"));
+            }
+        }
+        ["sign-all"] => {
+            for signed in manager.sign_all()? {
+                let state = if signed.already_signed { "already signed" } else { "signed" };
+                println!("{} {}", signed.path.display(), state);
+            }
+        }
+        ["hook"] => manager.install_hook()?,
+        ["enroll"] => println!("{}", manager.enrollment_instructions()),
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn format_chain(chain: &[String]) -> String {
+    if chain.is_empty() {
+        // لا توجد حزمة مثبتة صراحة تحتاجها
+        return "orphan (installed as a dependency, no longer required)".to_string();
+    }
+    chain.join(" -> ")
+}
+
+fn print_tree(tree: &DependencyTree, depth: usize) {
+    println!("{}{}", "  ".repeat(depth), tree.name);
+    for child in &tree.children {
+        print_tree(child, depth + 1);
+    }
+}
+
+fn run_ssh_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos ssh <render|check|apply|authorize USER FILE|regenerate-host-keys> [--root DIR]";
+
+    let mut positional = Vec::new();
+    let mut root = Path::new("/");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--root" => root = iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let ssh = SshManager::with_root(root);
+    let config = ssh.load_config()?;
+
+    match positional.as_slice() {
+        ["render"] => print!("{}", config.render()),
+        ["check"] => {
+            validate_config(&root.join("etc/ssh/sshd_config"))?;
+            println!("sshd configuration is valid");
+        }
+        ["apply"] => ssh.apply(&config)?,
+        ["authorize", user, file] => {
+            let keys = std::fs::read_to_string(file)?;
+            let added = ssh.import_authorized_keys(user, &[keys])?;
+            println!("added {} keys for {}", added, user);
+        }
+        ["regenerate-host-keys"] => ssh.schedule_host_key_regeneration()?,
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_sudo_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos sudo <render|check|apply> [--policy FILE] [--root DIR]";
+
+    let mut positional = Vec::new();
+    let mut policy_path = None;
+    let mut root = Path::new("/");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--policy" => policy_path = Some(iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?),
+            "--root" => root = iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let mut sudo = SudoManager::with_root(root);
+    if let Some(path) = policy_path {
+        sudo = sudo.with_policy_path(path);
+    }
+    let policy = sudo.load_policy()?;
+
+    match positional.as_slice() {
+        ["render"] => {
+            let sudoers = policy.render_sudoers()?;
+            let polkit = policy.render_polkit()?;
+            println!("# etc/sudoers.d/10-xbitos");
+            print!("{}", sudoers);
+            println!("# etc/polkit-1/rules.d/49-xbitos-admin.rules");
+            print!("{}", polkit);
+        }
+        ["check"] => {
+            policy.validate()?;
+            check_sudoers(&root.join("etc/sudoers"))?;
+            println!("sudo policy is valid");
+        }
+        ["apply"] => sudo.apply(&policy)?,
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
+fn run_kernel_profile_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: xbitos kernel-profile <show|list|render NAME|apply NAME> [--root DIR]";
+
+    let mut positional = Vec::new();
+    let mut root = Path::new("/");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--root" => root = iter.next().map(Path::new).ok_or_else(|| anyhow::anyhow!(usage))?,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let hardening = HardeningManager::with_root(root);
+
+    match positional.as_slice() {
+        ["show"] => println!("{}", hardening.current_profile()?.name()),
+        ["list"] => {
+            let current = hardening.current_profile()?;
+            for profile in KernelProfile::ALL {
+                let marker = if profile == current { "*" } else { " " };
+                println!("{} {}", marker, profile.name());
+            }
+        }
+        ["render", name] => {
+            let profile = KernelProfile::from_name(name)?;
+            println!("# kernel command line");
+            println!("{}", profile.cmdline().join(" "));
+            println!("# etc/sysctl.d/90-xbitos-hardening.conf");
+            print!("{}", profile.render_sysctl());
+            println!("# etc/modprobe.d/xbitos-blacklist.conf");
+            print!("{}", profile.render_modprobe());
+        }
+        ["apply", name] => hardening.apply(KernelProfile::from_name(name)?)?,
+        _ => return Err(anyhow::anyhow!(usage)),
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() {
     env_logger::init();
-    
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // أوامر الاستعلام لا تحتاج إلى إعداد النظام
+    if let Some(command) = args.first() {
+        let result = match command.as_str() {
+            "package" => run_package_command(&args[1..]),
+            "key" => run_key_command(&args[1..]),
+            "aur" => run_aur_command(&args[1..]),
+            "repro" => run_repro_command(&args[1..]),
+            "recipe" => run_recipe_command(&args[1..]),
+            "lint" => run_lint_command(&args[1..]),
+            "firewall" => run_firewall_command(&args[1..]),
+            "apparmor" => run_apparmor_command(&args[1..]),
+            "audit" => run_audit_command(&args[1..]),
+            "advisories" => run_advisories_command(&args[1..]),
+            "update" => UpdateManager::new().update_system(),
+            "software" => run_software_command(),
+            "secureboot" => run_secureboot_command(&args[1..]),
+            "ssh" => run_ssh_command(&args[1..]),
+            "sudo" => run_sudo_command(&args[1..]),
+            "kernel-profile" => run_kernel_profile_command(&args[1..]),
+            _ => Err(anyhow::anyhow!("Unknown command: {}", command)),
+        };
+
+        if let Err(e) = result {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     info!("Starting xBitOS setup...");
     
     if let Err(e) = setup_system().await {
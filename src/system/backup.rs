@@ -1,6 +1,5 @@
 use anyhow::Result;
 use chrono::Local;
-use log::{info, error};
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
@@ -40,7 +39,7 @@ impl BackupManager {
         let backup_name = format!("backup_{}", date);
 
         // إنشاء نسخة احتياطية للنظام
-        Command::new("borg")
+        let status = Command::new("borg")
             .args([
                 "create",
                 &format!("{}::{}", self.backup_dir.display(), backup_name),
@@ -55,17 +54,25 @@ impl BackupManager {
             ])
             .status()?;
 
+        if !status.success() {
+            return Err(anyhow::anyhow!("borg create failed for {}", backup_name));
+        }
+
         Ok(())
     }
 
     pub fn restore_backup(&self, backup_name: &str) -> Result<()> {
-        Command::new("borg")
+        let status = Command::new("borg")
             .args([
                 "extract",
                 &format!("{}::{}", self.backup_dir.display(), backup_name),
             ])
             .status()?;
 
+        if !status.success() {
+            return Err(anyhow::anyhow!("borg extract failed for {}", backup_name));
+        }
+
         Ok(())
     }
 
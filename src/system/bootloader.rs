@@ -1,20 +1,28 @@
 use anyhow::Result;
 use log::info;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 pub struct BootManager {
     esp_path: PathBuf,
+    kernel: crate::system::kernel::KernelManager,
 }
 
 impl BootManager {
     pub fn new() -> Self {
         Self {
             esp_path: PathBuf::from("/boot/efi"),
+            kernel: crate::system::kernel::KernelManager::new(),
         }
     }
 
+    // المثبت يختار ملف النواة قبل أن يوجد في النظام الجديد
+    pub fn with_kernel_profile(mut self, profile: crate::system::security::hardening::KernelProfile) -> Self {
+        self.kernel = self.kernel.with_profile(profile);
+        self
+    }
+
     pub fn setup_bootloader(&self) -> Result<()> {
         info!("Setting up bootloader...");
 
@@ -35,6 +43,9 @@ impl BootManager {
         // إعداد إدخالات الإقلاع
         self.configure_boot_entries()?;
 
+        // توقيع ملفات الإقلاع إذا أنشئت مفاتيح Secure Boot
+        self.setup_secure_boot()?;
+
         Ok(())
     }
 
@@ -52,6 +63,22 @@ impl BootManager {
         Ok(())
     }
 
+    fn setup_secure_boot(&self) -> Result<()> {
+        let secure_boot = crate::system::security::secure_boot::SecureBootManager::new()
+            .with_esp_path(&self.esp_path, Path::new("/boot"));
+        if !secure_boot.has_keys() {
+            return Ok(());
+        }
+
+        let pkg_manager = crate::system::package_manager::PackageManager::new();
+        pkg_manager.install_packages(&["sbsigntools"])?;
+
+        secure_boot.sign_all()?;
+        secure_boot.install_hook()?;
+
+        Ok(())
+    }
+
     fn configure_boot_entries(&self) -> Result<()> {
         let loader_conf = r#"
 default  xbitos.conf
@@ -62,8 +89,7 @@ editor   no
         fs::write(self.esp_path.join("loader/loader.conf"), loader_conf)?;
 
         // الحصول على معلمات النواة
-        let kernel_manager = crate::system::kernel::KernelManager::new();
-        let kernel_params = kernel_manager.get_kernel_parameters();
+        let kernel_params = self.kernel.get_kernel_parameters();
 
         let entry_content = format!(r#"
 title   xBitOS
@@ -96,4 +122,42 @@ options {}
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    // إضافة معلمة للنواة أو استبدال قيمتها في كل إدخالات الإقلاع الموجودة
+    pub fn set_kernel_parameter(&self, parameter: &str) -> Result<()> {
+        let entries_path = self.esp_path.join("loader/entries");
+        if !entries_path.exists() {
+            return Ok(());
+        }
+
+        let key = parameter.split('=').next().unwrap_or(parameter);
+
+        for entry in fs::read_dir(&entries_path)?.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "conf") {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path)?;
+            let updated: Vec<String> = content
+                .lines()
+                .map(|line| match line.strip_prefix("options") {
+                    Some(options) => {
+                        let mut parameters: Vec<&str> = options
+                            .split_whitespace()
+                            .filter(|p| p.split('=').next() != Some(key))
+                            .collect();
+                        parameters.push(parameter);
+                        format!("options {}", parameters.join(" "))
+                    }
+                    None => line.to_string(),
+                })
+                .collect();
+
+            fs::write(&path, updated.join("\n") + "\n")?;
+            info!("Set {} in {}", parameter, path.display());
+        }
+
+        Ok(())
+    }
+}
@@ -3,6 +3,8 @@ use log::info;
 use std::process::Command;
 use std::path::PathBuf;
 use std::fs;
+use crate::system::packaging::repo_db::{is_package_file, RepoDatabase};
+use crate::system::packaging::signing::KeyManager;
 
 pub struct SystemBuilder {
     build_path: PathBuf,
@@ -121,12 +123,21 @@ neofetch
         fs::write(self.build_path.join("packages.txt"), core_packages)?;
 
         // إنشاء مستودع الحزم المحلي
-        Command::new("repo-add")
-            .args([
-                self.packages_path.join("xbitos.db.tar.gz").to_str().unwrap(),
-                self.packages_path.join("*.pkg.tar.zst").to_str().unwrap(),
-            ])
-            .status()?;
+        let mut database = RepoDatabase::open(&self.packages_path, "xbitos")?
+            .sign_with(KeyManager::new().configured_signer()?);
+        let packages: Vec<PathBuf> = fs::read_dir(&self.packages_path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_package_file(&path.file_name().unwrap_or_default().to_string_lossy()))
+            .collect();
+
+        for package in packages {
+            // قد تكون حذفت كإصدار قديم أثناء إضافة إصدار أحدث
+            if package.exists() {
+                database.add_if_newer(&package)?;
+            }
+        }
+        database.write()?;
 
         Ok(())
     }
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AppCollection {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub name_ar: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub packages: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CollectionFile {
+    #[serde(default)]
+    collections: Vec<AppCollection>,
+}
+
+pub struct CollectionLoader {
+    system_file: PathBuf,
+    override_dir: PathBuf,
+}
+
+impl CollectionLoader {
+    pub fn new() -> Self {
+        Self {
+            system_file: PathBuf::from("/usr/share/xbitos/collections.json"),
+            override_dir: PathBuf::from("/etc/xbitos/collections.d"),
+        }
+    }
+
+    pub fn with_paths(system_file: &Path, override_dir: &Path) -> Self {
+        Self {
+            system_file: system_file.to_path_buf(),
+            override_dir: override_dir.to_path_buf(),
+        }
+    }
+
+    pub fn load(&self) -> Result<Vec<AppCollection>> {
+        let mut collections = Vec::new();
+
+        if self.system_file.exists() {
+            collections = read_collection_file(&self.system_file)?;
+        } else {
+            warn!("Collections file not found: {}", self.system_file.display());
+        }
+
+        if self.override_dir.is_dir() {
+            let mut overrides: Vec<PathBuf> = fs::read_dir(&self.override_dir)?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext == "json" || ext == "toml")
+                })
+                .collect();
+
+            // ترتيب أبجدي حتى يكون للملفات اللاحقة الأولوية
+            overrides.sort();
+
+            for path in overrides {
+                // ملف خاطئ من المسؤول يتجاهل وحده وتبقى مجموعات التوزيعة
+                let site_collections = match read_collection_file(&path) {
+                    Ok(site_collections) => site_collections,
+                    Err(e) => {
+                        warn!("Skipping collections override: {:#}", e);
+                        continue;
+                    }
+                };
+
+                for collection in site_collections {
+                    info!("Loading site collection {} from {}", collection.id, path.display());
+                    match collections.iter_mut().find(|c| c.id == collection.id) {
+                        Some(existing) => *existing = collection,
+                        None => collections.push(collection),
+                    }
+                }
+            }
+        }
+
+        // المجموعات الفارغة تستخدم لإخفاء مجموعة من ملف التوزيعة
+        collections.retain(|c| !c.packages.is_empty());
+        Ok(collections)
+    }
+}
+
+fn read_collection_file(path: &Path) -> Result<Vec<AppCollection>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let file: CollectionFile = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&content)
+            .with_context(|| format!("Invalid collections file: {}", path.display()))?
+    } else {
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid collections file: {}", path.display()))?
+    };
+
+    Ok(file.collections)
+}
@@ -0,0 +1,387 @@
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use zbus::message::Header;
+use zbus::{fdo, interface, Connection};
+use zvariant::{Type, Value};
+
+pub const BUS_NAME: &str = "org.xbitos.Daemon1";
+pub const OBJECT_PATH: &str = "/org/xbitos/Daemon1";
+
+// معرفات إجراءات polkit لكل عملية
+pub const ACTION_INSTALL_PACKAGES: &str = "org.xbitos.daemon.install-packages";
+pub const ACTION_REMOVE_PACKAGES: &str = "org.xbitos.daemon.remove-packages";
+pub const ACTION_UPDATE_SYSTEM: &str = "org.xbitos.daemon.update-system";
+pub const ACTION_MANAGE_SERVICES: &str = "org.xbitos.daemon.manage-services";
+pub const ACTION_CREATE_BACKUP: &str = "org.xbitos.daemon.create-backup";
+pub const ACTION_RESTORE_BACKUP: &str = "org.xbitos.daemon.restore-backup";
+
+// الناقل الخاص الذي يسمح عليه الاختبار بتعطيل polkit، ويجب أن يطابق --address حرفياً
+pub const TEST_BUS_ENV: &str = "XBITOS_DAEMON_TEST_BUS";
+
+pub enum BusAddress {
+    System,
+    Session,
+    Custom(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Authorization {
+    Polkit,
+    // للاختبار على ناقل خاص فقط حيث لا يوجد polkit
+    AllowAll,
+}
+
+#[derive(Serialize, Type)]
+struct Subject<'a> {
+    kind: &'a str,
+    details: HashMap<&'a str, Value<'a>>,
+}
+
+#[derive(Deserialize, Type)]
+struct AuthorizationResult {
+    is_authorized: bool,
+    _is_challenge: bool,
+    _details: HashMap<String, String>,
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait PolkitAuthority {
+    fn check_authorization(
+        &self,
+        subject: &Subject<'_>,
+        action_id: &str,
+        details: HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<AuthorizationResult>;
+}
+
+#[zbus::proxy(
+    interface = "org.xbitos.Daemon1",
+    default_service = "org.xbitos.Daemon1",
+    default_path = "/org/xbitos/Daemon1"
+)]
+pub trait XbitosDaemon {
+    fn install_packages(&self, packages: Vec<String>) -> zbus::Result<()>;
+    fn remove_packages(&self, packages: Vec<String>) -> zbus::Result<()>;
+    fn update_system(&self) -> zbus::Result<()>;
+    fn enable_service(&self, service: &str) -> zbus::Result<()>;
+    fn start_service(&self, service: &str) -> zbus::Result<()>;
+    fn create_backup(&self) -> zbus::Result<()>;
+    fn restore_backup(&self, backup_name: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn version(&self) -> zbus::Result<String>;
+}
+
+pub struct DaemonService {
+    authorization: Authorization,
+}
+
+impl DaemonService {
+    pub fn new(authorization: Authorization) -> Self {
+        Self { authorization }
+    }
+
+    async fn authorize(&self, connection: &Connection, header: &Header<'_>, action_id: &str) -> fdo::Result<()> {
+        if self.authorization == Authorization::AllowAll {
+            return Ok(());
+        }
+
+        let sender = header
+            .sender()
+            .ok_or_else(|| fdo::Error::AccessDenied("Missing sender".into()))?;
+
+        let subject = Subject {
+            kind: "system-bus-name",
+            details: HashMap::from([("name", Value::from(sender.as_str()))]),
+        };
+
+        let authority = PolkitAuthorityProxy::new(connection).await?;
+
+        // السماح لـ polkit بطلب كلمة المرور من المستخدم عند الحاجة
+        let result = authority
+            .check_authorization(&subject, action_id, HashMap::new(), 1, "")
+            .await?;
+
+        if !result.is_authorized {
+            warn!("Denied {} for {}", action_id, sender);
+            return Err(fdo::Error::AccessDenied(format!("Not authorized: {}", action_id)));
+        }
+
+        info!("Authorized {} for {}", action_id, sender);
+        Ok(())
+    }
+}
+
+#[interface(name = "org.xbitos.Daemon1")]
+impl DaemonService {
+    async fn install_packages(
+        &self,
+        packages: Vec<String>,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        self.authorize(connection, &header, ACTION_INSTALL_PACKAGES).await?;
+        validate_names(&packages)?;
+
+        run_blocking(move || {
+            let names: Vec<&str> = packages.iter().map(|p| p.as_str()).collect();
+            crate::system::package_manager::PackageManager::new().install_packages(&names)
+        })
+        .await
+    }
+
+    async fn remove_packages(
+        &self,
+        packages: Vec<String>,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        self.authorize(connection, &header, ACTION_REMOVE_PACKAGES).await?;
+        validate_names(&packages)?;
+
+        run_blocking(move || {
+            let names: Vec<&str> = packages.iter().map(|p| p.as_str()).collect();
+            crate::system::package_manager::PackageManager::new().remove_packages(&names)
+        })
+        .await
+    }
+
+    async fn update_system(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        self.authorize(connection, &header, ACTION_UPDATE_SYSTEM).await?;
+
+        run_blocking(|| crate::system::updates::UpdateManager::new().update_system()).await
+    }
+
+    async fn enable_service(
+        &self,
+        service: String,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        self.authorize(connection, &header, ACTION_MANAGE_SERVICES).await?;
+        validate_names(std::slice::from_ref(&service))?;
+
+        run_blocking(move || crate::system::services::ServiceManager::new().enable_service(&service)).await
+    }
+
+    async fn start_service(
+        &self,
+        service: String,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        self.authorize(connection, &header, ACTION_MANAGE_SERVICES).await?;
+        validate_names(std::slice::from_ref(&service))?;
+
+        run_blocking(move || crate::system::services::ServiceManager::new().start_service(&service)).await
+    }
+
+    async fn create_backup(
+        &self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        self.authorize(connection, &header, ACTION_CREATE_BACKUP).await?;
+
+        run_blocking(|| crate::system::backup::BackupManager::new().create_backup()).await
+    }
+
+    async fn restore_backup(
+        &self,
+        backup_name: String,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        self.authorize(connection, &header, ACTION_RESTORE_BACKUP).await?;
+        validate_names(std::slice::from_ref(&backup_name))?;
+
+        run_blocking(move || crate::system::backup::BackupManager::new().restore_backup(&backup_name)).await
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+}
+
+fn validate_names(names: &[String]) -> fdo::Result<()> {
+    for name in names {
+        // منع تمرير خيارات إلى pacman أو systemctl عبر أسماء تبدأ بشرطة
+        // والأسماء . و .. تشير إلى مجلدات وليست أسماء نسخ أو حزم
+        let valid = !name.is_empty()
+            && !name.starts_with('-')
+            && name != "."
+            && name != ".."
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || "@._+-:".contains(c));
+
+        if !valid {
+            return Err(fdo::Error::InvalidArgs(format!("Invalid name: {}", name)));
+        }
+    }
+    Ok(())
+}
+
+async fn run_blocking<F>(operation: F) -> fdo::Result<()>
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    tokio::task::spawn_blocking(operation)
+        .await
+        .map_err(|e| fdo::Error::Failed(e.to_string()))?
+        .map_err(|e| fdo::Error::Failed(e.to_string()))
+}
+
+fn connection_builder(address: &BusAddress) -> Result<zbus::connection::Builder<'static>> {
+    let builder = match address {
+        BusAddress::System => zbus::connection::Builder::system()?,
+        BusAddress::Session => zbus::connection::Builder::session()?,
+        BusAddress::Custom(address) => zbus::connection::Builder::address(address.as_str())?,
+    };
+    Ok(builder)
+}
+
+pub async fn connect(address: &BusAddress) -> Result<Connection> {
+    Ok(connection_builder(address)?.build().await?)
+}
+
+pub async fn serve(address: &BusAddress, authorization: Authorization) -> Result<Connection> {
+    // أي ناقل آخر قد يكون ناقل النظام بعنوان مختلف، فيصبح الجذر متاحاً لكل عميل
+    if authorization == Authorization::AllowAll {
+        let test_bus = std::env::var(TEST_BUS_ENV).ok();
+        let allowed = match address {
+            BusAddress::Custom(address) => test_bus.as_deref() == Some(address.as_str()),
+            BusAddress::System | BusAddress::Session => false,
+        };
+        if !allowed {
+            return Err(anyhow::anyhow!(
+                "Refusing to disable polkit outside a private test bus (set {} to the --address value)",
+                TEST_BUS_ENV
+            ));
+        }
+    }
+
+    let connection = connection_builder(address)?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, DaemonService::new(authorization))?
+        .build()
+        .await?;
+
+    info!("xbitos-daemon listening on {}", BUS_NAME);
+    Ok(connection)
+}
+
+pub struct DaemonInstaller {
+    dbus_config_path: PathBuf,
+    dbus_service_path: PathBuf,
+    polkit_actions_path: PathBuf,
+    systemd_path: PathBuf,
+}
+
+impl DaemonInstaller {
+    pub fn new() -> Self {
+        Self {
+            dbus_config_path: PathBuf::from("/usr/share/dbus-1/system.d"),
+            dbus_service_path: PathBuf::from("/usr/share/dbus-1/system-services"),
+            polkit_actions_path: PathBuf::from("/usr/share/polkit-1/actions"),
+            systemd_path: PathBuf::from("/etc/systemd/system"),
+        }
+    }
+
+    pub fn install(&self) -> Result<()> {
+        info!("Installing xbitos-daemon configuration...");
+
+        fs::create_dir_all(&self.dbus_config_path)?;
+        fs::create_dir_all(&self.dbus_service_path)?;
+        fs::create_dir_all(&self.polkit_actions_path)?;
+        fs::create_dir_all(&self.systemd_path)?;
+
+        // سياسة الناقل: الجذر فقط يمتلك الاسم، والجميع يستطيع الاستدعاء
+        let bus_policy = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <policy user="root">
+    <allow own="org.xbitos.Daemon1"/>
+  </policy>
+  <policy context="default">
+    <allow send_destination="org.xbitos.Daemon1"/>
+  </policy>
+</busconfig>
+"#;
+
+        let bus_service = r#"[D-BUS Service]
+Name=org.xbitos.Daemon1
+Exec=/usr/bin/xbitos-daemon
+User=root
+SystemdService=xbitos-daemon.service
+"#;
+
+        let systemd_unit = r#"[Unit]
+Description=xBitOS Privileged Helper
+
+[Service]
+Type=dbus
+BusName=org.xbitos.Daemon1
+ExecStart=/usr/bin/xbitos-daemon
+"#;
+
+        fs::write(self.dbus_config_path.join("org.xbitos.Daemon1.conf"), bus_policy)?;
+        fs::write(self.dbus_service_path.join("org.xbitos.Daemon1.service"), bus_service)?;
+        fs::write(self.systemd_path.join("xbitos-daemon.service"), systemd_unit)?;
+        fs::write(
+            self.polkit_actions_path.join("org.xbitos.daemon.policy"),
+            polkit_policy(),
+        )?;
+
+        Ok(())
+    }
+}
+
+fn polkit_policy() -> String {
+    let actions = [
+        (ACTION_INSTALL_PACKAGES, "Install software", "auth_admin_keep"),
+        (ACTION_REMOVE_PACKAGES, "Remove software", "auth_admin_keep"),
+        (ACTION_UPDATE_SYSTEM, "Update the system", "auth_admin_keep"),
+        (ACTION_MANAGE_SERVICES, "Manage system services", "auth_admin"),
+        (ACTION_CREATE_BACKUP, "Create a system backup", "auth_admin_keep"),
+        (ACTION_RESTORE_BACKUP, "Restore a system backup", "auth_admin"),
+    ];
+
+    let mut policy = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE policyconfig PUBLIC "-//freedesktop//DTD PolicyKit Policy Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/PolicyKit/1/policyconfig.dtd">
+<policyconfig>
+  <vendor>xBitOS</vendor>
+  <vendor_url>https://xbitos.org</vendor_url>
+"#);
+
+    for (id, description, active) in actions {
+        policy.push_str(&format!(r#"
+  <action id="{}">
+    <description>{}</description>
+    <message>Authentication is required to {}</message>
+    <defaults>
+      <allow_any>auth_admin</allow_any>
+      <allow_inactive>auth_admin</allow_inactive>
+      <allow_active>{}</allow_active>
+    </defaults>
+  </action>
+"#, id, description, description.to_lowercase(), active));
+    }
+
+    policy.push_str("</policyconfig>\n");
+    policy
+}
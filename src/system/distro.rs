@@ -2,6 +2,7 @@ use anyhow::Result;
 use log::info;
 use std::path::PathBuf;
 use std::fs;
+use crate::system::packaging::signing::KeyManager;
 
 pub struct DistroManager {
     config_path: PathBuf,
@@ -39,6 +40,9 @@ impl DistroManager {
         fs::create_dir_all(&self.config_path)?;
         fs::create_dir_all(&self.repo_path)?;
         fs::create_dir_all("/etc/xbitos/hooks")?;
+        fs::create_dir_all("/etc/xbitos/collections.d")?;
+        fs::create_dir_all("/etc/xbitos/recipes/xbitos-desktop")?;
+        fs::create_dir_all("/usr/share/xbitos")?;
         fs::create_dir_all("/var/lib/xbitos/cache")?;
         fs::create_dir_all("/var/log/xbitos")?;
 
@@ -46,8 +50,18 @@ impl DistroManager {
     }
 
     fn setup_repository(&self) -> Result<()> {
+        // المستودع الموقع يتطلب توقيعاً صالحاً، وبدون مفتاح يبقى اختيارياً
+        let keys = KeyManager::new();
+        let sig_level = match keys.get_config()? {
+            Some(config) => {
+                keys.trust_in_pacman(&config.key_id)?;
+                "Required"
+            }
+            None => "Optional TrustAll",
+        };
+
         // إعداد ملف تكوين pacman للمستودع المحلي
-        let repo_conf = r#"
+        let repo_conf = format!(r#"
 [options]
 HoldPkg     = pacman glibc
 Architecture = auto
@@ -61,7 +75,7 @@ SigLevel    = Required DatabaseOptional
 LocalFileSigLevel = Optional
 
 [xbitos]
-SigLevel = Optional TrustAll
+SigLevel = {}
 Server = file:///var/lib/xbitos/repo
 
 [core]
@@ -72,7 +86,7 @@ Include = /etc/pacman.d/mirrorlist
 
 [community]
 Include = /etc/pacman.d/mirrorlist
-"#;
+"#, sig_level);
 
         fs::write("/etc/pacman.conf", repo_conf)?;
 
@@ -107,6 +121,23 @@ DEFAULT_ICON_THEME="papirus"
 
         fs::write(self.config_path.join("xbitos.conf"), distro_conf)?;
 
+        // مجموعات التطبيقات المختارة لمركز البرمجيات
+        fs::write(
+            "/usr/share/xbitos/collections.json",
+            include_str!("../../config/collections.json"),
+        )?;
+
+        // وصفات الحزم الخاصة بالتوزيعة، دون استبدال نسخة عدلها المسؤول
+        let recipes = [
+            ("/etc/xbitos/recipes/xbitos-desktop.toml", include_str!("../../config/recipes/xbitos-desktop.toml")),
+            ("/etc/xbitos/recipes/xbitos-desktop/hyprland.conf", include_str!("../../config/recipes/xbitos-desktop/hyprland.conf")),
+        ];
+        for (path, content) in recipes {
+            if !std::path::Path::new(path).exists() {
+                fs::write(path, content)?;
+            }
+        }
+
         Ok(())
     }
 } 
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod software_center;
@@ -1,15 +1,15 @@
 use anyhow::Result;
-use std::process::Command;
+use std::io::{self, Write};
+use crate::system::collections::AppCollection;
+use crate::system::security::advisories::AffectedPackage;
+use crate::system::software::SoftwareCenter as SoftwareBackend;
 
-pub struct SoftwareCenter {
-    package_manager: crate::system::package_manager::PackageManager,
-}
+// واجهة العرض فقط، والعمليات تنفذ عبر SoftwareBackend
+pub struct SoftwareCenter;
 
 impl SoftwareCenter {
     pub fn new() -> Self {
-        Self {
-            package_manager: crate::system::package_manager::PackageManager::new(),
-        }
+        Self
     }
 
     pub fn show(&self) -> Result<()> {
@@ -17,9 +17,114 @@ impl SoftwareCenter {
         println!("1. Search packages");
         println!("2. Install package");
         println!("3. Remove package");
-        println!("4. Update system");
-        println!("5. Exit");
+        println!("4. Browse xBitOS picks");
+        println!("5. Update system");
+        println!("6. Security advisories");
+        println!("7. Exit");
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    // حلقة القائمة، كل خيار ينفذ عبر الواجهة الخلفية
+    pub fn run(&self, backend: &mut SoftwareBackend) -> Result<()> {
+        loop {
+            self.show()?;
+            let choice = prompt("> ")?;
+
+            let result = match choice.as_str() {
+                "1" => {
+                    let query = prompt("Search: ")?;
+                    for package in backend.search_packages(&query) {
+                        println!("{} {} - {}", package.name, package.version, package.description);
+                    }
+                    Ok(())
+                }
+                "2" => backend.install_package(&prompt("Package: ")?),
+                "3" => backend.remove_package(&prompt("Package: ")?),
+                "4" => {
+                    self.show_collections(backend.get_collections())?;
+                    let id = prompt("Install (number, empty to skip): ")?
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|number| number.checked_sub(1))
+                        .and_then(|index| backend.get_collections().get(index))
+                        .map(|collection| collection.id.clone());
+                    match id {
+                        Some(id) => self.install_collection(backend, &id),
+                        None => Ok(()),
+                    }
+                }
+                "5" => crate::system::updates::UpdateManager::new().update_system(),
+                "6" => backend
+                    .get_security_advisories(true)
+                    .and_then(|affected| self.show_advisories(&affected)),
+                "7" | "" => return Ok(()),
+                _ => {
+                    println!("Unknown choice: {}", choice);
+                    Ok(())
+                }
+            };
+
+            // فشل عملية واحدة لا يغلق مركز البرامج
+            if let Err(e) = result {
+                println!("Error: {:#}", e);
+            }
+        }
+    }
+
+    pub fn show_collections(&self, collections: &[AppCollection]) -> Result<()> {
+        println!("xBitOS Picks");
+
+        for (index, collection) in collections.iter().enumerate() {
+            match &collection.name_ar {
+                Some(name_ar) => println!("{}. {} ({})", index + 1, collection.name, name_ar),
+                None => println!("{}. {}", index + 1, collection.name),
+            }
+            println!("   {}", collection.description);
+            println!("   {}", collection.packages.join(", "));
+        }
+
+        Ok(())
+    }
+
+    pub fn show_advisories(&self, affected: &[AffectedPackage]) -> Result<()> {
+        println!("Security Advisories");
+
+        if affected.is_empty() {
+            println!("No installed package is affected by a known vulnerability");
+            return Ok(());
+        }
+
+        for package in affected {
+            println!("[{:?}] {} {} - {} ({})", package.severity, package.package, package.installed, package.kind, package.group);
+            if !package.issues.is_empty() {
+                println!("   {}", package.issues.join(", "));
+            }
+            match &package.fixed {
+                Some(fixed) => println!("   Update to {} to fix", fixed),
+                None => println!("   No fixed version yet"),
+            }
+        }
+
+        let fixable = affected.iter().filter(|p| p.has_fix()).count();
+        println!("{} affected packages, {} fixed by updating", affected.len(), fixable);
+
+        Ok(())
+    }
+
+    pub fn install_collection(&self, backend: &mut SoftwareBackend, collection_id: &str) -> Result<()> {
+        // تثبيت المجموعة كاملة بنقرة واحدة مع تسجيلها في سجل المعاملات
+        backend.install_collection(collection_id)?;
+        println!("Collection installed: {}", collection_id);
+        Ok(())
+    }
+}
+
+fn prompt(label: &str) -> Result<String> {
+    print!("{}", label);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
@@ -4,18 +4,46 @@ use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
 use serde::{Serialize, Deserialize};
+use crate::system::security::hardening::{HardeningManager, KernelProfile};
+use crate::system::security::password::{set_password_hash, PasswordHash, Secret};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct InstallConfig {
     hostname: String,
     username: String,
-    password: String,
+    // كلمة المرور النصية لا تحفظ أبداً، والمحفوظ هو التجزئة فقط
+    #[serde(default, skip_serializing)]
+    password: Option<Secret>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password_hash: Option<PasswordHash>,
     timezone: String,
     locale: String,
     keyboard: String,
     disk: String,
     use_encryption: bool,
     desktop_environment: String,
+    #[serde(default)]
+    ssh_authorized_keys: Vec<String>,
+    #[serde(default)]
+    kernel_profile: KernelProfile,
+}
+
+impl InstallConfig {
+    // تحويل كلمة المرور النصية إلى تجزئة، مثلاً قبل حفظ الإعدادات
+    pub fn hash_password(&mut self) -> Result<()> {
+        if let Some(password) = self.password.take() {
+            self.password_hash = Some(PasswordHash::from_secret(&password)?);
+        }
+        Ok(())
+    }
+
+    fn password_hash(&self) -> Result<PasswordHash> {
+        match (&self.password_hash, &self.password) {
+            (Some(hash), _) => Ok(hash.clone()),
+            (None, Some(password)) => PasswordHash::from_secret(password),
+            (None, None) => Err(anyhow::anyhow!("No password or password_hash set for {}", self.username)),
+        }
+    }
 }
 
 pub struct SystemInstaller {
@@ -49,6 +77,12 @@ impl SystemInstaller {
         // إعداد المستخدم
         self.setup_user()?;
 
+        // إعداد خادم SSH
+        self.setup_ssh()?;
+
+        // إعداد جدار الحماية
+        self.setup_firewall()?;
+
         // إعداد برنامج الإقلاع
         self.setup_bootloader()?;
 
@@ -117,6 +151,9 @@ impl SystemInstaller {
             &format!("echo KEYMAP={} > /etc/vconsole.conf", self.config.keyboard),
         ])?;
 
+        // إعدادات sysctl وقائمة الوحدات المحظورة حسب ملف النواة
+        HardeningManager::with_root(&self.mount_point).apply(self.config.kernel_profile)?;
+
         Ok(())
     }
 
@@ -138,12 +175,59 @@ impl SystemInstaller {
     fn setup_user(&self) -> Result<()> {
         info!("Setting up user account...");
 
+        // التجزئة قبل أي تغيير حتى لا يبقى مستخدم دون كلمة مرور
+        let password_hash = self.config.password_hash()?;
+
         // إنشاء المستخدم
         self.chroot_execute(&[
             &format!("useradd -m -G wheel -s /bin/bash {}", self.config.username),
-            &format!("echo '{}:{}' | chpasswd", self.config.username, self.config.password),
-            "echo '%wheel ALL=(ALL) ALL' > /etc/sudoers.d/wheel",
         ])?;
+        set_password_hash(&self.mount_point, &self.config.username, &password_hash)?;
+
+        // صلاحيات مجموعة wheel في sudo و polkit
+        crate::system::security::sudo::SudoManager::with_root(&self.mount_point).setup()?;
+
+        Ok(())
+    }
+
+    fn setup_ssh(&self) -> Result<()> {
+        info!("Configuring SSH server...");
+
+        let status = Command::new("pacstrap")
+            .args([&self.mount_point.to_string_lossy(), "openssh"])
+            .status()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to install openssh"));
+        }
+
+        let ssh = crate::system::security::ssh::SshManager::with_root(&self.mount_point);
+        ssh.setup()?;
+
+        if !self.config.ssh_authorized_keys.is_empty() {
+            ssh.import_authorized_keys(&self.config.username, &self.config.ssh_authorized_keys)?;
+        }
+
+        // مفاتيح المضيف تنشأ في الإقلاع الأول على الجهاز نفسه
+        ssh.schedule_host_key_regeneration()?;
+
+        Ok(())
+    }
+
+    fn setup_firewall(&self) -> Result<()> {
+        info!("Configuring firewall...");
+
+        // كتابة القواعد في النظام الجديد دون تشغيل أي خدمة
+        let firewall = crate::system::security::firewall::FirewallManager::new();
+        let policy = firewall.load_policy()?;
+
+        let status = Command::new("pacstrap")
+            .args([&self.mount_point.to_string_lossy(), policy.backend.package()])
+            .status()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to install {}", policy.backend.package()));
+        }
+
+        firewall.apply(&policy, &self.mount_point)?;
 
         Ok(())
     }
@@ -151,7 +235,8 @@ impl SystemInstaller {
     fn setup_bootloader(&self) -> Result<()> {
         info!("Setting up bootloader...");
 
-        let boot_manager = crate::system::bootloader::BootManager::new();
+        let boot_manager = crate::system::bootloader::BootManager::new()
+            .with_kernel_profile(self.config.kernel_profile);
         boot_manager.setup_bootloader()?;
 
         Ok(())
@@ -1,20 +1,37 @@
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use crate::system::security::hardening::{load_profile, KernelProfile};
 
 pub struct KernelManager {
     config_path: PathBuf,
+    profile: KernelProfile,
 }
 
 impl KernelManager {
     pub fn new() -> Self {
+        let profile = load_profile(Path::new("/etc/xbitos/kernel.toml")).unwrap_or_else(|e| {
+            warn!("{}, using the default kernel profile", e);
+            KernelProfile::default()
+        });
+
         Self {
             config_path: PathBuf::from("/etc/mkinitcpio.conf.d"),
+            profile,
         }
     }
 
+    pub fn with_profile(mut self, profile: KernelProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn get_profile(&self) -> KernelProfile {
+        self.profile
+    }
+
     pub fn setup_kernel(&self) -> Result<()> {
         info!("Setting up kernel and modules...");
 
@@ -81,9 +98,11 @@ MODULES_DECOMPRESS="yes"
     }
 
     pub fn get_kernel_parameters(&self) -> String {
-        // معلمات النواة الافتراضية
-        "root=PARTUUID=XXXX rw quiet splash nvidia-drm.modeset=1 \
-         amd_iommu=on iommu=pt threadirqs mitigations=off"
-            .to_string()
+        // معلمات النواة الأساسية ثم معلمات ملف التقوية المختار
+        format!(
+            "root=PARTUUID=XXXX rw quiet splash nvidia-drm.modeset=1 threadirqs {} {}",
+            self.profile.cmdline().join(" "),
+            crate::system::security::apparmor::LSM_PARAMETER
+        )
     }
 } 
\ No newline at end of file
@@ -0,0 +1,365 @@
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum InstallReason {
+    Explicit,
+    Dependency,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LocalPackage {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub size: u64,
+    pub reason: InstallReason,
+    pub depends: Vec<String>,
+    pub optdepends: Vec<String>,
+    pub provides: Vec<String>,
+    pub db_dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OptionalDependency {
+    pub name: String,
+    pub description: String,
+    pub installed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DependencyTree {
+    pub name: String,
+    pub children: Vec<DependencyTree>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackageDetails {
+    pub package: LocalPackage,
+    pub files: Vec<PathBuf>,
+    pub required_by: Vec<String>,
+    pub optional_deps: Vec<OptionalDependency>,
+    pub install_chain: Vec<String>,
+}
+
+pub struct LocalDatabase {
+    db_path: PathBuf,
+    packages: HashMap<String, LocalPackage>,
+    // فهارس تبنى مرة واحدة حتى لا يصبح البحث العكسي تربيعياً
+    providers: HashMap<String, Vec<String>>,
+    dependents: HashMap<String, Vec<String>>,
+}
+
+impl LocalDatabase {
+    pub fn new() -> Result<Self> {
+        Self::open(Path::new("/var/lib/pacman/local"))
+    }
+
+    pub fn open(db_path: &Path) -> Result<Self> {
+        let mut packages = HashMap::new();
+
+        let entries = fs::read_dir(db_path)
+            .with_context(|| format!("Failed to read local database: {}", db_path.display()))?;
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let desc_file = entry.path().join("desc");
+            if !desc_file.exists() {
+                continue;
+            }
+
+            let content = fs::read_to_string(&desc_file)?;
+            let package = parse_desc(&content, &entry.path())
+                .with_context(|| format!("Invalid desc file: {}", desc_file.display()))?;
+            packages.insert(package.name.clone(), package);
+        }
+
+        let mut database = Self {
+            db_path: db_path.to_path_buf(),
+            packages,
+            providers: HashMap::new(),
+            dependents: HashMap::new(),
+        };
+        database.build_indexes();
+        Ok(database)
+    }
+
+    fn build_indexes(&mut self) {
+        for package in self.packages.values() {
+            for provided in &package.provides {
+                self.providers
+                    .entry(dependency_name(provided).to_string())
+                    .or_default()
+                    .push(package.name.clone());
+            }
+        }
+        for providers in self.providers.values_mut() {
+            providers.sort();
+        }
+
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+        for package in self.packages.values() {
+            for dependency in &package.depends {
+                if let Some(resolved) = self.resolve(dependency) {
+                    dependents.entry(resolved.name.clone()).or_default().push(package.name.clone());
+                }
+            }
+        }
+        for names in dependents.values_mut() {
+            names.sort();
+            names.dedup();
+        }
+        self.dependents = dependents;
+    }
+
+    pub fn get_db_path(&self) -> &PathBuf {
+        &self.db_path
+    }
+
+    pub fn packages(&self) -> impl Iterator<Item = &LocalPackage> {
+        self.packages.values()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LocalPackage> {
+        self.packages.get(name)
+    }
+
+    pub fn is_installed(&self, dependency: &str) -> bool {
+        self.resolve(dependency).is_some()
+    }
+
+    // إيجاد الحزمة التي تلبي الاعتمادية سواء بالاسم أو عبر provides
+    pub fn resolve(&self, dependency: &str) -> Option<&LocalPackage> {
+        let name = dependency_name(dependency);
+
+        if let Some(package) = self.packages.get(name) {
+            return Some(package);
+        }
+
+        self.providers
+            .get(name)
+            .and_then(|providers| providers.first())
+            .and_then(|provider| self.packages.get(provider))
+    }
+
+    pub fn get_files(&self, name: &str) -> Result<Vec<PathBuf>> {
+        let package = self.require(name)?;
+        let content = fs::read_to_string(package.db_dir.join("files"))?;
+
+        let files = sections(&content)
+            .remove("FILES")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|f| PathBuf::from("/").join(f))
+            .collect();
+
+        Ok(files)
+    }
+
+    pub fn required_by(&self, name: &str) -> Vec<String> {
+        self.dependents.get(name).cloned().unwrap_or_default()
+    }
+
+    pub fn reverse_dependency_tree(&self, name: &str) -> Result<DependencyTree> {
+        self.require(name)?;
+
+        let mut visited = HashSet::new();
+        Ok(self.build_reverse_tree(name, &mut visited))
+    }
+
+    fn build_reverse_tree(&self, name: &str, visited: &mut HashSet<String>) -> DependencyTree {
+        visited.insert(name.to_string());
+
+        let mut children = Vec::new();
+        for dependent in self.required_by(name) {
+            if !visited.contains(&dependent) {
+                children.push(self.build_reverse_tree(&dependent, visited));
+            }
+        }
+
+        DependencyTree {
+            name: name.to_string(),
+            children,
+        }
+    }
+
+    // أقصر سلسلة من حزمة مثبتة صراحة إلى الحزمة المطلوبة
+    pub fn explain_installation(&self, name: &str) -> Result<Vec<String>> {
+        let package = self.require(name)?;
+        if package.reason == InstallReason::Explicit {
+            return Ok(vec![name.to_string()]);
+        }
+
+        let mut parents: HashMap<String, String> = HashMap::new();
+        let mut queue = VecDeque::from([name.to_string()]);
+        let mut visited = HashSet::from([name.to_string()]);
+
+        while let Some(current) = queue.pop_front() {
+            for dependent in self.required_by(&current) {
+                if !visited.insert(dependent.clone()) {
+                    continue;
+                }
+                parents.insert(dependent.clone(), current.clone());
+
+                if self.packages[&dependent].reason == InstallReason::Explicit {
+                    let mut chain = vec![dependent.clone()];
+                    let mut node = dependent;
+                    while let Some(parent) = parents.get(&node) {
+                        chain.push(parent.clone());
+                        node = parent.clone();
+                    }
+                    return Ok(chain);
+                }
+
+                queue.push_back(dependent);
+            }
+        }
+
+        // حزمة يتيمة: ثبتت كاعتمادية ولم يعد أحد يحتاجها
+        Ok(Vec::new())
+    }
+
+    pub fn optional_dependencies(&self, name: &str) -> Result<Vec<OptionalDependency>> {
+        let package = self.require(name)?;
+
+        Ok(package.optdepends
+            .iter()
+            .map(|optdep| {
+                let (dep, description) = optdep
+                    .split_once(": ")
+                    .unwrap_or((optdep.as_str(), ""));
+
+                OptionalDependency {
+                    name: dep.to_string(),
+                    description: description.to_string(),
+                    installed: self.is_installed(dep),
+                }
+            })
+            .collect())
+    }
+
+    pub fn find_owner(&self, path: &Path) -> Result<Option<String>> {
+        let relative = path.strip_prefix("/").unwrap_or(path);
+        let relative = relative.to_string_lossy();
+        let relative = relative.trim_end_matches('/');
+
+        for package in self.packages.values() {
+            let files_path = package.db_dir.join("files");
+            if !files_path.exists() {
+                continue;
+            }
+
+            let content = fs::read_to_string(files_path)?;
+            let owned = sections(&content)
+                .remove("FILES")
+                .unwrap_or_default()
+                .into_iter()
+                // المجلدات مشتركة بين الحزم لذلك نطابق الملفات فقط
+                .any(|f| !f.ends_with('/') && f == relative);
+
+            if owned {
+                return Ok(Some(package.name.clone()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // فهرس لكل ملف والحزمة المالكة له لتجنب قراءة ملفات files عند كل بحث
+    pub fn file_owners(&self) -> Result<HashMap<String, String>> {
+        let mut owners = HashMap::new();
+
+        for package in self.packages.values() {
+            let files_path = package.db_dir.join("files");
+            if !files_path.exists() {
+                continue;
+            }
+
+            let content = fs::read_to_string(files_path)?;
+            for file in sections(&content).remove("FILES").unwrap_or_default() {
+                if !file.ends_with('/') {
+                    owners.insert(file, package.name.clone());
+                }
+            }
+        }
+
+        Ok(owners)
+    }
+
+    pub fn get_details(&self, name: &str) -> Result<PackageDetails> {
+        Ok(PackageDetails {
+            package: self.require(name)?.clone(),
+            files: self.get_files(name)?,
+            required_by: self.required_by(name),
+            optional_deps: self.optional_dependencies(name)?,
+            install_chain: self.explain_installation(name)?,
+        })
+    }
+
+    fn require(&self, name: &str) -> Result<&LocalPackage> {
+        self.packages
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Package not installed: {}", name))
+    }
+}
+
+pub fn dependency_name(dependency: &str) -> &str {
+    let end = dependency
+        .find(['<', '>', '='])
+        .unwrap_or(dependency.len());
+    dependency[..end].trim()
+}
+
+// تقسيم ملفات قاعدة بيانات pacman إلى أقسام %NAME%
+pub fn sections(content: &str) -> HashMap<String, Vec<String>> {
+    let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in content.lines() {
+        if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+            let name = line.trim_matches('%').to_string();
+            sections.entry(name.clone()).or_default();
+            current = Some(name);
+        } else if line.is_empty() {
+            current = None;
+        } else if let Some(name) = &current {
+            sections.entry(name.clone()).or_default().push(line.to_string());
+        }
+    }
+
+    sections
+}
+
+fn parse_desc(content: &str, db_dir: &Path) -> Result<LocalPackage> {
+    let mut sections = sections(content);
+
+    let mut single = |key: &str| -> Option<String> {
+        sections.get_mut(key).and_then(|values| values.drain(..).next())
+    };
+
+    let name = single("NAME").ok_or_else(|| anyhow::anyhow!("Missing %NAME%"))?;
+    let version = single("VERSION").ok_or_else(|| anyhow::anyhow!("Missing %VERSION%"))?;
+    let description = single("DESC").unwrap_or_default();
+    let url = single("URL");
+    let size = single("SIZE").and_then(|s| s.parse().ok()).unwrap_or(0);
+    let reason = match single("REASON").as_deref() {
+        Some("1") => InstallReason::Dependency,
+        _ => InstallReason::Explicit,
+    };
+
+    Ok(LocalPackage {
+        name,
+        version,
+        description,
+        url,
+        size,
+        reason,
+        depends: sections.remove("DEPENDS").unwrap_or_default(),
+        optdepends: sections.remove("OPTDEPENDS").unwrap_or_default(),
+        provides: sections.remove("PROVIDES").unwrap_or_default(),
+        db_dir: db_dir.to_path_buf(),
+    })
+}
@@ -15,7 +15,15 @@ pub mod builder;
 pub mod distro;
 pub mod installer;
 pub mod software;
+pub mod transactions;
+pub mod local_db;
+pub mod collections;
+pub mod gui;
+pub mod security;
+pub mod packaging;
 pub mod iso_builder;
+pub mod backup;
+pub mod daemon;
 
 use anyhow::Result;
 use log::info; 
\ No newline at end of file
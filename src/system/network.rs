@@ -2,7 +2,6 @@ use anyhow::Result;
 use log::info;
 use std::fs;
 use std::path::PathBuf;
-use std::process::Command;
 
 pub struct NetworkManager {
     config_path: PathBuf,
@@ -25,7 +24,6 @@ impl NetworkManager {
             "iwd",
             "dhcpcd",
             "openssh",
-            "firewalld",
         ];
 
         let pkg_manager = crate::system::package_manager::PackageManager::new();
@@ -38,7 +36,6 @@ impl NetworkManager {
         let service_manager = crate::system::services::ServiceManager::new();
         service_manager.enable_service("NetworkManager")?;
         service_manager.enable_service("iwd")?;
-        service_manager.enable_service("firewalld")?;
 
         // إعداد جدار الحماية
         self.setup_firewall()?;
@@ -68,18 +65,9 @@ interval=300
     }
 
     fn setup_firewall(&self) -> Result<()> {
-        // تكوين قواعد جدار الحماية الأساسية
-        Command::new("firewall-cmd")
-            .args(["--permanent", "--add-service=ssh"])
-            .status()?;
-
-        Command::new("firewall-cmd")
-            .args(["--permanent", "--add-service=dhcpv6-client"])
-            .status()?;
-
-        Command::new("firewall-cmd")
-            .arg("--reload")
-            .status()?;
+        // تطبيق سياسة جدار الحماية من /etc/xbitos/firewall.toml
+        let firewall = crate::system::security::firewall::FirewallManager::new();
+        firewall.setup()?;
 
         Ok(())
     }
@@ -1,6 +1,7 @@
 use anyhow::{Result, Context};
 use log::{info, error};
 use std::process::Command;
+use crate::system::security::package_verifier::PackageVerifier;
 
 pub struct PackageManager {
     backend: PackageBackend,
@@ -22,6 +23,9 @@ impl PackageManager {
     pub fn install_packages(&self, packages: &[&str]) -> Result<()> {
         match self.backend {
             PackageBackend::Pacman => {
+                // حزم المستودع المحلي لا تثبت قبل التحقق من تجزئتها وتوقيعها
+                PackageVerifier::new().verify_repo_packages(packages)?;
+
                 for package in packages {
                     info!("Installing package: {}", package);
                     let status = Command::new("pacman")
@@ -39,6 +43,24 @@ impl PackageManager {
         Ok(())
     }
 
+    pub fn remove_packages(&self, packages: &[&str]) -> Result<()> {
+        match self.backend {
+            PackageBackend::Pacman => {
+                info!("Removing packages: {}", packages.join(" "));
+                let status = Command::new("pacman")
+                    .args(["-R", "--noconfirm"])
+                    .args(packages)
+                    .status()
+                    .context("Failed to remove packages")?;
+
+                if !status.success() {
+                    return Err(anyhow::anyhow!("Package removal failed: {}", packages.join(" ")));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn update_system(&self) -> Result<()> {
         match self.backend {
             PackageBackend::Pacman => {
@@ -0,0 +1,295 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::system::local_db::dependency_name;
+use super::builder::PackageConfig;
+use super::srcinfo;
+
+// شجرة git الفارغة لعرض كامل المحتوى عند أول مراجعة
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReviewDiff {
+    pub package: String,
+    pub reviewed: Option<String>,
+    pub head: String,
+    pub diff: String,
+}
+
+impl ReviewDiff {
+    pub fn is_reviewed(&self) -> bool {
+        self.reviewed.as_deref() == Some(self.head.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AurPackage {
+    pub base: String,
+    pub path: PathBuf,
+    pub configs: Vec<PackageConfig>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    results: Vec<RpcPackage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RpcPackage {
+    name: String,
+    package_base: String,
+}
+
+pub struct AurClient {
+    base_url: String,
+    cache_path: PathBuf,
+    reviews_path: PathBuf,
+}
+
+impl AurClient {
+    pub fn new(cache_path: &Path) -> Self {
+        Self {
+            base_url: "https://aur.archlinux.org".to_string(),
+            cache_path: cache_path.to_path_buf(),
+            reviews_path: cache_path.join("reviewed.json"),
+        }
+    }
+
+    // يمكن استبدال AUR بمجلد مستودعات git محلية
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn get_cache_path(&self) -> &PathBuf {
+        &self.cache_path
+    }
+
+    fn repo_url(&self, package: &str) -> String {
+        format!("{}/{}.git", self.base_url, package)
+    }
+
+    fn package_path(&self, package: &str) -> PathBuf {
+        self.cache_path.join(package)
+    }
+
+    // استنساخ الحزمة أول مرة ثم تحديث النسخة الموجودة في المرات اللاحقة
+    pub fn fetch(&self, package: &str) -> Result<PathBuf> {
+        let path = self.package_path(package);
+
+        if path.join(".git").exists() {
+            info!("Updating AUR clone: {}", package);
+            git(&path, &["fetch", "--quiet", "origin"])?;
+            git(&path, &["reset", "--quiet", "--hard", "origin/HEAD"])?;
+        } else {
+            info!("Cloning AUR package: {}", package);
+            fs::create_dir_all(&self.cache_path)?;
+            let status = Command::new("git")
+                .args(["clone", "--quiet", &self.repo_url(package)])
+                .arg(&path)
+                .status()
+                .context("Failed to run git clone")?;
+
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to clone AUR package: {}", package));
+            }
+        }
+
+        if !path.join(".SRCINFO").exists() {
+            return Err(anyhow::anyhow!("{} is not an AUR package (no .SRCINFO)", package));
+        }
+
+        Ok(path)
+    }
+
+    fn is_remote(&self) -> bool {
+        self.base_url.starts_with("https://") || self.base_url.starts_with("http://")
+    }
+
+    // AUR ينشئ مستودعاً فارغاً للأسماء غير الموجودة لذلك نتحقق من وجود مراجع
+    fn repo_exists(&self, base: &str) -> bool {
+        Command::new("git")
+            .args(["ls-remote", "--heads", &self.repo_url(base)])
+            .stderr(Stdio::null())
+            .output()
+            .map(|output| output.status.success() && !output.stdout.is_empty())
+            .unwrap_or(false)
+    }
+
+    // مستودعات AUR مسماة باسم pkgbase، لذلك تترجم أسماء الحزم المقسمة عبر RPC
+    pub fn package_base(&self, package: &str) -> Result<Option<String>> {
+        if !self.is_remote() {
+            // المرآة المحلية لا توفر RPC، ومستودعاتها مسماة باسم pkgbase
+            return Ok(self.repo_exists(package).then(|| package.to_string()));
+        }
+
+        let url = format!("{}/rpc/v5/info?arg[]={}", self.base_url, package.replace('+', "%2B"));
+        let output = Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--globoff", "--max-time", "60", &url])
+            .output()
+            .context("Failed to run curl")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "AUR RPC request for {} failed: {}",
+                package,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let response: RpcResponse = serde_json::from_slice(&output.stdout).context("Invalid AUR RPC response")?;
+        Ok(response
+            .results
+            .into_iter()
+            .find(|result| result.name == package)
+            .map(|result| result.package_base))
+    }
+
+    pub fn require_base(&self, package: &str) -> Result<String> {
+        self.package_base(package)?
+            .ok_or_else(|| anyhow::anyhow!("{} not found in AUR", package))
+    }
+
+    fn load_reviews(&self) -> Result<BTreeMap<String, String>> {
+        if !self.reviews_path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(&self.reviews_path)?)?)
+    }
+
+    pub fn review_diff(&self, package: &str) -> Result<ReviewDiff> {
+        let path = self.package_path(package);
+        let head = git(&path, &["rev-parse", "HEAD"])?;
+        let reviewed = self.load_reviews()?.remove(package);
+
+        // المراجعة السابقة قد تكون لالتزام لم يعد موجوداً بعد إعادة كتابة التاريخ
+        let from = match &reviewed {
+            Some(commit) if git(&path, &["cat-file", "-e", &format!("{}^{{commit}}", commit)]).is_ok() => commit.clone(),
+            Some(commit) => {
+                warn!("Reviewed commit {} of {} no longer exists, showing full diff", commit, package);
+                EMPTY_TREE.to_string()
+            }
+            None => EMPTY_TREE.to_string(),
+        };
+
+        let diff = git(&path, &["diff", "--no-color", &from, &head, "--", ".", ":(exclude).SRCINFO"])?;
+
+        Ok(ReviewDiff {
+            package: package.to_string(),
+            reviewed,
+            head,
+            diff,
+        })
+    }
+
+    pub fn mark_reviewed(&self, package: &str) -> Result<String> {
+        let head = git(&self.package_path(package), &["rev-parse", "HEAD"])?;
+
+        let mut reviews = self.load_reviews()?;
+        reviews.insert(package.to_string(), head.clone());
+        fs::create_dir_all(&self.cache_path)?;
+        fs::write(&self.reviews_path, serde_json::to_string_pretty(&reviews)?)?;
+
+        info!("Marked {} as reviewed at {}", package, head);
+        Ok(head)
+    }
+
+    // حل اعتماديات AUR بشكل متكرر وإرجاعها بترتيب البناء، والحزمة المطلوبة أخيراً
+    pub fn resolve(&self, package: &str) -> Result<Vec<AurPackage>> {
+        let mut resolved = Vec::new();
+        let mut visiting = Vec::new();
+        let mut provided = HashSet::new();
+        let base = self.require_base(package)?;
+        self.resolve_into(&base, &mut resolved, &mut visiting, &mut provided)?;
+        Ok(resolved)
+    }
+
+    fn resolve_into(
+        &self,
+        package: &str,
+        resolved: &mut Vec<AurPackage>,
+        visiting: &mut Vec<String>,
+        provided: &mut HashSet<String>,
+    ) -> Result<()> {
+        if visiting.iter().any(|p| p == package) {
+            visiting.push(package.to_string());
+            return Err(anyhow::anyhow!("AUR dependency cycle: {}", visiting.join(" -> ")));
+        }
+        visiting.push(package.to_string());
+
+        let path = self.fetch(package)?;
+        let configs = srcinfo::import_srcinfo(&path.join(".SRCINFO"), "x86_64")?;
+
+        // الحزم المقسمة توفر أكثر من اسم من نفس المستودع
+        let own: HashSet<String> = configs
+            .iter()
+            .flat_map(|config| {
+                std::iter::once(config.name.clone())
+                    .chain(config.provides.iter().map(|p| dependency_name(p).to_string()))
+            })
+            .collect();
+
+        let dependencies: Vec<String> = configs
+            .iter()
+            .flat_map(|config| config.dependencies.iter().chain(&config.build_dependencies))
+            .map(|dep| dependency_name(dep).to_string())
+            .collect();
+
+        for dependency in dependencies {
+            if own.contains(&dependency) || provided.contains(&dependency) || in_sync_repos(&dependency) {
+                continue;
+            }
+
+            if let Some(base) = self.package_base(&dependency)? {
+                self.resolve_into(&base, resolved, visiting, provided)?;
+            } else {
+                // ما ليس في AUR يترك لـ pacman داخل حاوية البناء
+                warn!("{} not found in AUR, assuming it comes from the repositories", dependency);
+            }
+        }
+
+        // الأسماء تعد متوفرة بعد حل اعتمادياتها فقط، وإلا لم تكتشف الحلقات
+        visiting.pop();
+        provided.extend(own);
+        resolved.push(AurPackage {
+            base: package.to_string(),
+            path,
+            configs,
+        });
+
+        Ok(())
+    }
+}
+
+fn in_sync_repos(dependency: &str) -> bool {
+    Command::new("pacman")
+        .args(["-Sp", "--print-format", "%n", dependency])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn git(path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
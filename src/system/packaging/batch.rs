@@ -0,0 +1,127 @@
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::system::local_db::dependency_name;
+use super::builder::PackageConfig;
+use super::chroot::BuildResult;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "status", content = "reason", rename_all = "lowercase")]
+pub enum BatchStatus {
+    Built,
+    Failed(String),
+    Skipped(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BatchEntry {
+    pub package: String,
+    pub status: BatchStatus,
+    pub result: Option<BuildResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BatchReport {
+    pub entries: Vec<BatchEntry>,
+}
+
+impl BatchReport {
+    pub fn built(&self) -> Vec<&str> {
+        self.filter(|status| matches!(status, BatchStatus::Built))
+    }
+
+    pub fn failed(&self) -> Vec<&str> {
+        self.filter(|status| matches!(status, BatchStatus::Failed(_)))
+    }
+
+    pub fn skipped(&self) -> Vec<&str> {
+        self.filter(|status| matches!(status, BatchStatus::Skipped(_)))
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.entries.iter().all(|entry| matches!(entry.status, BatchStatus::Built))
+    }
+
+    fn filter(&self, predicate: impl Fn(&BatchStatus) -> bool) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|entry| predicate(&entry.status))
+            .map(|entry| entry.package.as_str())
+            .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "{} built, {} failed, {} skipped",
+            self.built().len(),
+            self.failed().len(),
+            self.skipped().len(),
+        )];
+
+        for entry in &self.entries {
+            match &entry.status {
+                BatchStatus::Built => lines.push(format!("  built   {}", entry.package)),
+                BatchStatus::Failed(reason) => lines.push(format!("  failed  {}: {}", entry.package, reason)),
+                BatchStatus::Skipped(reason) => lines.push(format!("  skipped {}: {}", entry.package, reason)),
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+// الاعتماديات التي تبنى ضمن الدفعة نفسها، مفهرسة بموقعها في القائمة
+pub fn batch_dependencies(configs: &[PackageConfig]) -> Vec<BTreeSet<usize>> {
+    let mut providers: HashMap<&str, usize> = HashMap::new();
+    for (index, config) in configs.iter().enumerate() {
+        providers.insert(&config.name, index);
+        for provide in &config.provides {
+            providers.entry(dependency_name(provide)).or_insert(index);
+        }
+    }
+
+    configs
+        .iter()
+        .enumerate()
+        .map(|(index, config)| {
+            config
+                .dependencies
+                .iter()
+                .chain(&config.build_dependencies)
+                .filter_map(|dep| providers.get(dependency_name(dep)).copied())
+                .filter(|&dep| dep != index)
+                .collect()
+        })
+        .collect()
+}
+
+// ترتيب طوبولوجي ثابت: عند التساوي نحافظ على ترتيب الوصفات الأصلي
+pub fn build_order(configs: &[PackageConfig]) -> Result<Vec<usize>> {
+    let dependencies = batch_dependencies(configs);
+    let mut remaining: BTreeMap<usize, usize> = dependencies
+        .iter()
+        .enumerate()
+        .map(|(index, deps)| (index, deps.len()))
+        .collect();
+
+    let mut order = Vec::with_capacity(configs.len());
+    while let Some(&next) = remaining.iter().find(|(_, &count)| count == 0).map(|(index, _)| index) {
+        remaining.remove(&next);
+        order.push(next);
+
+        for (index, deps) in dependencies.iter().enumerate() {
+            if deps.contains(&next) {
+                if let Some(count) = remaining.get_mut(&index) {
+                    *count -= 1;
+                }
+            }
+        }
+    }
+
+    if !remaining.is_empty() {
+        let cycle: Vec<&str> = remaining.keys().map(|&index| configs[index].name.as_str()).collect();
+        return Err(anyhow::anyhow!("Dependency cycle between: {}", cycle.join(", ")));
+    }
+
+    Ok(order)
+}
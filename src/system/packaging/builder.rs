@@ -1,152 +1,654 @@
-use anyhow::Result;
-use log::{info, error};
+use anyhow::{Context, Result};
+use log::{info, warn, error};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
+use crate::system::security::integrity::sha256_file;
+use super::aur::AurClient;
+use super::batch::{self, BatchEntry, BatchReport, BatchStatus};
+use super::chroot::{BuildChroot, BuildResult};
+use super::reproducible::{self, ReproducibilityReport};
+use super::repo_db::{self, RepoDatabase};
+use super::signing::{KeyManager, PackageSigner};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildSystem {
+    #[default]
+    Autotools,
+    Make,
+    Cmake,
+    Meson,
+    Cargo,
+    Python,
+    Custom {
+        #[serde(default)]
+        prepare: Option<String>,
+        build: String,
+        package: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PackageConfig {
-    name: String,
-    version: String,
-    release: String,
-    description: String,
-    dependencies: Vec<String>,
-    build_dependencies: Vec<String>,
-    source: Vec<String>,
+    pub name: String,
+    pub version: String,
+    pub release: String,
+    #[serde(default)]
+    pub epoch: Option<u32>,
+    pub description: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub build_dependencies: Vec<String>,
+    #[serde(default)]
+    pub source: Vec<String>,
+    #[serde(default)]
+    pub build_system: BuildSystem,
+    #[serde(default = "default_arch")]
+    pub arch: Vec<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub license: Vec<String>,
+    #[serde(default)]
+    pub provides: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    #[serde(default)]
+    pub replaces: Vec<String>,
+    #[serde(default)]
+    pub optional_dependencies: Vec<String>,
+    #[serde(default)]
+    pub backup: Vec<String>,
+    // مسار سكربت .install المحلي
+    #[serde(default)]
+    pub install: Option<String>,
+    // مجموع sha256 المثبت لكل مصدر بعيد بنفس ترتيب source
+    #[serde(default)]
+    pub sha256sums: Vec<String>,
+    // مجاميع إضافية كما في PKGBUILD، ويكفي أحدها لتثبيت المصدر البعيد
+    #[serde(default)]
+    pub b2sums: Vec<String>,
+    #[serde(default)]
+    pub sha512sums: Vec<String>,
+    // اسم مجلد الشيفرة داخل $srcdir إن اختلف عن $pkgname-$pkgver
+    #[serde(default)]
+    pub source_directory: Option<String>,
+    // متغيرات مساعدة تكتب في PKGBUILD قبل الدوال
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    // المجلد الذي تحسب منه مسارات المصادر المحلية
+    #[serde(skip)]
+    pub recipe_dir: Option<PathBuf>,
+}
+
+impl BuildSystem {
+    // أدوات البناء التي يحتاجها القالب نفسه
+    pub fn required_tools(&self) -> &'static [&'static str] {
+        match self {
+            BuildSystem::Cmake => &["cmake"],
+            BuildSystem::Meson => &["meson"],
+            BuildSystem::Cargo => &["cargo"],
+            BuildSystem::Python => &["python-build", "python-installer"],
+            _ => &[],
+        }
+    }
+}
+
+fn default_arch() -> Vec<String> {
+    vec!["x86_64".to_string()]
 }
 
 pub struct PackageBuilder {
     build_root: PathBuf,
     repo_path: PathBuf,
-    aur_cache: PathBuf,
+    aur: AurClient,
+    chroot: BuildChroot,
+    signer: Option<PackageSigner>,
+    reproducible_path: PathBuf,
+    block_on_lint_errors: bool,
 }
 
 impl PackageBuilder {
     pub fn new() -> Self {
+        let repo_path = PathBuf::from("/var/lib/xbitos/repo");
+
         Self {
             build_root: PathBuf::from("/var/lib/xbitos/build"),
-            repo_path: PathBuf::from("/var/lib/xbitos/repo"),
-            aur_cache: PathBuf::from("/var/cache/xbitos/aur"),
+            chroot: BuildChroot::new().with_local_repo(&repo_path),
+            repo_path,
+            aur: AurClient::new(Path::new("/var/cache/xbitos/aur")),
+            signer: KeyManager::new().configured_signer().unwrap_or_else(|e| {
+                warn!("Package signing disabled: {}", e);
+                None
+            }),
+            reproducible_path: PathBuf::from("/var/lib/xbitos/reproducible"),
+            block_on_lint_errors: true,
         }
     }
 
-    pub fn build_package(&self, config: &PackageConfig) -> Result<()> {
+    pub fn with_chroot(mut self, chroot: BuildChroot) -> Self {
+        self.chroot = chroot;
+        self
+    }
+
+    pub fn with_aur(mut self, aur: AurClient) -> Self {
+        self.aur = aur;
+        self
+    }
+
+    // أخطاء الفحص تمنع النشر في المستودع ما لم يعطل ذلك صراحة
+    pub fn block_on_lint_errors(mut self, block: bool) -> Self {
+        self.block_on_lint_errors = block;
+        self
+    }
+
+    pub fn with_signer(mut self, signer: Option<PackageSigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    pub fn build_package(&self, config: &PackageConfig) -> Result<BuildResult> {
         info!("Building package: {}", config.name);
 
         // إنشاء مجلد البناء
         let build_dir = self.build_root.join(&config.name);
+        if build_dir.exists() {
+            fs::remove_dir_all(&build_dir)?;
+        }
         fs::create_dir_all(&build_dir)?;
 
         // إنشاء ملف PKGBUILD
         self.create_pkgbuild(&build_dir, config)?;
 
-        // تثبيت اعتماديات البناء
-        self.install_build_deps(config)?;
+        // بناء الحزمة داخل حاوية نظيفة مع اعتمادياتها المعلنة فقط
+        let mut result = self.chroot.build(&config.name, &build_dir, &self.build_deps(config))?;
+        info!("Build log: {}", result.log_path.display());
+
+        // فحص الحزمة ثم إضافتها إلى المستودع
+        self.publish(&mut result)?;
 
-        // بناء الحزمة
-        Command::new("makepkg")
-            .args(["-sf", "--noconfirm"])
-            .current_dir(&build_dir)
-            .status()?;
+        Ok(result)
+    }
 
-        // إضافة الحزمة إلى المستودع
-        self.add_to_repo(&build_dir)?;
+    // بناء الحزمة مرتين في حاويتين مستقلتين ومقارنة الأرشيفين دون نشرهما
+    pub fn check_reproducible(&self, config: &PackageConfig, source_date_epoch: i64) -> Result<Vec<ReproducibilityReport>> {
+        info!("Checking reproducibility of {} (SOURCE_DATE_EPOCH={})", config.name, source_date_epoch);
 
-        Ok(())
+        let build_dir = self.build_root.join(&config.name);
+        if build_dir.exists() {
+            fs::remove_dir_all(&build_dir)?;
+        }
+        fs::create_dir_all(&build_dir)?;
+        self.create_pkgbuild(&build_dir, config)?;
+
+        let deps = self.build_deps(config);
+        let chroot = self
+            .chroot
+            .clone()
+            .with_environment("SOURCE_DATE_EPOCH", &source_date_epoch.to_string());
+
+        // المنطقة الزمنية تختلف بين البناءين لكشف الاعتماد على بيئة المضيف
+        let first = chroot.clone().with_environment("TZ", "UTC").build(&config.name, &build_dir, &deps)?;
+        let second = chroot.with_environment("TZ", "Asia/Riyadh").build(&config.name, &build_dir, &deps)?;
+
+        let mut reports = Vec::new();
+        for first_artifact in &first.artifacts {
+            let file_name = first_artifact.file_name().unwrap_or_default();
+            let second_artifact = second
+                .artifacts
+                .iter()
+                .find(|artifact| artifact.file_name() == Some(file_name))
+                .ok_or_else(|| anyhow::anyhow!("Second build did not produce {}", file_name.to_string_lossy()))?;
+
+            let differences = reproducible::compare_packages(first_artifact, second_artifact)?;
+            let package = repo_db::read_package_info(first_artifact)?.name;
+
+            if differences.is_empty() {
+                info!("{} is reproducible", package);
+            } else {
+                warn!("{} is not reproducible: {} differences", package, differences.len());
+            }
+
+            let report = ReproducibilityReport {
+                package,
+                source_date_epoch,
+                first: first_artifact.clone(),
+                second: second_artifact.clone(),
+                reproducible: differences.is_empty(),
+                differences,
+                checked_at: chrono::Local::now().to_rfc3339(),
+            };
+            reproducible::save_report(&self.reproducible_path, &report)?;
+            reports.push(report);
+        }
+
+        Ok(reports)
+    }
+
+    pub fn get_reproducibility_reports(&self) -> Result<Vec<ReproducibilityReport>> {
+        reproducible::load_reports(&self.reproducible_path)
     }
 
-    pub fn build_aur_package(&self, package_name: &str) -> Result<()> {
+    // بناء مجموعة وصفات مترابطة بترتيب اعتمادياتها
+    pub fn build_batch(&self, configs: &[PackageConfig]) -> Result<BatchReport> {
+        let order = batch::build_order(configs)?;
+        let dependencies = batch::batch_dependencies(configs);
+
+        info!(
+            "Build order: {}",
+            order.iter().map(|&i| configs[i].name.as_str()).collect::<Vec<_>>().join(" -> ")
+        );
+
+        let mut statuses: Vec<Option<BatchStatus>> = vec![None; configs.len()];
+        let mut report = BatchReport::default();
+
+        for index in order {
+            let config = &configs[index];
+
+            // تخطي التوابع فقط عند فشل أحد اعتمادياتها
+            let blocked: Vec<&str> = dependencies[index]
+                .iter()
+                .filter(|&&dep| !matches!(statuses[dep], Some(BatchStatus::Built)))
+                .map(|&dep| configs[dep].name.as_str())
+                .collect();
+
+            let (status, result) = if !blocked.is_empty() {
+                warn!("Skipping {}: {} did not build", config.name, blocked.join(", "));
+                (BatchStatus::Skipped(format!("dependency failed: {}", blocked.join(", "))), None)
+            } else {
+                match self.build_package(config) {
+                    Ok(result) => (BatchStatus::Built, Some(result)),
+                    Err(e) => {
+                        error!("Failed to build {}: {:#}", config.name, e);
+                        (BatchStatus::Failed(format!("{:#}", e)), None)
+                    }
+                }
+            };
+
+            statuses[index] = Some(status.clone());
+            report.entries.push(BatchEntry {
+                package: config.name.clone(),
+                status,
+                result,
+            });
+        }
+
+        info!("Batch build finished: {}", report.summary());
+        Ok(report)
+    }
+
+    pub fn import_srcinfo(&self, srcinfo_path: &Path) -> Result<Vec<PackageConfig>> {
+        super::srcinfo::import_srcinfo(srcinfo_path, "x86_64")
+    }
+
+    pub fn import_pkgbuild(&self, pkgbuild_dir: &Path) -> Result<Vec<PackageConfig>> {
+        super::srcinfo::import_pkgbuild(pkgbuild_dir, "x86_64")
+    }
+
+    // بناء حزمة AUR مع اعتمادياتها من AUR ونشرها في المستودع دون تثبيتها
+    pub fn build_aur_package(&self, package_name: &str) -> Result<Vec<BuildResult>> {
         info!("Building AUR package: {}", package_name);
 
-        // استنساخ الحزمة من AUR
-        let aur_dir = self.aur_cache.join(package_name);
-        Command::new("git")
-            .args([
-                "clone",
-                &format!("https://aur.archlinux.org/{}.git", package_name),
-                aur_dir.to_str().unwrap(),
-            ])
-            .status()?;
-
-        // بناء الحزمة
-        Command::new("makepkg")
-            .args(["-si", "--noconfirm"])
-            .current_dir(&aur_dir)
-            .status()?;
+        let packages = self.aur.resolve(package_name)?;
 
-        Ok(())
+        // لا يبنى أي شيء قبل مراجعة كل التغييرات في PKGBUILD
+        let unreviewed: Vec<&str> = packages
+            .iter()
+            .filter(|package| {
+                self.aur
+                    .review_diff(&package.base)
+                    .map(|diff| !diff.is_reviewed())
+                    .unwrap_or(true)
+            })
+            .map(|package| package.base.as_str())
+            .collect();
+
+        if !unreviewed.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Unreviewed AUR changes in: {} (run: xbitos aur review <name>)",
+                unreviewed.join(", ")
+            ));
+        }
+
+        let mut results = Vec::new();
+        for package in &packages {
+            let mut deps: Vec<String> = package
+                .configs
+                .iter()
+                .flat_map(|config| config.dependencies.iter().chain(&config.build_dependencies))
+                .cloned()
+                .collect();
+            deps.sort();
+            deps.dedup();
+
+            let mut result = self.chroot.build(&package.base, &package.path, &deps)?;
+            info!("Build log: {}", result.log_path.display());
+
+            // نشر الحزمة في المستودع المحلي لتتوفر للبناءات التالية
+            self.publish(&mut result)?;
+            results.push(result);
+        }
+
+        Ok(results)
     }
 
-    fn create_pkgbuild(&self, build_dir: &PathBuf, config: &PackageConfig) -> Result<()> {
+    pub fn get_aur_client(&self) -> &AurClient {
+        &self.aur
+    }
+
+    pub fn create_pkgbuild(&self, build_dir: &Path, config: &PackageConfig) -> Result<()> {
+        let (sources, checksums) = self.prepare_sources(build_dir, config)?;
+
+        let mut optional_fields = String::new();
+        if let Some(epoch) = config.epoch {
+            optional_fields.push_str(&format!("epoch={}\n", epoch));
+        }
+        if let Some(url) = &config.url {
+            optional_fields.push_str(&format!("url={}\n", bash_quote(url)));
+        }
+        for (field, values) in [
+            ("optdepends", &config.optional_dependencies),
+            ("provides", &config.provides),
+            ("conflicts", &config.conflicts),
+            ("replaces", &config.replaces),
+            ("backup", &config.backup),
+        ] {
+            if !values.is_empty() {
+                optional_fields.push_str(&format!("{}=({})\n", field, bash_array(values)));
+            }
+        }
+        if let Some(install) = &config.install {
+            let script = self.resolve_local_source(config, install);
+            let file_name = file_name_of(&script)?;
+            fs::copy(&script, build_dir.join(&file_name))
+                .with_context(|| format!("Failed to copy install script: {}", script.display()))?;
+            optional_fields.push_str(&format!("install={}\n", bash_quote(&file_name)));
+        }
+
+        for (key, sums) in [("b2sums", &config.b2sums), ("sha512sums", &config.sha512sums)] {
+            if !sums.is_empty() {
+                optional_fields.push_str(&format!("{}=({})\n", key, bash_array(&extra_checksums(config, sums))));
+            }
+        }
+
+        let mut makedepends = config.build_dependencies.clone();
+        for tool in config.build_system.required_tools() {
+            if !makedepends.iter().any(|dep| dep == tool) {
+                makedepends.push(tool.to_string());
+            }
+        }
+
+        let license = if config.license.is_empty() {
+            vec!["custom".to_string()]
+        } else {
+            config.license.clone()
+        };
+
         let pkgbuild = format!(r#"
 # Maintainer: xBitOS Team <team@xbitos.org>
 pkgname={}
 pkgver={}
 pkgrel={}
-pkgdesc="{}"
-arch=('x86_64')
-depends=({})
+pkgdesc={}
+arch=({})
+license=({})
+{}depends=({})
 makedepends=({})
 source=({})
-sha256sums=('SKIP')
-
-build() {{
-    cd "$srcdir/$pkgname-$pkgver"
-    ./configure --prefix=/usr
-    make
-}}
-
-package() {{
-    cd "$srcdir/$pkgname-$pkgver"
-    make DESTDIR="$pkgdir/" install
-}}
-"#,
-            config.name,
-            config.version,
-            config.release,
-            config.description,
-            config.dependencies.join(" "),
-            config.build_dependencies.join(" "),
-            config.source.join(" "),
+sha256sums=({})
+_srcdir={}
+{}{}"#,
+            // القيم تقرأ بـ source في makepkg، فالقيمة غير المقتبسة قد تنفذ أوامر
+            bash_quote(&config.name),
+            bash_quote(&config.version),
+            bash_quote(&config.release),
+            bash_quote(&config.description),
+            bash_array(&config.arch),
+            bash_array(&license),
+            optional_fields,
+            bash_array(&config.dependencies),
+            bash_array(&makedepends),
+            bash_array(&sources),
+            bash_array(&checksums),
+            config.source_directory
+                .as_deref()
+                .map(bash_quote)
+                .unwrap_or_else(|| "\"$pkgname-$pkgver\"".to_string()),
+            config.variables
+                .iter()
+                .map(|(name, value)| format!("{}={}\n", name, bash_quote(value)))
+                .collect::<String>(),
+            build_functions(&config.build_system),
         );
 
         fs::write(build_dir.join("PKGBUILD"), pkgbuild)?;
         Ok(())
     }
 
-    fn install_build_deps(&self, config: &PackageConfig) -> Result<()> {
-        let pkg_manager = crate::system::package_manager::PackageManager::new();
-        pkg_manager.install_packages(&config.build_dependencies)?;
-        Ok(())
+    // نسخ المصادر المحلية إلى مجلد البناء وحساب مجاميعها، وتثبيت مجاميع المصادر البعيدة
+    fn prepare_sources(&self, build_dir: &Path, config: &PackageConfig) -> Result<(Vec<String>, Vec<String>)> {
+        let mut sources = Vec::new();
+        let mut checksums = Vec::new();
+
+        for (index, source) in config.source.iter().enumerate() {
+            let pinned = config.sha256sums.get(index).filter(|sum| !sum.is_empty());
+
+            if is_vcs_source(source) {
+                sources.push(source.clone());
+                checksums.push("SKIP".to_string());
+            } else if is_remote_source(source) {
+                let checksum = match pinned {
+                    Some(checksum) => {
+                        if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+                            return Err(anyhow::anyhow!("Invalid sha256sum for remote source: {}", source));
+                        }
+                        checksum.clone()
+                    }
+                    // makepkg يتحقق من المصدر عبر b2sums أو sha512sums
+                    None if has_extra_checksum(config, index) => "SKIP".to_string(),
+                    None => {
+                        return Err(anyhow::anyhow!("Missing pinned checksum for remote source: {}", source));
+                    }
+                };
+                sources.push(source.clone());
+                checksums.push(checksum);
+            } else {
+                let path = self.resolve_local_source(config, source);
+                let file_name = file_name_of(&path)?;
+                let checksum = sha256_file(&path)
+                    .with_context(|| format!("Failed to read local source: {}", path.display()))?;
+
+                if let Some(expected) = pinned {
+                    if expected != &checksum {
+                        return Err(anyhow::anyhow!("Checksum mismatch for local source: {}", source));
+                    }
+                }
+
+                fs::copy(&path, build_dir.join(&file_name))?;
+                sources.push(file_name);
+                checksums.push(checksum);
+            }
+        }
+
+        Ok((sources, checksums))
     }
 
-    fn add_to_repo(&self, build_dir: &PathBuf) -> Result<()> {
-        // نقل الحزمة المبنية إلى المستودع
-        let packages = fs::read_dir(build_dir)?
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                entry.path()
-                    .extension()
-                    .map_or(false, |ext| ext == "pkg.tar.zst")
-            });
+    fn resolve_local_source(&self, config: &PackageConfig, source: &str) -> PathBuf {
+        let path = PathBuf::from(source);
+        match &config.recipe_dir {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        }
+    }
+
+    fn build_deps(&self, config: &PackageConfig) -> Vec<String> {
+        let mut deps = config.dependencies.clone();
+        deps.extend(config.build_dependencies.iter().cloned());
+        deps.extend(config.build_system.required_tools().iter().map(|t| t.to_string()));
+        deps.sort();
+        deps.dedup();
+        deps
+    }
+
+    fn publish(&self, result: &mut BuildResult) -> Result<()> {
+        // الحاوية فحصت الحزم قبل حذفها
+        for report in &result.lint {
+            report.log();
+        }
 
+        let failed: Vec<&str> = result
+            .lint
+            .iter()
+            .filter(|report| report.has_errors())
+            .map(|report| report.package.as_str())
+            .collect();
+
+        if !failed.is_empty() && self.block_on_lint_errors {
+            return Err(anyhow::anyhow!(
+                "Lint errors in {}, not publishing (artifacts kept in {})",
+                failed.join(", "),
+                result.artifacts[0].parent().unwrap_or(Path::new("")).display()
+            ));
+        }
+
+        self.add_to_repo(&result.artifacts)
+    }
+
+    fn add_to_repo(&self, packages: &[PathBuf]) -> Result<()> {
+        fs::create_dir_all(&self.repo_path)?;
+
+        // نقل الحزمة المبنية إلى المستودع مع توقيع جديد لكل حزمة
         for package in packages {
-            fs::copy(
-                package.path(),
-                self.repo_path.join(package.file_name()),
-            )?;
+            let file_name = package
+                .file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid package path: {}", package.display()))?;
+            let target = self.repo_path.join(file_name);
+            fs::copy(package, &target)?;
+
+            if let Some(signer) = &self.signer {
+                signer.sign(&target)?;
+            }
         }
 
         // تحديث قاعدة بيانات المستودع
-        Command::new("repo-add")
-            .args([
-                self.repo_path.join("xbitos.db.tar.gz").to_str().unwrap(),
-                "*.pkg.tar.zst",
-            ])
-            .current_dir(&self.repo_path)
-            .status()?;
+        let mut database = RepoDatabase::open(&self.repo_path, "xbitos")?.sign_with(self.signer.clone());
+        for package in packages {
+            database.add(&self.repo_path.join(package.file_name().unwrap()))?;
+        }
+        database.write()?;
 
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+fn has_extra_checksum(config: &PackageConfig, index: usize) -> bool {
+    [&config.b2sums, &config.sha512sums]
+        .iter()
+        .any(|sums| sums.get(index).is_some_and(|sum| !sum.is_empty() && sum != "SKIP"))
+}
+
+// المصادر المحلية تتحقق منها sha256sums المحسوبة، فتبقى SKIP إن لم تثبت الوصفة قيمة لها
+fn extra_checksums(config: &PackageConfig, sums: &[String]) -> Vec<String> {
+    config
+        .source
+        .iter()
+        .enumerate()
+        .map(|(index, source)| match sums.get(index).filter(|sum| !sum.is_empty()) {
+            Some(sum) if !is_vcs_source(source) => sum.clone(),
+            _ => "SKIP".to_string(),
+        })
+        .collect()
+}
+
+pub fn is_vcs_source(source: &str) -> bool {
+    let url = source.split_once("::").map_or(source, |(_, url)| url);
+    ["git+", "svn+", "hg+", "bzr+"].iter().any(|prefix| url.starts_with(prefix))
+}
+
+pub fn is_remote_source(source: &str) -> bool {
+    source.contains("://")
+}
+
+fn file_name_of(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Invalid source path: {}", path.display()))
+}
+
+pub fn bash_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+pub fn bash_array(values: &[String]) -> String {
+    values.iter().map(|v| bash_quote(v)).collect::<Vec<_>>().join(" ")
+}
+
+fn build_functions(build_system: &BuildSystem) -> String {
+    let (prepare, build, package) = match build_system {
+        BuildSystem::Autotools => (
+            None,
+            r#"cd "$srcdir/$_srcdir"
+    ./configure --prefix=/usr
+    make"#.to_string(),
+            r#"cd "$srcdir/$_srcdir"
+    make DESTDIR="$pkgdir/" install"#.to_string(),
+        ),
+        BuildSystem::Make => (
+            None,
+            r#"cd "$srcdir/$_srcdir"
+    make PREFIX=/usr"#.to_string(),
+            r#"cd "$srcdir/$_srcdir"
+    make DESTDIR="$pkgdir/" PREFIX=/usr install"#.to_string(),
+        ),
+        BuildSystem::Cmake => (
+            None,
+            r#"cmake -B build -S "$srcdir/$_srcdir" \
+        -DCMAKE_BUILD_TYPE=None \
+        -DCMAKE_INSTALL_PREFIX=/usr \
+        -Wno-dev
+    cmake --build build"#.to_string(),
+            r#"DESTDIR="$pkgdir" cmake --install build"#.to_string(),
+        ),
+        BuildSystem::Meson => (
+            None,
+            r#"arch-meson "$srcdir/$_srcdir" build
+    meson compile -C build"#.to_string(),
+            r#"meson install -C build --destdir "$pkgdir""#.to_string(),
+        ),
+        BuildSystem::Cargo => (
+            Some(r#"cd "$srcdir/$_srcdir"
+    export RUSTUP_TOOLCHAIN=stable
+    cargo fetch --locked --target "$(rustc -vV | sed -n 's/host: //p')""#.to_string()),
+            r#"cd "$srcdir/$_srcdir"
+    export RUSTUP_TOOLCHAIN=stable
+    export CARGO_TARGET_DIR=target
+    cargo build --frozen --release"#.to_string(),
+            r#"cd "$srcdir/$_srcdir"
+    install -Dm0755 -t "$pkgdir/usr/bin/" "target/release/$pkgname""#.to_string(),
+        ),
+        BuildSystem::Python => (
+            None,
+            r#"cd "$srcdir/$_srcdir"
+    python -m build --wheel --no-isolation"#.to_string(),
+            r#"cd "$srcdir/$_srcdir"
+    python -m installer --destdir="$pkgdir" dist/*.whl"#.to_string(),
+        ),
+        BuildSystem::Custom { prepare, build, package } => (
+            prepare.clone(),
+            build.clone(),
+            package.clone(),
+        ),
+    };
+
+    let mut functions = String::new();
+    if let Some(prepare) = prepare {
+        functions.push_str(&format!("\nprepare() {{\n    {}\n}}\n", prepare));
+    }
+    functions.push_str(&format!("\nbuild() {{\n    {}\n}}\n", build));
+    functions.push_str(&format!("\npackage() {{\n    {}\n}}\n", package));
+    functions
+}
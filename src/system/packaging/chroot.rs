@@ -0,0 +1,353 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use super::lint::{LintReport, PackageLinter};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChrootBackend {
+    Nspawn,
+    Bubblewrap,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BuildResult {
+    pub build_id: String,
+    pub package: String,
+    pub artifacts: Vec<PathBuf>,
+    pub log_path: PathBuf,
+    #[serde(default)]
+    pub lint: Vec<LintReport>,
+}
+
+const BUILD_USER: &str = "builduser";
+const TEMPLATE_STAMP: &str = ".xbitos-template";
+const LOCAL_REPO_MOUNT: &str = "/var/lib/xbitos-local-repo";
+
+#[derive(Clone)]
+pub struct BuildChroot {
+    chroot_path: PathBuf,
+    artifacts_path: PathBuf,
+    logs_path: PathBuf,
+    backend: ChrootBackend,
+    base_packages: Vec<String>,
+    keep_failed: bool,
+    local_repo: Option<PathBuf>,
+    environment: Vec<(String, String)>,
+}
+
+impl BuildChroot {
+    pub fn new() -> Self {
+        Self {
+            chroot_path: PathBuf::from("/var/lib/xbitos/chroots"),
+            artifacts_path: PathBuf::from("/var/lib/xbitos/artifacts"),
+            logs_path: PathBuf::from("/var/log/xbitos/builds"),
+            backend: ChrootBackend::Nspawn,
+            base_packages: vec!["base-devel".to_string()],
+            keep_failed: false,
+            local_repo: None,
+            environment: Vec::new(),
+        }
+    }
+
+    pub fn with_backend(mut self, backend: ChrootBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn keep_failed(mut self, keep: bool) -> Self {
+        self.keep_failed = keep;
+        self
+    }
+
+    // إتاحة الحزم المبنية حديثاً للبناءات اللاحقة عبر المستودع المحلي
+    pub fn with_local_repo(mut self, repo_path: &Path) -> Self {
+        self.local_repo = Some(repo_path.to_path_buf());
+        self
+    }
+
+    // متغيرات بيئة تمرر إلى makepkg مثل SOURCE_DATE_EPOCH
+    pub fn with_environment(mut self, key: &str, value: &str) -> Self {
+        self.environment.retain(|(k, _)| k != key);
+        self.environment.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn get_logs_path(&self) -> &PathBuf {
+        &self.logs_path
+    }
+
+    fn template_path(&self) -> PathBuf {
+        self.chroot_path.join("template")
+    }
+
+    // إنشاء القالب الأساسي مرة واحدة ثم إعادة استخدامه لكل عملية بناء
+    pub fn ensure_template(&self) -> Result<()> {
+        let template = self.template_path();
+        if template.join(TEMPLATE_STAMP).exists() {
+            return Ok(());
+        }
+
+        info!("Creating build chroot template in {}", template.display());
+        fs::create_dir_all(&template)?;
+
+        // بدون -G و -M ينسخ pacstrap حلقة المفاتيح وقائمة المرايا من المضيف،
+        // وإلا يفشل pacman داخل الحاوية عند تثبيت اعتماديات البناء
+        let status = Command::new("pacstrap")
+            .arg("-c")
+            .arg(&template)
+            .args(&self.base_packages)
+            .status()
+            .context("Failed to run pacstrap")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to create chroot template"));
+        }
+
+        // مستخدم غير جذري لأن makepkg يرفض العمل كجذر
+        let status = self
+            .container_command(&template, None, None)
+            .args(["useradd", "-m", "-U", BUILD_USER])
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to create build user in template"));
+        }
+
+        fs::write(template.join(TEMPLATE_STAMP), Local::now().to_rfc3339())?;
+        Ok(())
+    }
+
+    pub fn update_template(&self) -> Result<()> {
+        self.ensure_template()?;
+        info!("Updating build chroot template...");
+
+        // قائمة المرايا قد تتغير على المضيف بعد إنشاء القالب، كما يفعل arch-nspawn
+        fs::copy("/etc/pacman.d/mirrorlist", self.template_path().join("etc/pacman.d/mirrorlist"))
+            .context("Failed to copy mirrorlist into chroot template")?;
+
+        let status = self
+            .container_command(&self.template_path(), None, None)
+            .args(["pacman", "-Syu", "--noconfirm"])
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to update chroot template"));
+        }
+
+        Ok(())
+    }
+
+    pub fn build(&self, package: &str, build_dir: &Path, dependencies: &[String]) -> Result<BuildResult> {
+        self.ensure_template()?;
+
+        // بناءان في نفس الثانية يحتاجان مجلدين مختلفين
+        let timestamp = format!("{}-{}", package, Local::now().format("%Y%m%d-%H%M%S"));
+        let mut build_id = timestamp.clone();
+        let mut attempt = 1;
+        while self.chroot_path.join("builds").join(&build_id).exists() || self.logs_path.join(&build_id).exists() {
+            attempt += 1;
+            build_id = format!("{}-{}", timestamp, attempt);
+        }
+
+        let root = self.chroot_path.join("builds").join(&build_id);
+        let log_dir = self.logs_path.join(&build_id);
+        let artifact_dir = self.artifacts_path.join(&build_id);
+
+        fs::create_dir_all(root.parent().unwrap())?;
+        fs::create_dir_all(&log_dir)?;
+        fs::create_dir_all(&artifact_dir)?;
+
+        info!("Building {} in clean chroot {}", package, build_id);
+        let log_path = log_dir.join("build.log");
+
+        let result = self.run_build(&root, build_dir, dependencies, &log_path);
+
+        let artifacts = match result {
+            Ok(()) => self.collect_artifacts(&root, &artifact_dir)?,
+            Err(e) => {
+                if self.keep_failed {
+                    warn!("Keeping failed build root: {}", root.display());
+                } else {
+                    self.remove_root(&root)?;
+                }
+                return Err(e.context(format!("Build failed, see {}", log_path.display())));
+            }
+        };
+
+        // الفحص يستخدم قاعدة بيانات الحاوية التي ثبتت فيها اعتماديات البناء، لا قاعدة المضيف
+        let lint = PackageLinter::with_db_path(&root.join("var/lib/pacman/local")).lint_all(&artifacts);
+        self.remove_root(&root)?;
+
+        Ok(BuildResult {
+            build_id,
+            package: package.to_string(),
+            artifacts,
+            log_path,
+            lint: lint?,
+        })
+    }
+
+    fn run_build(&self, root: &Path, build_dir: &Path, dependencies: &[String], log_path: &Path) -> Result<()> {
+        // نسخة جديدة من القالب لكل بناء
+        let status = Command::new("cp")
+            .args(["-a", "--reflink=auto"])
+            .arg(self.template_path())
+            .arg(root)
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to copy chroot template"));
+        }
+
+        let container_build = root.join("build");
+        fs::create_dir_all(&container_build)?;
+        for entry in fs::read_dir(build_dir)?.filter_map(|entry| entry.ok()) {
+            if entry.file_type()?.is_file() {
+                fs::copy(entry.path(), container_build.join(entry.file_name()))?;
+            }
+        }
+
+        let log = File::create(log_path)?;
+        self.configure_local_repo(root)?;
+
+        // تثبيت الاعتماديات المعلنة فقط داخل الحاوية، مع ترقية نسخة القالب حتى لا
+        // تثبت مكتبات جديدة فوق نظام قديم (ترقية جزئية)
+        if !dependencies.is_empty() {
+            let status = self
+                .container_command(root, None, None)
+                .args(["pacman", "-Syu", "--needed", "--noconfirm"])
+                .args(dependencies)
+                .stdout(Stdio::from(log.try_clone()?))
+                .stderr(Stdio::from(log.try_clone()?))
+                .status()?;
+
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to install build dependencies"));
+            }
+        }
+
+        let status = self
+            .container_command(root, None, None)
+            .args(["chown", "-R", &format!("{}:{}", BUILD_USER, BUILD_USER), "/build"])
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to prepare build directory"));
+        }
+
+        let mut makepkg = self.container_command(root, Some(BUILD_USER), Some("/build"));
+        if !self.environment.is_empty() {
+            makepkg.arg("env");
+            makepkg.args(self.environment.iter().map(|(key, value)| format!("{}={}", key, value)));
+        }
+
+        let status = makepkg
+            .args(["makepkg", "-f", "--noconfirm", "--nocolor"])
+            .stdout(Stdio::from(log.try_clone()?))
+            .stderr(Stdio::from(log))
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("makepkg failed"));
+        }
+
+        Ok(())
+    }
+
+    fn configure_local_repo(&self, root: &Path) -> Result<()> {
+        let Some(repo_path) = &self.local_repo else {
+            return Ok(());
+        };
+        if !repo_path.join("xbitos.db").exists() {
+            return Ok(());
+        }
+
+        // المستودع المحلي قبل core حتى تفوز الحزم المبنية محلياً، والتوقيع غير
+        // مطلوب هنا لأن حلقة مفاتيح الحاوية لا تعرف مفتاح المستودع
+        let section = format!(
+            "[xbitos]\nSigLevel = Never\nServer = file://{}\n\n",
+            LOCAL_REPO_MOUNT
+        );
+
+        let conf_path = root.join("etc/pacman.conf");
+        let conf = fs::read_to_string(&conf_path)?;
+        let conf = match conf.find("\n[core]") {
+            Some(index) => format!("{}\n{}{}", &conf[..index], section, &conf[index + 1..]),
+            None => format!("{}\n{}", conf, section),
+        };
+        fs::write(&conf_path, conf)?;
+
+        fs::create_dir_all(root.join(LOCAL_REPO_MOUNT.trim_start_matches('/')))?;
+        Ok(())
+    }
+
+    fn collect_artifacts(&self, root: &Path, artifact_dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut artifacts = Vec::new();
+
+        for entry in fs::read_dir(root.join("build"))?.filter_map(|entry| entry.ok()) {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.contains(".pkg.tar") {
+                let target = artifact_dir.join(&file_name);
+                fs::copy(entry.path(), &target)?;
+                artifacts.push(target);
+            }
+        }
+
+        if artifacts.is_empty() {
+            return Err(anyhow::anyhow!("Build produced no packages"));
+        }
+
+        artifacts.sort();
+        Ok(artifacts)
+    }
+
+    fn remove_root(&self, root: &Path) -> Result<()> {
+        if root.exists() {
+            fs::remove_dir_all(root)
+                .with_context(|| format!("Failed to remove build root: {}", root.display()))?;
+        }
+        Ok(())
+    }
+
+    fn container_command(&self, root: &Path, user: Option<&str>, workdir: Option<&str>) -> Command {
+        match self.backend {
+            ChrootBackend::Nspawn => {
+                let mut command = Command::new("systemd-nspawn");
+                command.args(["-q", "--register=no", "--as-pid2", "-D"]).arg(root);
+                if let Some(repo_path) = &self.local_repo {
+                    command.arg(format!("--bind-ro={}:{}", repo_path.display(), LOCAL_REPO_MOUNT));
+                }
+                if let Some(user) = user {
+                    command.arg(format!("--user={}", user));
+                }
+                if let Some(workdir) = workdir {
+                    command.arg(format!("--chdir={}", workdir));
+                }
+                command
+            }
+            ChrootBackend::Bubblewrap => {
+                let mut command = Command::new("bwrap");
+                command
+                    .arg("--bind").arg(root).arg("/")
+                    .args(["--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"])
+                    .args(["--ro-bind", "/etc/resolv.conf", "/etc/resolv.conf"])
+                    .args(["--unshare-all", "--share-net", "--die-with-parent"]);
+                if let Some(repo_path) = &self.local_repo {
+                    command.arg("--ro-bind").arg(repo_path).arg(LOCAL_REPO_MOUNT);
+                }
+                if let Some(workdir) = workdir {
+                    command.args(["--chdir", workdir]);
+                }
+                if let Some(user) = user {
+                    command.args(["runuser", "-u", user, "--"]);
+                }
+                command
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::{Context, Result};
+use goblin::elf::Elf;
+use log::{error, warn};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::system::local_db::{dependency_name, LocalDatabase};
+use super::repo_db::{open_package, parse_pkginfo, PackageInfo};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LintFinding {
+    pub severity: Severity,
+    pub check: String,
+    pub path: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LintReport {
+    pub package: String,
+    pub file: PathBuf,
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.findings.iter().any(|f| f.severity == Severity::Error)
+    }
+
+    pub fn log(&self) {
+        for finding in &self.findings {
+            let path = finding.path.as_deref().map(|p| format!(" {}", p)).unwrap_or_default();
+            match finding.severity {
+                Severity::Warning => warn!("{} W: {}{}: {}", self.package, finding.check, path, finding.message),
+                Severity::Error => error!("{} E: {}{}: {}", self.package, finding.check, path, finding.message),
+            }
+        }
+    }
+}
+
+struct ArchiveFile {
+    path: String,
+    is_dir: bool,
+    is_symlink: bool,
+    mode: u32,
+    needed: Vec<String>,
+}
+
+pub struct PackageLinter {
+    db_path: PathBuf,
+}
+
+impl PackageLinter {
+    pub fn new() -> Self {
+        Self {
+            db_path: PathBuf::from("/var/lib/pacman/local"),
+        }
+    }
+
+    // قاعدة البيانات المستخدمة لمعرفة الحزمة المالكة لكل مكتبة
+    pub fn with_db_path(db_path: &Path) -> Self {
+        Self {
+            db_path: db_path.to_path_buf(),
+        }
+    }
+
+    pub fn lint(&self, package_path: &Path) -> Result<LintReport> {
+        let index = LibraryIndex::load(&self.db_path)?;
+        self.lint_with(package_path, &index)
+    }
+
+    // فهرس المكتبات يبنى مرة واحدة لكل الحزم بدلاً من قراءة قاعدة البيانات لكل مكتبة
+    pub fn lint_all(&self, package_paths: &[PathBuf]) -> Result<Vec<LintReport>> {
+        let index = LibraryIndex::load(&self.db_path)?;
+        package_paths.iter().map(|path| self.lint_with(path, &index)).collect()
+    }
+
+    fn lint_with(&self, package_path: &Path, index: &LibraryIndex) -> Result<LintReport> {
+        let (info, files) = read_archive(package_path)
+            .with_context(|| format!("Failed to read package: {}", package_path.display()))?;
+
+        let mut findings = Vec::new();
+        let mut finding = |severity: Severity, check: &str, path: Option<&str>, message: String| {
+            findings.push(LintFinding {
+                severity,
+                check: check.to_string(),
+                path: path.map(|p| p.to_string()),
+                message,
+            });
+        };
+
+        if info.licenses.is_empty() {
+            finding(Severity::Error, "missing-license", None, "no license declared".to_string());
+        }
+
+        for file in &files {
+            let path = Some(file.path.as_str());
+
+            // نبلغ عن الملفات فقط حتى لا يتكرر التحذير لكل مجلد أب
+            if !file.is_dir && file.path.starts_with("usr/local/") {
+                finding(Severity::Error, "file-in-usr-local", path, "packages must not install into /usr/local".to_string());
+            }
+            if !file.is_dir && file.path.starts_with("home/") {
+                finding(Severity::Error, "file-in-home", path, "packages must not install into /home".to_string());
+            }
+
+            if file.is_symlink {
+                continue;
+            }
+
+            // المجلدات القابلة للكتابة مع sticky bit مثل /tmp مقبولة
+            let sticky = file.is_dir && file.mode & 0o1000 != 0;
+            if file.mode & 0o002 != 0 && !sticky {
+                finding(Severity::Error, "world-writable", path, format!("mode {:o}", file.mode & 0o7777));
+            }
+            if file.mode & 0o4000 != 0 && !file.is_dir {
+                finding(Severity::Warning, "setuid", path, format!("mode {:o}", file.mode & 0o7777));
+            }
+            if file.mode & 0o2000 != 0 && !file.is_dir {
+                finding(Severity::Warning, "setgid", path, format!("mode {:o}", file.mode & 0o7777));
+            }
+        }
+
+        for dir in empty_directories(&files) {
+            finding(Severity::Warning, "empty-directory", Some(&dir), "directory contains no files".to_string());
+        }
+
+        for (library, users) in missing_libraries(&info, &files, index) {
+            let user = users.first().cloned();
+            match library {
+                MissingLibrary::Undeclared { soname, owner } => finding(
+                    Severity::Error,
+                    "dependency-not-declared",
+                    user.as_deref(),
+                    format!("links to {} from {} which is not in depends", soname, owner),
+                ),
+                MissingLibrary::Unknown(soname) => finding(
+                    Severity::Warning,
+                    "library-not-found",
+                    user.as_deref(),
+                    format!("no installed package provides {}", soname),
+                ),
+            }
+        }
+
+        Ok(LintReport {
+            package: info.name,
+            file: package_path.to_path_buf(),
+            findings,
+        })
+    }
+}
+
+struct LibraryIndex {
+    db: Option<LocalDatabase>,
+    // اسم المكتبة في /usr/lib والحزمة المالكة لها
+    owners: HashMap<String, String>,
+}
+
+impl LibraryIndex {
+    fn load(db_path: &Path) -> Result<Self> {
+        if !db_path.exists() {
+            return Ok(Self {
+                db: None,
+                owners: HashMap::new(),
+            });
+        }
+
+        let db = LocalDatabase::open(db_path)?;
+        let owners = db
+            .file_owners()?
+            .into_iter()
+            .filter_map(|(file, owner)| {
+                let name = file.strip_prefix("usr/lib/")?;
+                (!name.contains('/')).then(|| (name.to_string(), owner))
+            })
+            .collect();
+
+        Ok(Self { db: Some(db), owners })
+    }
+}
+
+fn missing_libraries(
+    info: &PackageInfo,
+    files: &[ArchiveFile],
+    index: &LibraryIndex,
+) -> BTreeMap<MissingLibrary, Vec<String>> {
+    let mut users: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for file in files {
+        for soname in &file.needed {
+            users.entry(soname.clone()).or_default().push(file.path.clone());
+        }
+    }
+
+    // مكتبات الحزمة نفسها لا تحتاج اعتمادية
+    let own: BTreeSet<&str> = files
+        .iter()
+        .filter_map(|f| f.path.rsplit('/').next())
+        .collect();
+
+    let declared: BTreeSet<&str> = info.depends.iter().map(|d| dependency_name(d)).collect();
+
+    let mut missing = BTreeMap::new();
+
+    for (soname, paths) in users {
+        if own.contains(soname.as_str()) || declared.contains(soname.as_str()) {
+            continue;
+        }
+
+        let Some(owner) = index.owners.get(&soname).cloned() else {
+            missing.insert(MissingLibrary::Unknown(soname), paths);
+            continue;
+        };
+
+        // الاعتمادية قد تكون على اسم توفره الحزمة المالكة
+        let satisfied = declared.contains(owner.as_str())
+            || index
+                .db
+                .as_ref()
+                .and_then(|db| db.get(&owner))
+                .is_some_and(|package| {
+                    package.provides.iter().any(|p| declared.contains(dependency_name(p)))
+                });
+
+        if !satisfied {
+            missing.insert(MissingLibrary::Undeclared { soname, owner }, paths);
+        }
+    }
+
+    missing
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MissingLibrary {
+    Undeclared { soname: String, owner: String },
+    Unknown(String),
+}
+
+fn read_archive(package_path: &Path) -> Result<(PackageInfo, Vec<ArchiveFile>)> {
+    let mut archive = open_package(package_path)?;
+    let mut pkginfo = None;
+    let mut files = Vec::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().trim_end_matches('/').to_string();
+        let header = entry.header().clone();
+
+        if path == ".PKGINFO" {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            pkginfo = Some(parse_pkginfo(&content)?);
+            continue;
+        }
+        if path.starts_with('.') {
+            continue;
+        }
+
+        let is_dir = header.entry_type().is_dir();
+        let is_symlink = header.entry_type().is_symlink();
+        let mut needed = Vec::new();
+
+        if header.entry_type().is_file() {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            if content.starts_with(b"\x7fELF") {
+                // ملفات ELF تالفة أو غير مدعومة لا توقف الفحص
+                if let Ok(elf) = Elf::parse(&content) {
+                    needed = elf.libraries.iter().map(|l| l.to_string()).collect();
+                }
+            }
+        }
+
+        files.push(ArchiveFile {
+            path,
+            is_dir,
+            is_symlink,
+            mode: header.mode()?,
+            needed,
+        });
+    }
+
+    let info = pkginfo.ok_or_else(|| anyhow::anyhow!("Missing .PKGINFO"))?;
+    Ok((info, files))
+}
+
+fn empty_directories(files: &[ArchiveFile]) -> Vec<String> {
+    let mut empty = Vec::new();
+    for dir in files.iter().filter(|f| f.is_dir) {
+        let prefix = format!("{}/", dir.path);
+        if !files.iter().any(|f| f.path.starts_with(&prefix)) {
+            empty.push(dir.path.clone());
+        }
+    }
+    empty
+}
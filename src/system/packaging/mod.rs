@@ -0,0 +1,10 @@
+pub mod builder;
+pub mod aur;
+pub mod srcinfo;
+pub mod chroot;
+pub mod repo_db;
+pub mod batch;
+pub mod reproducible;
+pub mod recipes;
+pub mod lint;
+pub mod signing;
@@ -0,0 +1,316 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::system::security::integrity::sha256_file;
+use super::builder::{is_remote_source, is_vcs_source, BuildSystem, PackageConfig};
+
+const VALID_ARCHES: [&str; 4] = ["x86_64", "aarch64", "i686", "any"];
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct RecipeState {
+    // بصمة الوصفة ومصادرها عند آخر بناء ناجح
+    fingerprints: BTreeMap<String, String>,
+}
+
+pub struct RecipeStore {
+    recipes_path: PathBuf,
+    state_path: PathBuf,
+}
+
+impl RecipeStore {
+    pub fn new() -> Self {
+        Self {
+            recipes_path: PathBuf::from("/etc/xbitos/recipes"),
+            state_path: PathBuf::from("/var/lib/xbitos/recipes.json"),
+        }
+    }
+
+    pub fn with_paths(recipes_path: &Path, state_path: &Path) -> Self {
+        Self {
+            recipes_path: recipes_path.to_path_buf(),
+            state_path: state_path.to_path_buf(),
+        }
+    }
+
+    pub fn get_recipes_path(&self) -> &PathBuf {
+        &self.recipes_path
+    }
+
+    fn recipe_file(&self, name: &str) -> PathBuf {
+        self.recipes_path.join(format!("{}.toml", name))
+    }
+
+    pub fn names(&self) -> Result<Vec<String>> {
+        if !self.recipes_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&self.recipes_path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+            .collect();
+
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn load(&self, name: &str) -> Result<PackageConfig> {
+        let path = self.recipe_file(name);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Recipe not found: {}", path.display()))?;
+
+        let mut config: PackageConfig = toml::from_str(&content)
+            .with_context(|| format!("Invalid recipe {}", path.display()))?;
+
+        let errors = validate(&config, name);
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!("Invalid recipe {}:\n  {}", path.display(), errors.join("\n  ")));
+        }
+
+        // الملفات المحلية تبحث أولاً في مجلد يحمل اسم الوصفة
+        let recipe_dir = self.recipes_path.join(name);
+        config.recipe_dir = Some(if recipe_dir.is_dir() { recipe_dir } else { self.recipes_path.clone() });
+
+        Ok(config)
+    }
+
+    pub fn load_all(&self) -> Result<Vec<PackageConfig>> {
+        let mut configs = Vec::new();
+        let mut errors = Vec::new();
+
+        for name in self.names()? {
+            match self.load(&name) {
+                Ok(config) => configs.push(config),
+                Err(e) => errors.push(format!("{:#}", e)),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!("{}", errors.join("\n")));
+        }
+
+        Ok(configs)
+    }
+
+    // تعديل الإصدار مع الحفاظ على التعليقات وتنسيق الملف
+    pub fn bump(&self, name: &str, version: Option<&str>) -> Result<PackageConfig> {
+        let current = self.load(name)?;
+        let path = self.recipe_file(name);
+        let original = fs::read_to_string(&path)?;
+        let mut document: toml_edit::DocumentMut = original.parse()?;
+
+        match version {
+            Some(version) => {
+                document["version"] = toml_edit::value(version);
+                document["release"] = toml_edit::value("1");
+            }
+            None => {
+                let release: u32 = current
+                    .release
+                    .split('.')
+                    .next()
+                    .and_then(|r| r.parse().ok())
+                    .ok_or_else(|| anyhow::anyhow!("Cannot bump release: {}", current.release))?;
+                document["release"] = toml_edit::value((release + 1).to_string());
+            }
+        }
+
+        fs::write(&path, document.to_string())?;
+
+        // إعادة الملف الأصلي إذا أصبحت الوصفة غير صالحة
+        let config = match self.load(name) {
+            Ok(config) => config,
+            Err(e) => {
+                fs::write(&path, original)?;
+                return Err(e);
+            }
+        };
+        info!("{}: {}-{} -> {}-{}", name, current.version, current.release, config.version, config.release);
+
+        Ok(config)
+    }
+
+    // البصمة تشمل الوصفة والمصادر المحلية وآخر التزام للمصادر من git
+    pub fn fingerprint(&self, config: &PackageConfig) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(fs::read(self.recipe_file(&config.name))?);
+
+        let recipe_dir = config.recipe_dir.clone().unwrap_or_else(|| self.recipes_path.clone());
+        let local_files = config.source.iter().chain(config.install.iter());
+
+        for source in local_files {
+            hasher.update(source.as_bytes());
+            if is_vcs_source(source) {
+                hasher.update(vcs_revision(source)?.as_bytes());
+            } else if !is_remote_source(source) {
+                // المصادر البعيدة مثبتة بمجموع sha256 داخل الوصفة نفسها
+                hasher.update(sha256_file(&recipe_dir.join(source))?.as_bytes());
+            }
+        }
+
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    fn load_state(&self) -> Result<RecipeState> {
+        if !self.state_path.exists() {
+            return Ok(RecipeState::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(&self.state_path)?)?)
+    }
+
+    pub fn changed(&self) -> Result<Vec<PackageConfig>> {
+        let state = self.load_state()?;
+        let mut changed = Vec::new();
+
+        for config in self.load_all()? {
+            let fingerprint = self.fingerprint(&config)?;
+            if state.fingerprints.get(&config.name) != Some(&fingerprint) {
+                changed.push(config);
+            }
+        }
+
+        Ok(changed)
+    }
+
+    pub fn record_built(&self, config: &PackageConfig) -> Result<()> {
+        let mut state = self.load_state()?;
+        state.fingerprints.insert(config.name.clone(), self.fingerprint(config)?);
+
+        if let Some(parent) = self.state_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.state_path, serde_json::to_string_pretty(&state)?)?;
+        Ok(())
+    }
+}
+
+pub fn validate(config: &PackageConfig, file_stem: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if config.name != file_stem {
+        errors.push(format!("name '{}' does not match file name '{}.toml'", config.name, file_stem));
+    }
+    if !is_valid_name(&config.name) {
+        errors.push(format!("invalid package name '{}'", config.name));
+    }
+    // الأحرف التي يقبلها makepkg في pkgver فقط
+    if config.version.is_empty()
+        || !config.version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '~'))
+    {
+        errors.push(format!("invalid version '{}' (letters, digits, '.', '_', '+' and '~' only)", config.version));
+    }
+    if !config.release.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        || config.release.split('.').count() > 2
+    {
+        errors.push(format!("invalid release '{}' (expected a number like 1 or 1.1)", config.release));
+    }
+    if config.description.trim().is_empty() {
+        errors.push("description must not be empty".to_string());
+    }
+    if config.license.is_empty() {
+        errors.push("license must not be empty".to_string());
+    }
+    for arch in &config.arch {
+        if !VALID_ARCHES.contains(&arch.as_str()) {
+            errors.push(format!("unsupported arch '{}'", arch));
+        }
+    }
+    if config.arch.iter().any(|a| a == "any") && config.arch.len() > 1 {
+        errors.push("arch 'any' cannot be combined with other architectures".to_string());
+    }
+
+    for (key, sums, length) in [
+        ("sha256sums", &config.sha256sums, 64),
+        ("b2sums", &config.b2sums, 128),
+        ("sha512sums", &config.sha512sums, 128),
+    ] {
+        if !sums.is_empty() && sums.len() != config.source.len() {
+            errors.push(format!("{} {} for {} sources", sums.len(), key, config.source.len()));
+        }
+        for checksum in sums {
+            if !checksum.is_empty() && checksum != "SKIP" && !is_hex(checksum, length) {
+                errors.push(format!("invalid {} entry '{}'", key, checksum));
+            }
+        }
+    }
+    for (index, source) in config.source.iter().enumerate() {
+        let pinned = [&config.sha256sums, &config.b2sums, &config.sha512sums]
+            .iter()
+            .any(|sums| sums.get(index).is_some_and(|sum| !sum.is_empty() && sum != "SKIP"));
+        if is_remote_source(source) && !is_vcs_source(source) && !pinned {
+            errors.push(format!("remote source needs a pinned checksum: {}", source));
+        }
+    }
+
+    for dependency in config.dependencies.iter().chain(&config.build_dependencies) {
+        if !is_valid_name(crate::system::local_db::dependency_name(dependency)) {
+            errors.push(format!("invalid dependency '{}'", dependency));
+        }
+    }
+
+    if let BuildSystem::Custom { build, package, .. } = &config.build_system {
+        if build.trim().is_empty() || package.trim().is_empty() {
+            errors.push("custom build_system needs non-empty build and package steps".to_string());
+        }
+    }
+
+    for key in config.variables.keys() {
+        if !key.starts_with('_') || !key[1..].chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            errors.push(format!("variable '{}' must start with '_' and be a valid shell name", key));
+        }
+    }
+
+    errors
+}
+
+// نفس قواعد makepkg لأسماء الحزم
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['-', '.'])
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "@._+-".contains(c))
+}
+
+fn is_hex(value: &str, length: usize) -> bool {
+    value.len() == length && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn vcs_revision(source: &str) -> Result<String> {
+    let url = source.split_once("::").map_or(source, |(_, url)| url);
+    let (url, fragment) = url.split_once('#').unwrap_or((url, ""));
+    let url = url.trim_start_matches("git+");
+
+    // الالتزام المحدد في الوصفة لا يتغير
+    let reference = match fragment.split_once('=') {
+        Some(("commit", commit)) => return Ok(commit.to_string()),
+        Some(("tag", tag)) => format!("refs/tags/{}", tag),
+        Some(("branch", branch)) => format!("refs/heads/{}", branch),
+        _ => "HEAD".to_string(),
+    };
+
+    if !source.contains("git+") {
+        warn!("Cannot track revisions of non-git source: {}", source);
+        return Ok(String::new());
+    }
+
+    let output = Command::new("git")
+        .args(["ls-remote", url, &reference])
+        .output()
+        .context("Failed to run git ls-remote")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("Failed to query {}", url));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
@@ -0,0 +1,615 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{info, warn};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use crate::system::local_db;
+use crate::system::security::integrity::sha256_file;
+use base64::Engine;
+use super::signing::{signature_path, PackageSigner};
+
+#[derive(Clone, Debug, Default)]
+pub struct PackageInfo {
+    pub name: String,
+    pub base: Option<String>,
+    pub version: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub arch: String,
+    pub build_date: Option<String>,
+    pub packager: Option<String>,
+    pub installed_size: u64,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub depends: Vec<String>,
+    pub optdepends: Vec<String>,
+    pub makedepends: Vec<String>,
+    pub checkdepends: Vec<String>,
+    pub provides: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub replaces: Vec<String>,
+    pub backup: Vec<String>,
+    pub files: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+struct RepoEntry {
+    name: String,
+    version: String,
+    filename: String,
+    desc: String,
+    files: String,
+}
+
+pub struct RepoDatabase {
+    repo_path: PathBuf,
+    name: String,
+    entries: BTreeMap<String, RepoEntry>,
+    prune: bool,
+    signer: Option<PackageSigner>,
+}
+
+impl RepoDatabase {
+    pub fn open(repo_path: &Path, name: &str) -> Result<Self> {
+        let mut database = Self {
+            repo_path: repo_path.to_path_buf(),
+            name: name.to_string(),
+            entries: BTreeMap::new(),
+            prune: true,
+            signer: None,
+        };
+
+        let files_db = database.db_file("files");
+        let db = database.db_file("db");
+
+        // قاعدة files تحتوي على desc أيضاً لذلك نفضلها عند وجودها
+        if files_db.exists() {
+            database.load(&files_db)?;
+        } else if db.exists() {
+            database.load(&db)?;
+        }
+
+        Ok(database)
+    }
+
+    pub fn keep_old_packages(mut self, keep: bool) -> Self {
+        self.prune = !keep;
+        self
+    }
+
+    // توقيع الحزم وقاعدة البيانات عند الكتابة كما في repo-add --sign
+    pub fn sign_with(mut self, signer: Option<PackageSigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    pub fn packages(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.values().map(|e| (e.name.as_str(), e.version.as_str()))
+    }
+
+    pub fn get_filename(&self, name: &str) -> Option<&str> {
+        self.entries.get(name).map(|e| e.filename.as_str())
+    }
+
+    pub fn get_desc(&self, name: &str) -> Option<&str> {
+        self.entries.get(name).map(|e| e.desc.as_str())
+    }
+
+    fn db_file(&self, kind: &str) -> PathBuf {
+        self.repo_path.join(format!("{}.{}.tar.gz", self.name, kind))
+    }
+
+    fn load(&mut self, path: &Path) -> Result<()> {
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+        let mut descs: BTreeMap<String, String> = BTreeMap::new();
+        let mut files: BTreeMap<String, String> = BTreeMap::new();
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.to_string_lossy().to_string();
+            let Some((dir, file)) = entry_path.trim_end_matches('/').split_once('/') else {
+                continue;
+            };
+
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+
+            match file {
+                "desc" => { descs.insert(dir.to_string(), content); }
+                "files" => { files.insert(dir.to_string(), content); }
+                _ => {}
+            }
+        }
+
+        for (dir, desc) in descs {
+            let mut sections = local_db::sections(&desc);
+            let mut single = |key: &str| {
+                sections.remove(key).and_then(|v| v.into_iter().next()).unwrap_or_default()
+            };
+
+            let entry = RepoEntry {
+                name: single("NAME"),
+                version: single("VERSION"),
+                filename: single("FILENAME"),
+                files: files.remove(&dir).unwrap_or_default(),
+                desc,
+            };
+            self.entries.insert(entry.name.clone(), entry);
+        }
+
+        Ok(())
+    }
+
+    // إضافة الحزمة فقط إذا كانت أحدث من المسجلة، كما في repo-add -n
+    pub fn add_if_newer(&mut self, package_path: &Path) -> Result<bool> {
+        let info = read_package_info(package_path)?;
+
+        if let Some(existing) = self.entries.get(&info.name) {
+            let file_name = package_path.file_name().unwrap_or_default().to_string_lossy();
+            match vercmp(&info.version, &existing.version) {
+                Ordering::Greater => {}
+                // الإصدار نفسه بملف مختلف، مثلاً ضغط آخر، يستبدل القديم ويحذف الملف القديم فقط
+                Ordering::Equal if existing.filename != file_name => {}
+                _ => return Ok(false),
+            }
+        }
+
+        self.add_info(info, package_path)?;
+        Ok(true)
+    }
+
+    pub fn add(&mut self, package_path: &Path) -> Result<()> {
+        let info = read_package_info(package_path)?;
+        self.add_info(info, package_path)
+    }
+
+    fn add_info(&mut self, info: PackageInfo, package_path: &Path) -> Result<()> {
+        let filename = package_path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Invalid package path: {}", package_path.display()))?
+            .to_string_lossy()
+            .to_string();
+
+        if let Some(existing) = self.entries.get(&info.name) {
+            match vercmp(&info.version, &existing.version) {
+                Ordering::Less => warn!(
+                    "Replacing {} {} with older version {}",
+                    info.name, existing.version, info.version
+                ),
+                Ordering::Equal if existing.filename == filename => {}
+                _ => info!("Updating {} {} -> {}", info.name, existing.version, info.version),
+            }
+        } else {
+            info!("Adding {} {}", info.name, info.version);
+        }
+
+        if let Some(signer) = &self.signer {
+            if signer.needs_signature(package_path)? {
+                info!("Signing {}", filename);
+                signer.sign(package_path)?;
+            }
+        }
+
+        let entry = RepoEntry {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            desc: render_desc(&info, &filename, package_path)?,
+            files: render_files(&info),
+            filename,
+        };
+
+        let previous = self.entries.insert(info.name.clone(), entry);
+
+        // حذف الإصدار القديم من المستودع بعد استبداله
+        if let Some(previous) = previous {
+            if self.prune && previous.filename != self.entries[&info.name].filename {
+                self.remove_package_file(&previous.filename)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        match self.entries.remove(name) {
+            Some(entry) => {
+                info!("Removing {} {}", entry.name, entry.version);
+                if self.prune {
+                    self.remove_package_file(&entry.filename)?;
+                }
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // حذف ملفات الحزم التي لم تعد مسجلة في قاعدة البيانات
+    pub fn prune_unreferenced(&self) -> Result<Vec<PathBuf>> {
+        let referenced: HashSet<&str> = self.entries.values().map(|e| e.filename.as_str()).collect();
+        let mut removed = Vec::new();
+
+        for entry in fs::read_dir(&self.repo_path)?.filter_map(|entry| entry.ok()) {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let package_name = file_name.trim_end_matches(".sig");
+
+            if is_package_file(package_name) && !referenced.contains(package_name) {
+                fs::remove_file(entry.path())?;
+                removed.push(entry.path());
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn remove_package_file(&self, filename: &str) -> Result<()> {
+        for name in [filename.to_string(), format!("{}.sig", filename)] {
+            let path = self.repo_path.join(&name);
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self) -> Result<()> {
+        fs::create_dir_all(&self.repo_path)?;
+
+        self.write_archive("db", false)?;
+        self.write_archive("files", true)?;
+
+        info!("Repository {} updated with {} packages", self.name, self.entries.len());
+        Ok(())
+    }
+
+    fn write_archive(&self, kind: &str, with_files: bool) -> Result<()> {
+        let target = self.db_file(kind);
+        let temp = self.repo_path.join(format!(".{}.{}.tar.gz.tmp", self.name, kind));
+
+        {
+            let encoder = GzEncoder::new(File::create(&temp)?, Compression::default());
+            let mut builder = tar::Builder::new(encoder);
+
+            for entry in self.entries.values() {
+                let dir = format!("{}-{}", entry.name, entry.version);
+                append_dir(&mut builder, &dir)?;
+                append_file(&mut builder, &format!("{}/desc", dir), &entry.desc)?;
+                if with_files {
+                    append_file(&mut builder, &format!("{}/files", dir), &entry.files)?;
+                }
+            }
+
+            builder.into_inner()?.finish()?.sync_all()?;
+        }
+
+        let target_signature = signature_path(&target);
+        let link = self.repo_path.join(format!("{}.{}", self.name, kind));
+
+        match &self.signer {
+            Some(signer) => {
+                let temp_signature = signature_path(&temp);
+                signer.sign_to(&temp, &temp_signature)?;
+                fs::rename(&temp_signature, &target_signature)?;
+            }
+            // توقيع قديم لا يطابق القاعدة الجديدة سيرفضه pacman
+            None if target_signature.exists() => fs::remove_file(&target_signature)?,
+            None => {}
+        }
+
+        // الاستبدال الذري حتى لا يرى pacman قاعدة بيانات ناقصة
+        fs::rename(&temp, &target)?;
+
+        self.update_link(&target, &link)?;
+        if self.signer.is_some() {
+            self.update_link(&target_signature, &signature_path(&link))?;
+        } else if signature_path(&link).is_symlink() {
+            fs::remove_file(signature_path(&link))?;
+        }
+
+        Ok(())
+    }
+
+    fn update_link(&self, target: &Path, link: &Path) -> Result<()> {
+        let temp_link = self.repo_path.join(format!(
+            ".{}.tmp",
+            link.file_name().unwrap_or_default().to_string_lossy()
+        ));
+
+        if temp_link.exists() || temp_link.is_symlink() {
+            fs::remove_file(&temp_link)?;
+        }
+        std::os::unix::fs::symlink(target.file_name().unwrap(), &temp_link)?;
+        fs::rename(&temp_link, link)?;
+
+        Ok(())
+    }
+}
+
+fn append_dir<W: io::Write>(builder: &mut tar::Builder<W>, path: &str) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_mode(0o755);
+    header.set_size(0);
+    header.set_mtime(0);
+    builder.append_data(&mut header, format!("{}/", path), io::empty())?;
+    Ok(())
+}
+
+fn append_file<W: io::Write>(builder: &mut tar::Builder<W>, path: &str, content: &str) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_size(content.len() as u64);
+    header.set_mtime(0);
+    builder.append_data(&mut header, path, content.as_bytes())?;
+    Ok(())
+}
+
+pub fn is_package_file(file_name: &str) -> bool {
+    [".pkg.tar.zst", ".pkg.tar.xz", ".pkg.tar.gz", ".pkg.tar"]
+        .iter()
+        .any(|ext| file_name.ends_with(ext))
+}
+
+pub fn open_package(path: &Path) -> Result<tar::Archive<Box<dyn Read>>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open package: {}", path.display()))?;
+    let name = path.to_string_lossy();
+
+    let reader: Box<dyn Read> = if name.ends_with(".zst") {
+        Box::new(zstd::Decoder::new(file)?)
+    } else if name.ends_with(".xz") {
+        Box::new(xz2::read::XzDecoder::new(file))
+    } else if name.ends_with(".gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    Ok(tar::Archive::new(reader))
+}
+
+pub fn read_package_info(path: &Path) -> Result<PackageInfo> {
+    let mut archive = open_package(path)?;
+    let mut pkginfo = None;
+    let mut files = Vec::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().to_string();
+
+        if entry_path == ".PKGINFO" {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            pkginfo = Some(content);
+        } else if !entry_path.starts_with('.') {
+            let mut file = entry_path.trim_start_matches("./").to_string();
+            if entry.header().entry_type().is_dir() && !file.ends_with('/') {
+                file.push('/');
+            }
+            files.push(file);
+        }
+    }
+
+    let pkginfo = pkginfo
+        .ok_or_else(|| anyhow::anyhow!("Missing .PKGINFO in {}", path.display()))?;
+
+    let mut info = parse_pkginfo(&pkginfo)?;
+    files.sort();
+    info.files = files;
+    Ok(info)
+}
+
+pub fn parse_pkginfo(content: &str) -> Result<PackageInfo> {
+    let mut info = PackageInfo::default();
+
+    for line in content.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let value = value.to_string();
+
+        match key {
+            "pkgname" => info.name = value,
+            "pkgbase" => info.base = Some(value),
+            "pkgver" => info.version = value,
+            "pkgdesc" => info.description = value,
+            "url" => info.url = Some(value),
+            "builddate" => info.build_date = Some(value),
+            "packager" => info.packager = Some(value),
+            "size" => info.installed_size = value.parse().unwrap_or(0),
+            "arch" => info.arch = value,
+            "license" => info.licenses.push(value),
+            "group" => info.groups.push(value),
+            "depend" => info.depends.push(value),
+            "optdepend" => info.optdepends.push(value),
+            "makedepend" => info.makedepends.push(value),
+            "checkdepend" => info.checkdepends.push(value),
+            "provides" => info.provides.push(value),
+            "conflict" => info.conflicts.push(value),
+            "replaces" => info.replaces.push(value),
+            "backup" => info.backup.push(value),
+            _ => {}
+        }
+    }
+
+    if info.name.is_empty() || info.version.is_empty() {
+        return Err(anyhow::anyhow!("Incomplete .PKGINFO: missing pkgname or pkgver"));
+    }
+
+    Ok(info)
+}
+
+fn render_desc(info: &PackageInfo, filename: &str, package_path: &Path) -> Result<String> {
+    let mut desc = String::new();
+
+    let mut field = |key: &str, values: &[String]| {
+        if !values.is_empty() {
+            desc.push_str(&format!("%{}%\n{}\n\n", key, values.join("\n")));
+        }
+    };
+
+    let one = |value: &Option<String>| value.iter().cloned().collect::<Vec<_>>();
+
+    field("FILENAME", &[filename.to_string()]);
+    field("NAME", std::slice::from_ref(&info.name));
+    field("BASE", &one(&info.base));
+    field("VERSION", std::slice::from_ref(&info.version));
+    field("DESC", std::slice::from_ref(&info.description));
+    field("GROUPS", &info.groups);
+    field("CSIZE", &[fs::metadata(package_path)?.len().to_string()]);
+    field("ISIZE", &[info.installed_size.to_string()]);
+    field("SHA256SUM", &[sha256_file(package_path)?]);
+
+    // pacman يقرأ التوقيع من قاعدة البيانات بدلاً من تنزيل ملف .sig
+    let signature = signature_path(package_path);
+    if signature.exists() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(fs::read(&signature)?);
+        field("PGPSIG", &[encoded]);
+    }
+    field("URL", &one(&info.url));
+    field("LICENSE", &info.licenses);
+    field("ARCH", std::slice::from_ref(&info.arch));
+    field("BUILDDATE", &one(&info.build_date));
+    field("PACKAGER", &one(&info.packager));
+    field("REPLACES", &info.replaces);
+    field("CONFLICTS", &info.conflicts);
+    field("PROVIDES", &info.provides);
+    field("DEPENDS", &info.depends);
+    field("OPTDEPENDS", &info.optdepends);
+    field("MAKEDEPENDS", &info.makedepends);
+    field("CHECKDEPENDS", &info.checkdepends);
+
+    Ok(desc)
+}
+
+fn render_files(info: &PackageInfo) -> String {
+    format!("%FILES%\n{}\n", info.files.join("\n"))
+}
+
+// مقارنة الإصدارات بنفس خوارزمية vercmp في pacman
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let (epoch_a, version_a, release_a) = split_evr(a);
+    let (epoch_b, version_b, release_b) = split_evr(b);
+
+    let epoch_a: u64 = epoch_a.parse().unwrap_or(0);
+    let epoch_b: u64 = epoch_b.parse().unwrap_or(0);
+
+    epoch_a
+        .cmp(&epoch_b)
+        .then_with(|| rpmvercmp(version_a, version_b))
+        .then_with(|| match (release_a, release_b) {
+            (Some(ra), Some(rb)) => rpmvercmp(ra, rb),
+            _ => Ordering::Equal,
+        })
+}
+
+fn split_evr(evr: &str) -> (&str, &str, Option<&str>) {
+    let (epoch, rest) = match evr.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => (epoch, rest),
+        _ => ("0", evr),
+    };
+
+    match rest.rsplit_once('-') {
+        Some((version, release)) => (epoch, version, Some(release)),
+        None => (epoch, rest, None),
+    }
+}
+
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let (start_i, start_j) = (i, j);
+        while i < a.len() && !a[i].is_ascii_alphanumeric() {
+            i += 1;
+        }
+        while j < b.len() && !b[j].is_ascii_alphanumeric() {
+            j += 1;
+        }
+
+        if i >= a.len() || j >= b.len() {
+            break;
+        }
+
+        // اختلاف عدد الفواصل يعني أن الإصدار ذو الفواصل الأكثر هو الأحدث
+        if (i - start_i) != (j - start_j) {
+            return (i - start_i).cmp(&(j - start_j));
+        }
+
+        let numeric = a[i].is_ascii_digit();
+        let (seg_a_start, seg_b_start) = (i, j);
+
+        if numeric {
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+        } else {
+            while i < a.len() && a[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_alphabetic() {
+                j += 1;
+            }
+        }
+
+        let seg_a = &a[seg_a_start..i];
+        let seg_b = &b[seg_b_start..j];
+
+        if seg_b.is_empty() {
+            // مقطع رقمي مقابل مقطع حرفي: الرقمي أحدث
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+
+        let ordering = if numeric {
+            let seg_a = trim_leading_zeros(seg_a);
+            let seg_b = trim_leading_zeros(seg_b);
+            seg_a.len().cmp(&seg_b.len()).then_with(|| seg_a.cmp(seg_b))
+        } else {
+            seg_a.cmp(seg_b)
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    let rest_a = &a[i.min(a.len())..];
+    let rest_b = &b[j.min(b.len())..];
+
+    match (rest_a.is_empty(), rest_b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        // ما تبقى حرفياً يعني إصداراً تجريبياً أقدم مثل 1.0alpha
+        (true, false) => {
+            if rest_b[0].is_ascii_alphabetic() { Ordering::Greater } else { Ordering::Less }
+        }
+        (false, true) => {
+            if rest_a[0].is_ascii_alphabetic() { Ordering::Less } else { Ordering::Greater }
+        }
+        (false, false) => Ordering::Equal,
+    }
+}
+
+fn trim_leading_zeros(segment: &[u8]) -> &[u8] {
+    let start = segment.iter().position(|&c| c != b'0').unwrap_or(segment.len());
+    &segment[start..]
+}
@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::system::security::integrity::sha256_file;
+use super::repo_db::open_package;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum DifferenceKind {
+    OnlyInFirst,
+    OnlyInSecond,
+    Metadata,
+    Content,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveDifference {
+    pub path: String,
+    pub kind: DifferenceKind,
+    pub field: Option<String>,
+    pub first: String,
+    pub second: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReproducibilityReport {
+    pub package: String,
+    pub source_date_epoch: i64,
+    pub first: PathBuf,
+    pub second: PathBuf,
+    pub reproducible: bool,
+    pub differences: Vec<ArchiveDifference>,
+    pub checked_at: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct ArchiveEntry {
+    entry_type: String,
+    mode: u32,
+    uid: u64,
+    gid: u64,
+    owner: String,
+    mtime: u64,
+    size: u64,
+    link: Option<String>,
+    sha256: String,
+    // ملفات البيانات الوصفية تقارن سطراً بسطر
+    text: Option<Vec<String>>,
+}
+
+const METADATA_FILES: [&str; 3] = [".PKGINFO", ".BUILDINFO", ".MTREE"];
+
+pub fn compare_packages(first: &Path, second: &Path) -> Result<Vec<ArchiveDifference>> {
+    let first_entries = read_entries(first)?;
+    let second_entries = read_entries(second)?;
+    let mut differences = Vec::new();
+
+    let paths: BTreeSet<&String> = first_entries.keys().chain(second_entries.keys()).collect();
+    for path in paths {
+        match (first_entries.get(path), second_entries.get(path)) {
+            (Some(a), Some(b)) => compare_entry(path, a, b, &mut differences),
+            (Some(a), None) => differences.push(ArchiveDifference {
+                path: path.clone(),
+                kind: DifferenceKind::OnlyInFirst,
+                field: None,
+                first: a.entry_type.clone(),
+                second: String::new(),
+            }),
+            (None, Some(b)) => differences.push(ArchiveDifference {
+                path: path.clone(),
+                kind: DifferenceKind::OnlyInSecond,
+                field: None,
+                first: String::new(),
+                second: b.entry_type.clone(),
+            }),
+            (None, None) => {}
+        }
+    }
+
+    // اختلاف ترتيب المدخلات أو الضغط وحده يجعل الأرشيف غير مطابق بايت ببايت
+    if differences.is_empty() {
+        let (first_hash, second_hash) = (sha256_file(first)?, sha256_file(second)?);
+        if first_hash != second_hash {
+            differences.push(ArchiveDifference {
+                path: String::new(),
+                kind: DifferenceKind::Content,
+                field: Some("archive".to_string()),
+                first: first_hash,
+                second: second_hash,
+            });
+        }
+    }
+
+    Ok(differences)
+}
+
+fn compare_entry(path: &str, a: &ArchiveEntry, b: &ArchiveEntry, differences: &mut Vec<ArchiveDifference>) {
+    let mut metadata = |field: &str, first: String, second: String| {
+        if first != second {
+            differences.push(ArchiveDifference {
+                path: path.to_string(),
+                kind: DifferenceKind::Metadata,
+                field: Some(field.to_string()),
+                first,
+                second,
+            });
+        }
+    };
+
+    metadata("type", a.entry_type.clone(), b.entry_type.clone());
+    metadata("mode", format!("{:o}", a.mode), format!("{:o}", b.mode));
+    metadata("owner", format!("{}:{} ({})", a.uid, a.gid, a.owner), format!("{}:{} ({})", b.uid, b.gid, b.owner));
+    metadata("mtime", a.mtime.to_string(), b.mtime.to_string());
+    metadata("link", a.link.clone().unwrap_or_default(), b.link.clone().unwrap_or_default());
+
+    if a.sha256 == b.sha256 {
+        return;
+    }
+
+    match (&a.text, &b.text) {
+        (Some(first_lines), Some(second_lines)) => {
+            // عرض الأسطر المختلفة فقط مثل builddate في .PKGINFO
+            let first_set: BTreeSet<&String> = first_lines.iter().collect();
+            let second_set: BTreeSet<&String> = second_lines.iter().collect();
+            let removed: Vec<&str> = first_lines.iter().filter(|l| !second_set.contains(l)).map(|l| l.as_str()).collect();
+            let added: Vec<&str> = second_lines.iter().filter(|l| !first_set.contains(l)).map(|l| l.as_str()).collect();
+
+            differences.push(ArchiveDifference {
+                path: path.to_string(),
+                kind: DifferenceKind::Content,
+                field: None,
+                first: removed.join("\n"),
+                second: added.join("\n"),
+            });
+        }
+        _ => differences.push(ArchiveDifference {
+            path: path.to_string(),
+            kind: DifferenceKind::Content,
+            field: Some("sha256".to_string()),
+            first: format!("{} bytes, sha256 {}", a.size, a.sha256),
+            second: format!("{} bytes, sha256 {}", b.size, b.sha256),
+        }),
+    }
+}
+
+fn read_entries(path: &Path) -> Result<BTreeMap<String, ArchiveEntry>> {
+    let mut archive = open_package(path)
+        .with_context(|| format!("Failed to open package: {}", path.display()))?;
+    let mut entries = BTreeMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().trim_end_matches('/').to_string();
+        let header = entry.header().clone();
+
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+
+        let text = if METADATA_FILES.contains(&name.as_str()) {
+            let raw = if name == ".MTREE" {
+                let mut decoded = Vec::new();
+                GzDecoder::new(content.as_slice()).read_to_end(&mut decoded)?;
+                decoded
+            } else {
+                content.clone()
+            };
+            Some(String::from_utf8_lossy(&raw).lines().map(|l| l.to_string()).collect())
+        } else {
+            None
+        };
+
+        let entry_type = match header.entry_type() {
+            tar::EntryType::Directory => "dir",
+            tar::EntryType::Symlink => "link",
+            tar::EntryType::Link => "hardlink",
+            _ => "file",
+        };
+
+        entries.insert(name, ArchiveEntry {
+            entry_type: entry_type.to_string(),
+            mode: header.mode()?,
+            uid: header.uid()?,
+            gid: header.gid()?,
+            owner: format!(
+                "{}:{}",
+                header.username().ok().flatten().unwrap_or(""),
+                header.groupname().ok().flatten().unwrap_or("")
+            ),
+            mtime: header.mtime()?,
+            size: content.len() as u64,
+            link: header.link_name()?.map(|l| l.to_string_lossy().to_string()),
+            sha256: hex::encode(Sha256::digest(&content)),
+            text,
+        });
+    }
+
+    Ok(entries)
+}
+
+pub fn save_report(reports_path: &Path, report: &ReproducibilityReport) -> Result<()> {
+    fs::create_dir_all(reports_path)?;
+    fs::write(
+        reports_path.join(format!("{}.json", report.package)),
+        serde_json::to_string_pretty(report)?,
+    )?;
+    Ok(())
+}
+
+pub fn load_reports(reports_path: &Path) -> Result<Vec<ReproducibilityReport>> {
+    if !reports_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut reports = Vec::new();
+    for entry in fs::read_dir(reports_path)?.filter_map(|entry| entry.ok()) {
+        if entry.path().extension().is_some_and(|ext| ext == "json") {
+            reports.push(serde_json::from_str(&fs::read_to_string(entry.path())?)?);
+        }
+    }
+
+    reports.sort_by(|a: &ReproducibilityReport, b| a.package.cmp(&b.package));
+    Ok(reports)
+}
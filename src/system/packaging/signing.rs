@@ -0,0 +1,346 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SigningConfig {
+    pub key_id: String,
+    pub gnupg_home: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SigningKey {
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    pub created: Option<i64>,
+    pub expires: Option<i64>,
+    pub has_secret: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct PackageSigner {
+    gnupg_home: PathBuf,
+    key_id: String,
+}
+
+impl PackageSigner {
+    pub fn new(gnupg_home: &Path, key_id: &str) -> Self {
+        Self {
+            gnupg_home: gnupg_home.to_path_buf(),
+            key_id: key_id.to_string(),
+        }
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    // توقيع منفصل ثنائي بجانب الملف كما يتوقعه pacman
+    pub fn sign(&self, path: &Path) -> Result<PathBuf> {
+        let signature = signature_path(path);
+        self.sign_to(path, &signature)?;
+        Ok(signature)
+    }
+
+    pub fn sign_to(&self, path: &Path, signature: &Path) -> Result<()> {
+        let status = gpg(&self.gnupg_home)
+            .args(["--yes", "--no-armor", "--detach-sign", "--local-user", &self.key_id])
+            .arg("--output")
+            .arg(signature)
+            .arg(path)
+            .status()
+            .context("Failed to run gpg")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to sign {}", path.display()));
+        }
+
+        Ok(())
+    }
+
+    pub fn verify(&self, path: &Path, signature: &Path) -> Result<bool> {
+        let status = gpg(&self.gnupg_home)
+            .arg("--verify")
+            .arg(signature)
+            .arg(path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to run gpg")?;
+
+        Ok(status.success())
+    }
+
+    // التوقيع قديم إذا لم يوجد أو كان أقدم من الملف نفسه
+    pub fn needs_signature(&self, path: &Path) -> Result<bool> {
+        let signature = signature_path(path);
+        if !signature.exists() {
+            return Ok(true);
+        }
+        Ok(fs::metadata(&signature)?.modified()? < fs::metadata(path)?.modified()?)
+    }
+}
+
+pub struct KeyManager {
+    gnupg_home: PathBuf,
+    config_path: PathBuf,
+}
+
+impl KeyManager {
+    pub fn new() -> Self {
+        Self {
+            gnupg_home: PathBuf::from("/etc/xbitos/gnupg"),
+            config_path: PathBuf::from("/etc/xbitos/signing.json"),
+        }
+    }
+
+    // حلقة مفاتيح مؤقتة للاختبار بدلاً من مفاتيح النظام
+    pub fn with_paths(gnupg_home: &Path, config_path: &Path) -> Self {
+        Self {
+            gnupg_home: gnupg_home.to_path_buf(),
+            config_path: config_path.to_path_buf(),
+        }
+    }
+
+    pub fn get_gnupg_home(&self) -> &PathBuf {
+        &self.gnupg_home
+    }
+
+    fn ensure_home(&self) -> Result<()> {
+        fs::create_dir_all(&self.gnupg_home)?;
+        // gpg يرفض العمل إذا كان المجلد مقروءاً للآخرين
+        fs::set_permissions(&self.gnupg_home, fs::Permissions::from_mode(0o700))?;
+        Ok(())
+    }
+
+    pub fn generate_key(&self, name: &str, email: &str) -> Result<String> {
+        self.ensure_home()?;
+        info!("Generating signing key for {} <{}>", name, email);
+
+        let user_id = format!("{} <{}>", name, email);
+        let status = gpg(&self.gnupg_home)
+            .args(["--passphrase", "", "--quick-gen-key", &user_id, "ed25519", "sign", "never"])
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to run gpg")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to generate signing key"));
+        }
+
+        self.list_keys()?
+            .into_iter()
+            .rev()
+            .find(|key| key.has_secret && key.user_ids.contains(&user_id))
+            .map(|key| key.fingerprint)
+            .ok_or_else(|| anyhow::anyhow!("Generated key not found in keyring"))
+    }
+
+    pub fn list_keys(&self) -> Result<Vec<SigningKey>> {
+        if !self.gnupg_home.exists() {
+            return Ok(Vec::new());
+        }
+
+        let public = self.read_keys("--list-keys")?;
+        let secret: Vec<String> = self
+            .read_keys("--list-secret-keys")?
+            .into_iter()
+            .map(|key| key.fingerprint)
+            .collect();
+
+        Ok(public
+            .into_iter()
+            .map(|mut key| {
+                key.has_secret = secret.contains(&key.fingerprint);
+                key
+            })
+            .collect())
+    }
+
+    fn read_keys(&self, list_arg: &str) -> Result<Vec<SigningKey>> {
+        let output = gpg(&self.gnupg_home)
+            .args(["--with-colons", "--fixed-list-mode", list_arg])
+            .stderr(Stdio::null())
+            .output()
+            .context("Failed to run gpg")?;
+
+        Ok(parse_colon_listing(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    pub fn export_public_key(&self, key_id: &str, output: &Path) -> Result<()> {
+        let result = gpg(&self.gnupg_home)
+            .args(["--armor", "--export", key_id])
+            .output()
+            .context("Failed to run gpg")?;
+
+        if !result.status.success() || result.stdout.is_empty() {
+            return Err(anyhow::anyhow!("No public key found for {}", key_id));
+        }
+
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(output, result.stdout)?;
+        Ok(())
+    }
+
+    pub fn import_key(&self, path: &Path) -> Result<()> {
+        self.ensure_home()?;
+
+        let status = gpg(&self.gnupg_home)
+            .arg("--import")
+            .arg(path)
+            .status()
+            .context("Failed to run gpg")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to import key from {}", path.display()));
+        }
+
+        Ok(())
+    }
+
+    // تحديد المفتاح المستخدم لتوقيع الحزم وقاعدة بيانات المستودع
+    pub fn set_signing_key(&self, key_id: &str) -> Result<SigningConfig> {
+        let key = find_key(self.list_keys()?, key_id)?;
+
+        if !key.has_secret {
+            return Err(anyhow::anyhow!("No secret key available for {}", key.fingerprint));
+        }
+
+        let config = SigningConfig {
+            key_id: key.fingerprint,
+            gnupg_home: self.gnupg_home.clone(),
+        };
+
+        if let Some(parent) = self.config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.config_path, serde_json::to_string_pretty(&config)?)?;
+        info!("Packages will be signed with {}", config.key_id);
+
+        Ok(config)
+    }
+
+    pub fn get_config(&self) -> Result<Option<SigningConfig>> {
+        if !self.config_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&self.config_path)?;
+        Ok(Some(serde_json::from_str(&content).with_context(|| {
+            format!("Invalid signing config: {}", self.config_path.display())
+        })?))
+    }
+
+    pub fn configured_signer(&self) -> Result<Option<PackageSigner>> {
+        Ok(self
+            .get_config()?
+            .map(|config| PackageSigner::new(&config.gnupg_home, &config.key_id)))
+    }
+
+    // إضافة مفتاح المستودع إلى حلقة مفاتيح pacman ومنحه الثقة محلياً
+    pub fn trust_in_pacman(&self, key_id: &str) -> Result<()> {
+        let public_key = self.gnupg_home.join("xbitos-repo.asc");
+        self.export_public_key(key_id, &public_key)?;
+
+        let status = Command::new("pacman-key")
+            .arg("--add")
+            .arg(&public_key)
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to add repository key to pacman keyring"));
+        }
+
+        let status = Command::new("pacman-key")
+            .args(["--lsign-key", key_id])
+            .status()?;
+
+        if !status.success() {
+            warn!("Failed to locally sign repository key {}", key_id);
+        }
+
+        Ok(())
+    }
+}
+
+// البصمة الكاملة أو معرف المفتاح الطويل يطابقان حرفياً، وما عداهما يبحث في uid
+// ويجب أن يطابق مفتاحاً واحداً فقط حتى لا يختار مفتاح توقيع خاطئ بصمت
+fn find_key(keys: Vec<SigningKey>, key_id: &str) -> Result<SigningKey> {
+    let hex = key_id.trim_start_matches("0x").to_uppercase();
+    if matches!(hex.len(), 16 | 40) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return keys
+            .into_iter()
+            .find(|key| key.fingerprint == hex || (hex.len() == 16 && key.fingerprint.ends_with(&hex)))
+            .ok_or_else(|| anyhow::anyhow!("Key not found: {}", key_id));
+    }
+
+    let mut matches: Vec<SigningKey> = keys
+        .into_iter()
+        .filter(|key| key.user_ids.iter().any(|uid| uid.contains(key_id)))
+        .collect();
+
+    match matches.len() {
+        0 => Err(anyhow::anyhow!("Key not found: {}", key_id)),
+        1 => Ok(matches.remove(0)),
+        _ => Err(anyhow::anyhow!(
+            "{} matches several keys, use a fingerprint: {}",
+            key_id,
+            matches.iter().map(|key| key.fingerprint.as_str()).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(".sig");
+    PathBuf::from(signature)
+}
+
+fn gpg(gnupg_home: &Path) -> Command {
+    let mut command = Command::new("gpg");
+    command.arg("--homedir").arg(gnupg_home).args(["--batch", "--no-tty"]);
+    command
+}
+
+fn parse_colon_listing(output: &str) -> Vec<SigningKey> {
+    let mut keys: Vec<SigningKey> = Vec::new();
+    // سطر fpr الأول بعد pub/sec يخص المفتاح الرئيسي وليس المفاتيح الفرعية
+    let mut awaiting_fingerprint = false;
+
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        let field = |index: usize| fields.get(index).copied().unwrap_or("");
+
+        match field(0) {
+            "pub" | "sec" => {
+                keys.push(SigningKey {
+                    created: field(5).parse().ok(),
+                    expires: field(6).parse().ok(),
+                    ..Default::default()
+                });
+                awaiting_fingerprint = true;
+            }
+            "fpr" if awaiting_fingerprint => {
+                if let Some(key) = keys.last_mut() {
+                    key.fingerprint = field(9).to_string();
+                }
+                awaiting_fingerprint = false;
+            }
+            "uid" => {
+                if let Some(key) = keys.last_mut() {
+                    key.user_ids.push(field(9).to_string());
+                }
+            }
+            "sub" | "ssb" => awaiting_fingerprint = false,
+            _ => {}
+        }
+    }
+
+    keys
+}
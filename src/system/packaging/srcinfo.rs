@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use super::builder::{BuildSystem, PackageConfig};
+
+// قسم واحد من .SRCINFO: pkgbase أو pkgname مع مفاتيحه
+struct Section {
+    name: String,
+    values: HashMap<String, Vec<String>>,
+}
+
+impl Section {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            values: HashMap::new(),
+        }
+    }
+
+    fn single(&self, key: &str) -> Option<String> {
+        self.values.get(key).and_then(|values| values.first().cloned())
+    }
+}
+
+pub fn parse_srcinfo(content: &str, arch: &str) -> Result<Vec<PackageConfig>> {
+    let mut base: Option<Section> = None;
+    let mut packages: Vec<Section> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // التقسيم قبل التقليم، فالسطر "depends = " قيمته فارغة
+        let (key, value) = line
+            .split_once('=')
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .ok_or_else(|| anyhow::anyhow!("Invalid .SRCINFO line {}: {}", number + 1, line))?;
+
+        match key {
+            "pkgbase" => base = Some(Section::new(value)),
+            "pkgname" => packages.push(Section::new(value)),
+            _ => {
+                let section = packages
+                    .last_mut()
+                    .or(base.as_mut())
+                    .ok_or_else(|| anyhow::anyhow!("Key outside of pkgbase section: {}", key))?;
+
+                // القيمة الفارغة في قسم الحزمة تستبدل مصفوفة pkgbase بمصفوفة فارغة
+                let values = section.values.entry(key.to_string()).or_default();
+                if !value.is_empty() {
+                    values.push(value.to_string());
+                }
+            }
+        }
+    }
+
+    let base = base.ok_or_else(|| anyhow::anyhow!("Missing pkgbase in .SRCINFO"))?;
+    if packages.is_empty() {
+        packages.push(Section::new(&base.name));
+    }
+
+    packages
+        .iter()
+        .map(|package| section_to_config(&base, package, arch))
+        .collect()
+}
+
+fn section_to_config(base: &Section, package: &Section, arch: &str) -> Result<PackageConfig> {
+    // المفتاح المعرف في قسم الحزمة يستبدل قيمة pkgbase، لكن depends و depends_x86_64
+    // متغيران منفصلان في makepkg فيرث كل منهما من pkgbase وحده ثم يدمجان
+    let inherited = |key: &str| -> Vec<String> {
+        package
+            .values
+            .get(key)
+            .or_else(|| base.values.get(key))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let array = |key: &str| -> Vec<String> {
+        let mut values = inherited(key);
+        values.extend(inherited(&format!("{}_{}", key, arch)));
+        values
+    };
+    let single = |key: &str| -> Option<String> {
+        if package.values.contains_key(key) {
+            package.single(key)
+        } else {
+            base.single(key)
+        }
+    };
+
+    let version = base
+        .single("pkgver")
+        .ok_or_else(|| anyhow::anyhow!("Missing pkgver for {}", package.name))?;
+    let release = base
+        .single("pkgrel")
+        .ok_or_else(|| anyhow::anyhow!("Missing pkgrel for {}", package.name))?;
+
+    let mut build_dependencies = array("makedepends");
+    build_dependencies.extend(array("checkdepends"));
+
+    let source = array("source");
+    let sha256sums = array("sha256sums");
+    let b2sums = array("b2sums");
+    let sha512sums = array("sha512sums");
+    if !source.is_empty() && sha256sums.is_empty() && b2sums.is_empty() && sha512sums.is_empty() {
+        warn!("{} has no checksums; remote sources must be pinned before building", package.name);
+    }
+
+    let mut arches = array("arch");
+    if arches.is_empty() {
+        arches.push(arch.to_string());
+    }
+
+    Ok(PackageConfig {
+        name: package.name.clone(),
+        version,
+        release,
+        epoch: base.single("epoch").map(|e| e.parse()).transpose()?,
+        description: single("pkgdesc").unwrap_or_default(),
+        dependencies: array("depends"),
+        build_dependencies,
+        source,
+        build_system: BuildSystem::default(),
+        arch: arches,
+        url: single("url"),
+        license: array("license"),
+        provides: array("provides"),
+        conflicts: array("conflicts"),
+        replaces: array("replaces"),
+        optional_dependencies: array("optdepends"),
+        backup: array("backup"),
+        install: single("install"),
+        sha256sums,
+        b2sums,
+        sha512sums,
+        source_directory: None,
+        variables: BTreeMap::new(),
+        recipe_dir: None,
+    })
+}
+
+pub fn import_srcinfo(path: &Path, arch: &str) -> Result<Vec<PackageConfig>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut configs = parse_srcinfo(&content, arch)?;
+    for config in &mut configs {
+        config.recipe_dir = path.parent().map(|dir| dir.to_path_buf());
+    }
+
+    Ok(configs)
+}
+
+pub fn import_pkgbuild(dir: &Path, arch: &str) -> Result<Vec<PackageConfig>> {
+    info!("Importing PKGBUILD from {}", dir.display());
+
+    // makepkg يولد .SRCINFO بعد تقييم المتغيرات داخل PKGBUILD
+    let output = Command::new("makepkg")
+        .arg("--printsrcinfo")
+        .current_dir(dir)
+        .output()
+        .context("Failed to run makepkg --printsrcinfo")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "makepkg --printsrcinfo failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let mut configs = parse_srcinfo(&String::from_utf8(output.stdout)?, arch)?;
+    let split = configs.len() > 1;
+
+    for config in &mut configs {
+        config.recipe_dir = Some(dir.to_path_buf());
+
+        // الاحتفاظ بدوال البناء الأصلية كقالب مخصص
+        let package_function = if split {
+            format!("package_{}", config.name)
+        } else {
+            "package".to_string()
+        };
+
+        let functions = read_functions(dir, &["prepare", "build", &package_function])?;
+        match functions.get(&package_function) {
+            Some(package) => {
+                config.build_system = BuildSystem::Custom {
+                    prepare: functions.get("prepare").cloned(),
+                    build: functions.get("build").cloned().unwrap_or_else(|| ":".to_string()),
+                    package: package.clone(),
+                };
+            }
+            None => warn!("No {}() found for {}, using default template", package_function, config.name),
+        }
+
+        // المتغيرات المساعدة مثل _commit تستخدمها الدوال الأصلية
+        config.variables = read_helper_variables(dir)?;
+    }
+
+    Ok(configs)
+}
+
+fn read_helper_variables(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let script = r#"source ./PKGBUILD >/dev/null 2>&1
+for v in $(compgen -v); do
+    [[ $v == _[a-zA-Z]* ]] && printf '%s\0%s\0' "$v" "${!v}"
+done"#;
+
+    let output = Command::new("bash")
+        .args(["-c", script])
+        .current_dir(dir)
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let fields: Vec<&str> = stdout.split('\0').collect();
+
+    Ok(fields
+        .chunks(2)
+        .filter(|pair| pair.len() == 2 && !pair[0].is_empty())
+        .map(|pair| (pair[0].to_string(), pair[1].to_string()))
+        .collect())
+}
+
+fn read_functions(dir: &Path, names: &[&str]) -> Result<HashMap<String, String>> {
+    let script = format!(
+        "source ./PKGBUILD >/dev/null 2>&1; for f in {}; do declare -f \"$f\" && echo '#--'; done",
+        names.join(" "),
+    );
+
+    let output = Command::new("bash")
+        .args(["-c", &script])
+        .current_dir(dir)
+        .output()?;
+
+    let mut functions = HashMap::new();
+    for definition in String::from_utf8(output.stdout)?.split("#--\n") {
+        let Some((header, body)) = definition.split_once('\n') else {
+            continue;
+        };
+        let name = header.trim().trim_end_matches("()").trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        // declare -f يطبع الجسم بين سطري { و }
+        let body = body.trim();
+        let body = body
+            .strip_prefix('{')
+            .and_then(|b| b.strip_suffix('}'))
+            .unwrap_or(body);
+
+        let lines: Vec<&str> = body
+            .lines()
+            .map(|line| line.strip_prefix("    ").unwrap_or(line))
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        functions.insert(name.to_string(), lines.join("\n    "));
+    }
+
+    Ok(functions)
+}
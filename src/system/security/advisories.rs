@@ -0,0 +1,241 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::system::local_db::LocalDatabase;
+use crate::system::packaging::repo_db::vercmp;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdvisorySeverity {
+    Critical,
+    High,
+    Medium,
+    Low,
+    #[serde(other)]
+    Unknown,
+}
+
+impl AdvisorySeverity {
+    pub fn rank(&self) -> u8 {
+        match self {
+            AdvisorySeverity::Critical => 4,
+            AdvisorySeverity::High => 3,
+            AdvisorySeverity::Medium => 2,
+            AdvisorySeverity::Low => 1,
+            AdvisorySeverity::Unknown => 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdvisoryStatus {
+    Vulnerable,
+    Fixed,
+    Testing,
+    #[serde(rename = "Not affected")]
+    NotAffected,
+    #[serde(other)]
+    Unknown,
+}
+
+// مدخل واحد من issues/all.json في متتبع أمان Arch (مجموعة AVG)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Advisory {
+    pub name: String,
+    pub packages: Vec<String>,
+    pub status: AdvisoryStatus,
+    pub severity: AdvisorySeverity,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub affected: String,
+    pub fixed: Option<String>,
+    #[serde(default)]
+    pub ticket: Option<String>,
+    #[serde(default)]
+    pub issues: Vec<String>,
+    #[serde(default)]
+    pub advisories: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AffectedPackage {
+    pub package: String,
+    pub installed: String,
+    pub group: String,
+    pub severity: AdvisorySeverity,
+    pub kind: String,
+    pub issues: Vec<String>,
+    pub fixed: Option<String>,
+}
+
+impl AffectedPackage {
+    // الإصلاح متاح ويكفي تحديث الحزمة
+    pub fn has_fix(&self) -> bool {
+        self.fixed.is_some()
+    }
+}
+
+pub struct AdvisoryFeed {
+    source: String,
+    cache_path: PathBuf,
+}
+
+impl AdvisoryFeed {
+    pub fn new() -> Self {
+        Self {
+            source: "https://security.archlinux.org/issues/all.json".to_string(),
+            cache_path: PathBuf::from("/var/lib/xbitos/advisories.json"),
+        }
+    }
+
+    // المصدر رابط أو ملف محلي، مثلاً نسخة منزلة مسبقاً لجهاز دون اتصال
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = source.to_string();
+        self
+    }
+
+    pub fn with_cache_path(mut self, cache_path: &Path) -> Self {
+        self.cache_path = cache_path.to_path_buf();
+        self
+    }
+
+    pub fn get_cache_path(&self) -> &PathBuf {
+        &self.cache_path
+    }
+
+    fn is_remote(&self) -> bool {
+        self.source.starts_with("https://") || self.source.starts_with("http://")
+    }
+
+    fn read_source(&self) -> Result<String> {
+        if self.is_remote() {
+            let output = Command::new("curl")
+                .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "60", &self.source])
+                .output()
+                .context("Failed to run curl")?;
+            if !output.status.success() {
+                return Err(anyhow::anyhow!(
+                    "Failed to download advisories: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            String::from_utf8(output.stdout).context("Advisory feed is not valid UTF-8")
+        } else {
+            fs::read_to_string(&self.source)
+                .with_context(|| format!("Failed to read advisory feed: {}", self.source))
+        }
+    }
+
+    // قراءة المصدر مباشرة دون المرور بالنسخة المحلية المشتركة أو تعديلها
+    pub fn fetch(&self) -> Result<Vec<Advisory>> {
+        parse_advisories(&self.read_source()?)
+    }
+
+    // تحديث النسخة المحلية، ولا تستبدل إلا بملف صالح
+    pub fn refresh(&self) -> Result<Vec<Advisory>> {
+        info!("Refreshing security advisories from {}", self.source);
+
+        let content = self.read_source()?;
+        let advisories = parse_advisories(&content)?;
+
+        if let Some(parent) = self.cache_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary = self.cache_path.with_extension("json.new");
+        fs::write(&temporary, &content)?;
+        fs::rename(&temporary, &self.cache_path)?;
+
+        info!("Loaded {} advisories", advisories.len());
+        Ok(advisories)
+    }
+
+    pub fn load_cached(&self) -> Result<Vec<Advisory>> {
+        if !self.cache_path.exists() {
+            return Err(anyhow::anyhow!(
+                "No local advisory data in {}, run `xbitos advisories --refresh`",
+                self.cache_path.display()
+            ));
+        }
+        parse_advisories(&fs::read_to_string(&self.cache_path)?)
+    }
+
+    // عند انقطاع الشبكة نكمل بآخر نسخة محلية
+    pub fn load(&self, refresh: bool) -> Result<Vec<Advisory>> {
+        if refresh {
+            match self.refresh() {
+                Ok(advisories) => return Ok(advisories),
+                Err(e) => warn!("{}, using local copy", e),
+            }
+        }
+        self.load_cached()
+    }
+
+    // مطابقة الإرشادات مع الحزم المثبتة
+    pub fn check_installed(&self, refresh: bool) -> Result<Vec<AffectedPackage>> {
+        let advisories = self.load(refresh)?;
+        let db = LocalDatabase::new()?;
+        Ok(affected_packages(&advisories, &db))
+    }
+}
+
+pub fn parse_advisories(content: &str) -> Result<Vec<Advisory>> {
+    serde_json::from_str(content).context("Invalid security advisory feed")
+}
+
+pub fn affected_packages(advisories: &[Advisory], db: &LocalDatabase) -> Vec<AffectedPackage> {
+    let mut affected = Vec::new();
+
+    for advisory in advisories {
+        if advisory.status == AdvisoryStatus::NotAffected {
+            continue;
+        }
+
+        for name in &advisory.packages {
+            let Some(package) = db.get(name) else {
+                continue;
+            };
+
+            // الإصدار في affected هو المعروف وقت التسجيل، والأقدم منه متأثر غالباً أيضاً
+            if let Some(fixed) = &advisory.fixed {
+                if vercmp(&package.version, fixed) != Ordering::Less {
+                    continue;
+                }
+            }
+
+            affected.push(AffectedPackage {
+                package: package.name.clone(),
+                installed: package.version.clone(),
+                group: advisory.name.clone(),
+                severity: advisory.severity,
+                kind: advisory.kind.clone(),
+                issues: advisory.issues.clone(),
+                fixed: advisory.fixed.clone(),
+            });
+        }
+    }
+
+    affected.sort_by(|a, b| b.severity.rank().cmp(&a.severity.rank()).then_with(|| a.package.cmp(&b.package)));
+    affected
+}
+
+pub fn log_affected(affected: &[AffectedPackage]) {
+    for package in affected {
+        let fix = match &package.fixed {
+            Some(fixed) => format!("fixed in {}", fixed),
+            None => "no fix available".to_string(),
+        };
+        warn!(
+            "{} {} is affected by {} ({:?}, {}): {} - {}",
+            package.package,
+            package.installed,
+            package.group,
+            package.severity,
+            package.kind,
+            package.issues.join(", "),
+            fix
+        );
+    }
+}
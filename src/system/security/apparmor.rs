@@ -1,12 +1,132 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub struct AppArmorManager;
+// ترتيب وحدات الأمان الموصى به مع تفعيل AppArmor
+pub const LSM_PARAMETER: &str = "lsm=landlock,lockdown,yama,integrity,apparmor,bpf";
+
+const TEMPLATES: [(&str, &str); 2] = [
+    ("system-tool", include_str!("../../../config/apparmor/system-tool.profile")),
+    ("desktop-app", include_str!("../../../config/apparmor/desktop-app.profile")),
+];
+
+const MEDIA_READ: &str = "  owner @{HOME}/** r,\n  /{media,mnt,run/media}/** r,\n";
+
+struct BuiltinProfile {
+    name: &'static str,
+    template: &'static str,
+    exec: &'static str,
+    variables: &'static [(&'static str, &'static str)],
+}
+
+// الملفات الشخصية المرفقة مع النظام، تبدأ في وضع complain حتى تجرب
+const BUILTIN_PROFILES: [BuiltinProfile; 5] = [
+    BuiltinProfile {
+        name: "xbitos",
+        template: "system-tool",
+        exec: "/usr/bin/xbitos",
+        variables: &[],
+    },
+    BuiltinProfile {
+        name: "build-iso",
+        template: "system-tool",
+        exec: "/usr/bin/build-iso",
+        variables: &[],
+    },
+    BuiltinProfile {
+        name: "mpv",
+        template: "desktop-app",
+        exec: "/usr/bin/mpv",
+        variables: &[
+            ("config_dir", "mpv"),
+            ("rules", "  owner @{HOME}/** r,\n  /{media,mnt,run/media}/** r,\n  network inet stream,\n  network inet6 stream,\n\n  # تشغيل الروابط عبر yt-dlp\n  include <abstractions/python>\n  /usr/bin/yt-dlp ix,\n  /usr/bin/python3* ix,\n"),
+        ],
+    },
+    BuiltinProfile {
+        name: "imv",
+        template: "desktop-app",
+        exec: "/usr/bin/imv{,-wayland}",
+        variables: &[("config_dir", "imv"), ("rules", MEDIA_READ)],
+    },
+    BuiltinProfile {
+        name: "zathura",
+        template: "desktop-app",
+        exec: "/usr/bin/zathura",
+        variables: &[("config_dir", "zathura"), ("rules", MEDIA_READ)],
+    },
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileMode {
+    #[default]
+    Complain,
+    Enforce,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ProfileSettings {
+    #[serde(default)]
+    pub mode: ProfileMode,
+    // ملفات شخصية إضافية ينشئها المسؤول من أحد القوالب
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub template: String,
+    pub mode: ProfileMode,
+    pub variables: BTreeMap<String, String>,
+}
+
+impl Profile {
+    pub fn render(&self) -> Result<String> {
+        let template = template_source(&self.template)?;
+
+        let mut variables = self.variables.clone();
+        variables.insert("name".to_string(), self.name.clone());
+        variables.entry("rules".to_string()).or_default();
+        variables.insert(
+            "flags".to_string(),
+            match self.mode {
+                ProfileMode::Complain => "flags=(complain) ".to_string(),
+                ProfileMode::Enforce => String::new(),
+            },
+        );
+
+        render_template(template, &variables)
+            .with_context(|| format!("Failed to render AppArmor profile {}", self.name))
+    }
+
+    pub fn file_name(&self) -> String {
+        // البادئة تمنع الكتابة فوق ملفات حزمة apparmor نفسها
+        format!("xbitos.{}", self.name)
+    }
+}
+
+pub struct AppArmorManager {
+    settings_path: PathBuf,
+}
 
 impl AppArmorManager {
     pub fn new() -> Self {
-        Self
+        Self {
+            settings_path: PathBuf::from("/etc/xbitos/apparmor.json"),
+        }
+    }
+
+    pub fn with_settings_path(settings_path: &Path) -> Self {
+        Self {
+            settings_path: settings_path.to_path_buf(),
+        }
     }
 
     pub fn setup(&self) -> Result<()> {
@@ -17,6 +137,10 @@ impl AppArmorManager {
         // تكوين الملفات الشخصية الأساسية
         self.setup_base_profiles()?;
 
+        // الخدمة لا تفعل شيئاً إذا لم تحمل النواة وحدة AppArmor
+        let boot_manager = crate::system::bootloader::BootManager::new();
+        boot_manager.set_kernel_parameter(LSM_PARAMETER)?;
+
         // تمكين وتشغيل AppArmor
         let service_manager = crate::system::services::ServiceManager::new();
         service_manager.enable_service("apparmor")?;
@@ -27,7 +151,242 @@ impl AppArmorManager {
 
     fn setup_base_profiles(&self) -> Result<()> {
         // إضافة ملفات تعريف AppArmor الأساسية
-        // يمكن إضافة المزيد من الملفات الشخصية حسب الحاجة
+        self.install_profiles(Path::new("/"))?;
+        Ok(())
+    }
+
+    fn load_settings(&self) -> Result<BTreeMap<String, ProfileSettings>> {
+        if !self.settings_path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let content = fs::read_to_string(&self.settings_path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid AppArmor settings: {}", self.settings_path.display()))
+    }
+
+    fn save_settings(&self, settings: &BTreeMap<String, ProfileSettings>) -> Result<()> {
+        if let Some(parent) = self.settings_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.settings_path, serde_json::to_string_pretty(settings)?)?;
         Ok(())
     }
-} 
\ No newline at end of file
+
+    pub fn template_names(&self) -> Vec<&'static str> {
+        TEMPLATES.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn profiles(&self) -> Result<Vec<Profile>> {
+        let mut settings = self.load_settings()?;
+        let mut profiles = Vec::new();
+
+        for builtin in &BUILTIN_PROFILES {
+            let overrides = settings.remove(builtin.name).unwrap_or_default();
+
+            let mut merged: BTreeMap<String, String> = builtin
+                .variables
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            merged.insert("exec".to_string(), builtin.exec.to_string());
+            merged.extend(overrides.variables);
+
+            profiles.push(Profile {
+                name: builtin.name.to_string(),
+                template: overrides.template.unwrap_or_else(|| builtin.template.to_string()),
+                mode: overrides.mode,
+                variables: merged,
+            });
+        }
+
+        // ما تبقى ملفات شخصية أنشأها المسؤول
+        for (name, custom) in settings {
+            let Some(template) = custom.template else {
+                warn!("AppArmor profile {} has no template, ignoring", name);
+                continue;
+            };
+            profiles.push(Profile {
+                name,
+                template,
+                mode: custom.mode,
+                variables: custom.variables,
+            });
+        }
+
+        Ok(profiles)
+    }
+
+    pub fn get_profile(&self, name: &str) -> Result<Profile> {
+        self.profiles()?
+            .into_iter()
+            .find(|profile| profile.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown AppArmor profile: {}", name))
+    }
+
+    pub fn create_profile(&self, name: &str, template: &str, variables: BTreeMap<String, String>) -> Result<Profile> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) {
+            return Err(anyhow::anyhow!("Invalid AppArmor profile name: {}", name));
+        }
+        if self.profiles()?.iter().any(|profile| profile.name == name) {
+            return Err(anyhow::anyhow!("AppArmor profile {} already exists", name));
+        }
+
+        let profile = Profile {
+            name: name.to_string(),
+            template: template.to_string(),
+            mode: ProfileMode::Complain,
+            variables,
+        };
+        // التأكد من اكتمال المتغيرات قبل الحفظ
+        profile.render()?;
+
+        let mut settings = self.load_settings()?;
+        settings.insert(name.to_string(), ProfileSettings {
+            mode: profile.mode,
+            template: Some(profile.template.clone()),
+            variables: profile.variables.clone(),
+        });
+        self.save_settings(&settings)?;
+
+        info!("Created AppArmor profile {} from template {}", name, template);
+        Ok(profile)
+    }
+
+    pub fn set_mode(&self, name: &str, mode: ProfileMode, root: &Path) -> Result<()> {
+        let mut profile = self.get_profile(name)?;
+        profile.mode = mode;
+
+        // نكتب الملف ونتحقق منه قبل حفظ الوضع الجديد
+        self.install_profile(&profile, root)?;
+
+        let mut settings = self.load_settings()?;
+        settings.entry(name.to_string()).or_default().mode = mode;
+        self.save_settings(&settings)?;
+
+        info!("AppArmor profile {} set to {:?}", name, mode);
+        Ok(())
+    }
+
+    // يعمل أيضاً على نظام مثبت في مجلد آخر
+    pub fn install_profiles(&self, root: &Path) -> Result<()> {
+        for profile in self.profiles()? {
+            self.install_profile(&profile, root)?;
+        }
+        Ok(())
+    }
+
+    fn install_profile(&self, profile: &Profile, root: &Path) -> Result<()> {
+        let profiles_dir = root.join("etc/apparmor.d");
+        fs::create_dir_all(&profiles_dir)?;
+
+        let content = profile.render()?;
+        let path = profiles_dir.join(profile.file_name());
+        let temporary = path.with_extension("new");
+        fs::write(&temporary, &content)?;
+
+        if let Err(e) = validate_profile(&temporary, &profiles_dir) {
+            let _ = fs::remove_file(&temporary);
+            return Err(e);
+        }
+        fs::rename(&temporary, &path)?;
+
+        if root == Path::new("/") && kernel_enabled() {
+            let status = Command::new("apparmor_parser").arg("-r").arg(&path).status()?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to load AppArmor profile {}", profile.name));
+            }
+        }
+
+        info!("Installed AppArmor profile {} ({:?})", profile.name, profile.mode);
+        Ok(())
+    }
+
+    // الأوضاع كما تراها النواة حالياً
+    pub fn loaded_profiles(&self) -> Result<BTreeMap<String, String>> {
+        let path = Path::new("/sys/kernel/security/apparmor/profiles");
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let mut loaded = BTreeMap::new();
+        for line in fs::read_to_string(path)?.lines() {
+            if let Some((name, mode)) = line.rsplit_once(" (") {
+                loaded.insert(name.to_string(), mode.trim_end_matches(')').to_string());
+            }
+        }
+        Ok(loaded)
+    }
+}
+
+pub fn kernel_enabled() -> bool {
+    fs::read_to_string("/sys/module/apparmor/parameters/enabled")
+        .map(|value| value.trim() == "Y")
+        .unwrap_or(false)
+}
+
+pub fn validate_profile(path: &Path, base: &Path) -> Result<()> {
+    let output = match Command::new("apparmor_parser")
+        .args(["-Q", "-K", "--base"])
+        .arg(base)
+        .arg(path)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow::anyhow!(
+                "apparmor_parser not found, cannot check {} (install apparmor)",
+                path.display()
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "AppArmor profile {} rejected: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+fn template_source(name: &str) -> Result<&'static str> {
+    TEMPLATES
+        .iter()
+        .find(|(template, _)| *template == name)
+        .map(|(_, source)| *source)
+        .ok_or_else(|| anyhow::anyhow!("Unknown AppArmor template: {}", name))
+}
+
+fn render_template(template: &str, variables: &BTreeMap<String, String>) -> Result<String> {
+    let mut output = String::new();
+    let mut missing = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| anyhow::anyhow!("Unterminated placeholder in template"))?;
+        let key = after[..end].trim();
+
+        match variables.get(key) {
+            Some(value) => output.push_str(value),
+            None => missing.push(key.to_string()),
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+
+    if !missing.is_empty() {
+        missing.sort();
+        missing.dedup();
+        return Err(anyhow::anyhow!("Missing template variables: {}", missing.join(", ")));
+    }
+
+    Ok(output)
+}
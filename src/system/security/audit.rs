@@ -0,0 +1,529 @@
+use anyhow::Result;
+use log::warn;
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditFinding {
+    pub severity: Severity,
+    pub check: String,
+    pub location: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditReport {
+    pub root: PathBuf,
+    pub findings: Vec<AuditFinding>,
+    pub checked_at: String,
+}
+
+impl AuditReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|f| f.severity == severity).count()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} findings: {} high, {} medium, {} low",
+            self.findings.len(),
+            self.count(Severity::High),
+            self.count(Severity::Medium),
+            self.count(Severity::Low)
+        )
+    }
+}
+
+type AuditCheck = fn(&SecurityAuditor) -> Result<Vec<AuditFinding>>;
+
+// التدقيق للقراءة فقط، لا يغير أي ملف في النظام
+pub struct SecurityAuditor {
+    root: PathBuf,
+}
+
+impl SecurityAuditor {
+    pub fn new() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+        }
+    }
+
+    // تدقيق نظام مثبت في مجلد آخر، الفحوص التي تحتاج النظام العامل تعتمد على ملفاته فقط
+    pub fn with_root(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        self.root == Path::new("/")
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    pub fn run(&self) -> AuditReport {
+        let checks: [(&str, AuditCheck); 8] = [
+            ("mitigations", Self::check_mitigations),
+            ("sudoers", Self::check_sudoers),
+            ("world-writable", Self::check_world_writable),
+            ("ssh-root-login", Self::check_ssh_root_login),
+            ("firewall", Self::check_firewall),
+            ("apparmor", Self::check_apparmor),
+            ("root-encryption", Self::check_root_encryption),
+            ("pacman-siglevel", Self::check_pacman_siglevel),
+        ];
+
+        let mut findings = Vec::new();
+        for (name, check) in checks {
+            match check(self) {
+                Ok(found) => findings.extend(found),
+                // فشل فحص واحد لا يوقف بقية التدقيق
+                Err(e) => {
+                    warn!("Audit check {} failed: {}", name, e);
+                    findings.push(finding(Severity::Low, name, None, format!("check could not run: {}", e)));
+                }
+            }
+        }
+
+        findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+
+        AuditReport {
+            root: self.root.clone(),
+            findings,
+            checked_at: chrono::Local::now().to_rfc3339(),
+        }
+    }
+
+    fn boot_entries(&self) -> Vec<PathBuf> {
+        let mut entries = Vec::new();
+        for dir in ["boot/efi/loader/entries", "boot/loader/entries"] {
+            let Ok(read) = fs::read_dir(self.path(dir)) else {
+                continue;
+            };
+            let mut found: Vec<PathBuf> = read
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "conf"))
+                .collect();
+            found.sort();
+            entries.extend(found);
+        }
+        entries
+    }
+
+    // معلمات النواة من كل إدخالات الإقلاع، ومن النواة العاملة إن وجدت
+    fn kernel_command_lines(&self) -> Vec<(String, String)> {
+        let mut lines = Vec::new();
+
+        if self.is_live() {
+            if let Ok(cmdline) = fs::read_to_string("/proc/cmdline") {
+                lines.push(("/proc/cmdline".to_string(), cmdline.trim().to_string()));
+            }
+        }
+
+        for entry in self.boot_entries() {
+            let Ok(content) = fs::read_to_string(&entry) else {
+                continue;
+            };
+            for line in content.lines() {
+                if let Some(options) = line.trim().strip_prefix("options") {
+                    lines.push((entry.display().to_string(), options.trim().to_string()));
+                }
+            }
+        }
+
+        lines
+    }
+
+    fn check_mitigations(&self) -> Result<Vec<AuditFinding>> {
+        let mut findings = Vec::new();
+        for (location, cmdline) in self.kernel_command_lines() {
+            if cmdline.split_whitespace().any(|p| p == "mitigations=off") {
+                findings.push(finding(
+                    Severity::High,
+                    "mitigations",
+                    Some(location),
+                    "CPU vulnerability mitigations are disabled (mitigations=off)".to_string(),
+                ));
+            }
+        }
+        Ok(findings)
+    }
+
+    fn check_sudoers(&self) -> Result<Vec<AuditFinding>> {
+        let mut files = vec![self.path("etc/sudoers")];
+        if let Ok(read) = fs::read_dir(self.path("etc/sudoers.d")) {
+            // sudo يتجاهل الأسماء التي تحتوي على نقطة أو تنتهي بـ ~ مثل ملفات النسخ والتحرير
+            let mut dropins: Vec<PathBuf> = read
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    !name.contains('.') && !name.ends_with('~')
+                })
+                .map(|entry| entry.path())
+                .collect();
+            dropins.sort();
+            files.extend(dropins);
+        }
+
+        let mut findings = Vec::new();
+        for file in files {
+            if !file.exists() {
+                continue;
+            }
+            let content = fs::read_to_string(&file)?;
+            for (number, line) in content.lines().enumerate() {
+                let line = line.trim();
+                // Defaults وأسطر التعليق لا تمنح صلاحيات
+                if line.starts_with('#') || line.starts_with("Defaults") {
+                    continue;
+                }
+                if line.contains("NOPASSWD") {
+                    findings.push(finding(
+                        Severity::Medium,
+                        "sudoers-nopasswd",
+                        Some(format!("{}:{}", file.display(), number + 1)),
+                        format!("passwordless sudo rule: {}", line),
+                    ));
+                }
+            }
+        }
+        Ok(findings)
+    }
+
+    fn check_world_writable(&self) -> Result<Vec<AuditFinding>> {
+        let mut findings = Vec::new();
+        let mut pending = vec![self.path("etc")];
+
+        while let Some(dir) = pending.pop() {
+            let Ok(read) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in read.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                let Ok(metadata) = fs::symlink_metadata(&path) else {
+                    continue;
+                };
+                if metadata.file_type().is_symlink() {
+                    continue;
+                }
+
+                let mode = metadata.permissions().mode();
+                let sticky = metadata.is_dir() && mode & 0o1000 != 0;
+                if mode & 0o002 != 0 && !sticky {
+                    findings.push(finding(
+                        Severity::High,
+                        "world-writable",
+                        Some(path.display().to_string()),
+                        format!("writable by all users (mode {:o})", mode & 0o7777),
+                    ));
+                }
+
+                if metadata.is_dir() {
+                    pending.push(path);
+                }
+            }
+        }
+
+        findings.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(findings)
+    }
+
+    fn check_ssh_root_login(&self) -> Result<Vec<AuditFinding>> {
+        let config = self.path("etc/ssh/sshd_config");
+        if !config.exists() {
+            return Ok(Vec::new());
+        }
+
+        // sshd يأخذ أول قيمة يجدها، وبدونها تطبق القيمة الافتراضية prohibit-password
+        let (value, location) = sshd_option(&self.root, &config, "permitrootlogin")?.unwrap_or_else(|| {
+            ("prohibit-password".to_string(), format!("{} (default)", config.display()))
+        });
+
+        let findings = match value.to_lowercase().as_str() {
+            "yes" => vec![finding(
+                Severity::High,
+                "ssh-root-login",
+                Some(location),
+                "sshd allows root login with a password".to_string(),
+            )],
+            "prohibit-password" | "without-password" | "forced-commands-only" => vec![finding(
+                Severity::Low,
+                "ssh-root-login",
+                Some(location),
+                format!("sshd allows root login with keys (PermitRootLogin {})", value),
+            )],
+            _ => Vec::new(),
+        };
+        Ok(findings)
+    }
+
+    fn check_firewall(&self) -> Result<Vec<AuditFinding>> {
+        let services = ["firewalld.service", "nftables.service"];
+
+        let active = if self.is_live() {
+            services.iter().any(|service| systemctl_check(None, "is-active", service))
+        } else {
+            services.iter().any(|service| systemctl_check(Some(&self.root), "is-enabled", service))
+        };
+
+        if active {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![finding(
+            Severity::High,
+            "firewall",
+            None,
+            if self.is_live() {
+                "no firewall service is active".to_string()
+            } else {
+                "no firewall service is enabled".to_string()
+            },
+        )])
+    }
+
+    fn check_apparmor(&self) -> Result<Vec<AuditFinding>> {
+        let mut findings = Vec::new();
+
+        if self.is_live() && !super::apparmor::kernel_enabled() {
+            findings.push(finding(
+                Severity::Medium,
+                "apparmor",
+                Some("/sys/module/apparmor/parameters/enabled".to_string()),
+                "AppArmor is not enabled in the running kernel".to_string(),
+            ));
+            return Ok(findings);
+        }
+
+        if !self.is_live() {
+            let lsm_enabled = self.kernel_command_lines().iter().any(|(_, cmdline)| {
+                cmdline
+                    .split_whitespace()
+                    .any(|p| p.strip_prefix("lsm=").is_some_and(|lsm| lsm.split(',').any(|m| m == "apparmor")))
+            });
+            if !lsm_enabled {
+                findings.push(finding(
+                    Severity::Medium,
+                    "apparmor",
+                    None,
+                    "no boot entry enables the AppArmor LSM".to_string(),
+                ));
+            }
+        }
+
+        let service_enabled = systemctl_check((!self.is_live()).then_some(self.root.as_path()), "is-enabled", "apparmor.service");
+        if !service_enabled {
+            findings.push(finding(
+                Severity::Medium,
+                "apparmor",
+                None,
+                "apparmor.service is not enabled, profiles are not loaded at boot".to_string(),
+            ));
+        }
+
+        Ok(findings)
+    }
+
+    fn check_root_encryption(&self) -> Result<Vec<AuditFinding>> {
+        let encrypted = if self.is_live() {
+            let source = root_mount_source()?;
+            // lsblk -s يعرض الجهاز وكل الأجهزة التي يقوم عليها
+            let output = Command::new("lsblk")
+                .args(["-s", "-n", "-o", "TYPE"])
+                .arg(&source)
+                .output()?;
+            if !output.status.success() {
+                return Err(anyhow::anyhow!("lsblk failed for {}", source));
+            }
+            String::from_utf8_lossy(&output.stdout).lines().any(|t| t.trim() == "crypt")
+        } else {
+            // في نظام غير عامل نعتمد على fstab ومعلمات الإقلاع
+            let fstab = fs::read_to_string(self.path("etc/fstab")).unwrap_or_default();
+            let root_on_mapper = fstab.lines().any(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                fields.len() > 1 && !fields[0].starts_with('#') && fields[1] == "/" && fields[0].starts_with("/dev/mapper/")
+            });
+            root_on_mapper
+                || self.kernel_command_lines().iter().any(|(_, cmdline)| {
+                    cmdline
+                        .split_whitespace()
+                        .any(|p| p.starts_with("cryptdevice=") || p.starts_with("rd.luks"))
+                })
+        };
+
+        if encrypted {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![finding(
+            Severity::Medium,
+            "root-encryption",
+            None,
+            "root filesystem is not on an encrypted device".to_string(),
+        )])
+    }
+
+    fn check_pacman_siglevel(&self) -> Result<Vec<AuditFinding>> {
+        let path = self.path("etc/pacman.conf");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut findings = Vec::new();
+        let mut section = String::new();
+
+        for (number, line) in fs::read_to_string(&path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line.trim_matches(|c| c == '[' || c == ']').to_string();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if key.trim() != "SigLevel" {
+                continue;
+            }
+
+            let levels: Vec<&str> = value.split_whitespace().collect();
+            let location = Some(format!("{}:{} [{}]", path.display(), number + 1, section));
+            if levels.contains(&"Never") {
+                findings.push(finding(
+                    Severity::High,
+                    "pacman-siglevel",
+                    location,
+                    format!("signature checking disabled for [{}]", section),
+                ));
+            } else if levels.iter().any(|level| level.ends_with("TrustAll")) {
+                findings.push(finding(
+                    Severity::Medium,
+                    "pacman-siglevel",
+                    location,
+                    format!("[{}] trusts signatures from any key (TrustAll)", section),
+                ));
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+fn finding(severity: Severity, check: &str, location: Option<String>, message: String) -> AuditFinding {
+    AuditFinding {
+        severity,
+        check: check.to_string(),
+        location,
+        message,
+    }
+}
+
+fn systemctl_check(root: Option<&Path>, verb: &str, service: &str) -> bool {
+    let mut command = Command::new("systemctl");
+    if let Some(root) = root {
+        command.arg(format!("--root={}", root.display()));
+    }
+    command
+        .args([verb, "--quiet", service])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn root_mount_source() -> Result<String> {
+    let mounts = fs::read_to_string("/proc/self/mounts")?;
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?, fields.next()?))
+        })
+        .rfind(|(_, target)| *target == "/")
+        .map(|(source, _)| source.to_string())
+        .ok_or_else(|| anyhow::anyhow!("root filesystem not found in /proc/self/mounts"))
+}
+
+// أول قيمة لخيار في sshd_config مع اتباع Include، مثل sshd نفسه
+pub fn sshd_option(root: &Path, config: &Path, key: &str) -> Result<Option<(String, String)>> {
+    let content = fs::read_to_string(config)?;
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let name = fields.next().unwrap_or("").to_lowercase();
+        let value = fields.next().unwrap_or("");
+
+        // ما بعد Match ينطبق على اتصالات محددة فقط
+        if name == "match" {
+            break;
+        }
+
+        if name == "include" {
+            for included in expand_include(root, value)? {
+                if let Some(found) = sshd_option(root, &included, key)? {
+                    return Ok(Some(found));
+                }
+            }
+            continue;
+        }
+
+        if name == key {
+            return Ok(Some((value.to_string(), format!("{}:{}", config.display(), number + 1))));
+        }
+    }
+
+    Ok(None)
+}
+
+fn expand_include(root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    // المسارات النسبية في Include تبدأ من /etc/ssh
+    let pattern = if pattern.starts_with('/') {
+        pattern.to_string()
+    } else {
+        format!("/etc/ssh/{}", pattern)
+    };
+    let path = root.join(pattern.trim_start_matches('/'));
+
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let Some(suffix) = file_name.strip_prefix('*') else {
+        return Ok(if path.exists() { vec![path] } else { Vec::new() });
+    };
+
+    let Some(dir) = path.parent() else {
+        return Ok(Vec::new());
+    };
+    let Ok(read) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut files: Vec<PathBuf> = read
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().ends_with(suffix)))
+        .collect();
+    files.sort();
+    Ok(files)
+}
@@ -1,32 +1,682 @@
-use anyhow::Result;
-use std::process::Command;
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
-pub struct FirewallManager;
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallBackend {
+    #[default]
+    Firewalld,
+    Nftables,
+}
+
+impl FirewallBackend {
+    pub fn package(&self) -> &'static str {
+        match self {
+            FirewallBackend::Firewalld => "firewalld",
+            FirewallBackend::Nftables => "nftables",
+        }
+    }
+
+    pub fn service(&self) -> &'static str {
+        match self {
+            FirewallBackend::Firewalld => "firewalld.service",
+            FirewallBackend::Nftables => "nftables.service",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoneTarget {
+    #[default]
+    Drop,
+    Reject,
+    Accept,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateUnit {
+    Second,
+    Minute,
+    Hour,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    pub rate: u32,
+    pub per: RateUnit,
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceRule {
+    pub name: String,
+    // عناوين أو شبكات CIDR المسموح لها فقط، والقائمة الفارغة تعني الجميع
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PortRule {
+    pub port: u16,
+    // نهاية النطاق عند فتح مجموعة منافذ
+    #[serde(default)]
+    pub to_port: Option<u16>,
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct IcmpPolicy {
+    pub allow_echo: bool,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for IcmpPolicy {
+    fn default() -> Self {
+        Self {
+            allow_echo: true,
+            rate_limit: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Zone {
+    pub name: String,
+    #[serde(default)]
+    pub target: ZoneTarget,
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    // الحزم القادمة من هذه الشبكات تعالج في هذه المنطقة
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub services: Vec<ServiceRule>,
+    #[serde(default)]
+    pub ports: Vec<PortRule>,
+    #[serde(default)]
+    pub icmp: IcmpPolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FirewallPolicy {
+    #[serde(default)]
+    pub backend: FirewallBackend,
+    pub default_zone: String,
+    pub zones: Vec<Zone>,
+}
+
+impl Default for FirewallPolicy {
+    fn default() -> Self {
+        Self {
+            backend: FirewallBackend::Firewalld,
+            default_zone: "public".to_string(),
+            zones: vec![Zone {
+                name: "public".to_string(),
+                target: ZoneTarget::Drop,
+                interfaces: Vec::new(),
+                sources: Vec::new(),
+                services: vec![
+                    ServiceRule {
+                        name: "ssh".to_string(),
+                        sources: Vec::new(),
+                        rate_limit: Some(RateLimit { rate: 10, per: RateUnit::Minute, burst: Some(5) }),
+                    },
+                    ServiceRule {
+                        name: "dhcpv6-client".to_string(),
+                        sources: Vec::new(),
+                        rate_limit: None,
+                    },
+                ],
+                ports: Vec::new(),
+                icmp: IcmpPolicy {
+                    allow_echo: true,
+                    rate_limit: Some(RateLimit { rate: 5, per: RateUnit::Second, burst: Some(10) }),
+                },
+            }],
+        }
+    }
+}
+
+// منافذ الخدمات المعروفة لخلفية nftables، firewalld يعرفها بنفسه
+fn service_ports(name: &str) -> Option<&'static [(Protocol, u16, u16)]> {
+    let ports: &'static [(Protocol, u16, u16)] = match name {
+        "ssh" => &[(Protocol::Tcp, 22, 22)],
+        "http" => &[(Protocol::Tcp, 80, 80)],
+        "https" => &[(Protocol::Tcp, 443, 443)],
+        "dhcpv6-client" => &[(Protocol::Udp, 546, 546)],
+        "dhcp" => &[(Protocol::Udp, 67, 67)],
+        "dns" => &[(Protocol::Tcp, 53, 53), (Protocol::Udp, 53, 53)],
+        "mdns" => &[(Protocol::Udp, 5353, 5353)],
+        "samba" => &[(Protocol::Udp, 137, 138), (Protocol::Tcp, 139, 139), (Protocol::Tcp, 445, 445)],
+        "ipp" => &[(Protocol::Tcp, 631, 631), (Protocol::Udp, 631, 631)],
+        "kdeconnect" => &[(Protocol::Tcp, 1714, 1764), (Protocol::Udp, 1714, 1764)],
+        "syncthing" => &[(Protocol::Tcp, 22000, 22000), (Protocol::Udp, 22000, 22000), (Protocol::Udp, 21027, 21027)],
+        _ => return None,
+    };
+    Some(ports)
+}
+
+impl FirewallPolicy {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        let mut zone_names = HashSet::new();
+        let mut interfaces = HashSet::new();
+
+        if !self.zones.iter().any(|z| z.name == self.default_zone) {
+            errors.push(format!("default zone '{}' is not defined", self.default_zone));
+        }
+
+        for zone in &self.zones {
+            if zone.name.is_empty() || !zone.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                errors.push(format!("invalid zone name '{}'", zone.name));
+            }
+            if !zone_names.insert(zone.name.as_str()) {
+                errors.push(format!("zone '{}' is defined twice", zone.name));
+            }
+
+            for interface in &zone.interfaces {
+                if !interfaces.insert(interface.as_str()) {
+                    errors.push(format!("interface '{}' is bound to more than one zone", interface));
+                }
+            }
+
+            let all_sources = zone
+                .sources
+                .iter()
+                .chain(zone.services.iter().flat_map(|s| &s.sources))
+                .chain(zone.ports.iter().flat_map(|p| &p.sources));
+            for source in all_sources {
+                if parse_network(source).is_none() {
+                    errors.push(format!("invalid source address '{}' in zone '{}'", source, zone.name));
+                }
+            }
+
+            for port in &zone.ports {
+                if port.port == 0 || port.to_port.is_some_and(|end| end < port.port) {
+                    errors.push(format!("invalid port range {} in zone '{}'", port_range(port), zone.name));
+                }
+            }
+
+            let limits = zone
+                .services
+                .iter()
+                .filter_map(|s| s.rate_limit.as_ref())
+                .chain(zone.ports.iter().filter_map(|p| p.rate_limit.as_ref()))
+                .chain(zone.icmp.rate_limit.as_ref());
+            for limit in limits {
+                if limit.rate == 0 {
+                    errors.push(format!("rate limit must be positive in zone '{}'", zone.name));
+                }
+            }
+
+            if self.backend == FirewallBackend::Nftables {
+                for service in &zone.services {
+                    if service_ports(&service.name).is_none() {
+                        errors.push(format!("unknown service '{}' for nftables backend, use a port rule", service.name));
+                    }
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!("Invalid firewall policy:\n  {}", errors.join("\n  ")));
+        }
+
+        Ok(())
+    }
+
+    pub fn render_nftables(&self) -> Result<String> {
+        self.validate()?;
+
+        let mut out = String::from("#!/usr/sbin/nft -f\n# Generated by xBitOS, edit /etc/xbitos/firewall.toml instead\n\nflush ruleset\n\ntable inet xbitos {\n");
+
+        out.push_str("    chain input {\n        type filter hook input priority filter; policy drop;\n\n");
+        out.push_str("        ct state established,related accept\n        ct state invalid drop\n        iif \"lo\" accept\n");
+        // اكتشاف الجيران في IPv6 ضروري لعمل الشبكة أصلاً
+        out.push_str("        icmpv6 type { nd-neighbor-solicit, nd-neighbor-advert, nd-router-advert, nd-router-solicit } ip6 hoplimit 255 accept\n\n");
+
+        // ربط المصادر أولاً ثم الواجهات كما يفعل firewalld
+        for zone in &self.zones {
+            for (family, addresses) in split_families(&zone.sources) {
+                out.push_str(&format!("        {} saddr {{ {} }} jump zone_{}\n", family, addresses.join(", "), zone.name));
+            }
+        }
+        for zone in &self.zones {
+            if !zone.interfaces.is_empty() {
+                let names: Vec<String> = zone.interfaces.iter().map(|i| format!("\"{}\"", i)).collect();
+                out.push_str(&format!("        iifname {{ {} }} jump zone_{}\n", names.join(", "), zone.name));
+            }
+        }
+        out.push_str(&format!("        jump zone_{}\n    }}\n\n", self.default_zone));
+
+        out.push_str("    chain forward {\n        type filter hook forward priority filter; policy drop;\n    }\n\n");
+        out.push_str("    chain output {\n        type filter hook output priority filter; policy accept;\n    }\n");
+
+        for zone in &self.zones {
+            out.push_str(&format!("\n    chain zone_{} {{\n", zone.name));
+
+            if zone.icmp.allow_echo {
+                let limit = zone.icmp.rate_limit.as_ref().map(nft_limit).unwrap_or_default();
+                out.push_str(&format!("        icmp type echo-request {}accept\n", limit));
+                out.push_str(&format!("        icmpv6 type echo-request {}accept\n", limit));
+            }
+            out.push_str("        icmp type { destination-unreachable, time-exceeded, parameter-problem } accept\n");
+            out.push_str("        icmpv6 type { destination-unreachable, packet-too-big, time-exceeded, parameter-problem } accept\n");
+
+            for service in &zone.services {
+                for &(protocol, start, end) in service_ports(&service.name).unwrap_or(&[]) {
+                    let ports = if start == end { start.to_string() } else { format!("{}-{}", start, end) };
+                    push_nft_rule(&mut out, protocol, &ports, &service.sources, service.rate_limit.as_ref(), &service.name);
+                }
+            }
+            for port in &zone.ports {
+                push_nft_rule(&mut out, port.protocol, &port_range(port), &port.sources, port.rate_limit.as_ref(), "port");
+            }
+
+            out.push_str(match zone.target {
+                ZoneTarget::Drop => "        drop\n",
+                ZoneTarget::Reject => "        reject with icmpx type admin-prohibited\n",
+                ZoneTarget::Accept => "        accept\n",
+            });
+            out.push_str("    }\n");
+        }
+
+        out.push_str("}\n");
+        Ok(out)
+    }
+
+    // ملفات المناطق الدائمة لـ firewalld مع اسم المنطقة الافتراضية
+    pub fn render_firewalld(&self) -> Result<Vec<(String, String)>> {
+        self.validate()?;
+
+        let mut zones = Vec::new();
+        for zone in &self.zones {
+            let target = match zone.target {
+                ZoneTarget::Drop => "DROP",
+                ZoneTarget::Reject => "%%REJECT%%",
+                ZoneTarget::Accept => "ACCEPT",
+            };
+
+            let mut xml = format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!-- Generated by xBitOS, edit /etc/xbitos/firewall.toml instead -->\n<zone target=\"{}\">\n  <short>{}</short>\n",
+                target, zone.name
+            );
+
+            for interface in &zone.interfaces {
+                xml.push_str(&format!("  <interface name=\"{}\"/>\n", xml_escape(interface)));
+            }
+            for source in &zone.sources {
+                xml.push_str(&format!("  <source address=\"{}\"/>\n", source));
+            }
+
+            // القواعد المقيدة بمصدر أو بمعدل تحتاج rich rules
+            for service in &zone.services {
+                let element = format!("<service name=\"{}\"/>", xml_escape(&service.name));
+                push_firewalld_rule(&mut xml, &element, &service.sources, service.rate_limit.as_ref());
+            }
+            for port in &zone.ports {
+                let protocol = match port.protocol {
+                    Protocol::Tcp => "tcp",
+                    Protocol::Udp => "udp",
+                };
+                let element = format!("<port protocol=\"{}\" port=\"{}\"/>", protocol, port_range(port));
+                push_firewalld_rule(&mut xml, &element, &port.sources, port.rate_limit.as_ref());
+            }
+
+            match (&zone.icmp.allow_echo, &zone.icmp.rate_limit) {
+                (false, _) => xml.push_str("  <icmp-block name=\"echo-request\"/>\n"),
+                (true, Some(limit)) => {
+                    // icmp-block يطبق قبل rich rules العادية لذلك نستخدم أولوية سالبة
+                    xml.push_str(&format!(
+                        "  <rule priority=\"-2\">\n    <icmp-type name=\"echo-request\"/>\n    <accept>\n      <limit value=\"{}\"/>\n    </accept>\n  </rule>\n",
+                        firewalld_limit(limit)
+                    ));
+                    xml.push_str("  <rule priority=\"-1\">\n    <icmp-type name=\"echo-request\"/>\n    <drop/>\n  </rule>\n");
+                }
+                (true, None) => {}
+            }
+
+            xml.push_str("</zone>\n");
+            zones.push((zone.name.clone(), xml));
+        }
+
+        Ok(zones)
+    }
+}
+
+pub struct FirewallManager {
+    policy_path: PathBuf,
+}
 
 impl FirewallManager {
     pub fn new() -> Self {
-        Self
+        Self {
+            policy_path: PathBuf::from("/etc/xbitos/firewall.toml"),
+        }
+    }
+
+    pub fn with_policy_path(policy_path: &Path) -> Self {
+        Self {
+            policy_path: policy_path.to_path_buf(),
+        }
+    }
+
+    pub fn load_policy(&self) -> Result<FirewallPolicy> {
+        if !self.policy_path.exists() {
+            return Ok(FirewallPolicy::default());
+        }
+
+        let content = fs::read_to_string(&self.policy_path)?;
+        let policy: FirewallPolicy = toml::from_str(&content)
+            .with_context(|| format!("Invalid firewall policy: {}", self.policy_path.display()))?;
+        policy.validate()?;
+        Ok(policy)
     }
 
     pub fn setup(&self) -> Result<()> {
-        // تثبيت وإعداد firewalld
-        let pkg_manager = crate::system::package_manager::PackageManager::new();
-        pkg_manager.install_packages(&["firewalld"])?;
+        // كتابة السياسة الافتراضية ليعدلها المسؤول لاحقاً
+        if !self.policy_path.exists() {
+            if let Some(parent) = self.policy_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&self.policy_path, toml::to_string_pretty(&FirewallPolicy::default())?)?;
+        }
 
-        // تكوين القواعد الأساسية
-        Command::new("firewall-cmd")
-            .args(["--permanent", "--add-service=ssh"])
-            .status()?;
+        let policy = self.load_policy()?;
+
+        let pkg_manager = crate::system::package_manager::PackageManager::new();
+        pkg_manager.install_packages(&[policy.backend.package()])?;
 
-        Command::new("firewall-cmd")
-            .args(["--permanent", "--add-service=dhcpv6-client"])
-            .status()?;
+        self.apply(&policy, Path::new("/"))?;
 
-        // تمكين وتشغيل الخدمة
         let service_manager = crate::system::services::ServiceManager::new();
-        service_manager.enable_service("firewalld")?;
-        service_manager.start_service("firewalld")?;
+        service_manager.start_service(policy.backend.service())?;
+
+        Ok(())
+    }
+
+    // يعمل على نظام مثبت في مجلد دون الحاجة إلى خدمة جدار حماية عاملة
+    pub fn apply(&self, policy: &FirewallPolicy, root: &Path) -> Result<()> {
+        info!("Applying {:?} firewall policy to {}", policy.backend, root.display());
+
+        match policy.backend {
+            FirewallBackend::Nftables => {
+                let ruleset = policy.render_nftables()?;
+                check_nftables(&ruleset, root)?;
+                fs::create_dir_all(root.join("etc"))?;
+                fs::write(root.join("etc/nftables.conf"), ruleset)?;
+            }
+            FirewallBackend::Firewalld => {
+                let zones_dir = root.join("etc/firewalld/zones");
+                fs::create_dir_all(&zones_dir)?;
+                for (name, xml) in policy.render_firewalld()? {
+                    fs::write(zones_dir.join(format!("{}.xml", name)), xml)?;
+                }
+                set_firewalld_default_zone(&root.join("etc/firewalld/firewalld.conf"), &policy.default_zone)?;
+            }
+        }
+
+        // خلفية واحدة فقط تدير القواعد
+        let other = match policy.backend {
+            FirewallBackend::Nftables => FirewallBackend::Firewalld,
+            FirewallBackend::Firewalld => FirewallBackend::Nftables,
+        };
+        systemctl(root, &["enable", policy.backend.service()])?;
+        let disable = other.service();
+        if let Err(e) = systemctl(root, &["disable", disable]) {
+            warn!("Failed to disable {}: {}", disable, e);
+        }
+
+        if root == Path::new("/") {
+            self.reload(policy.backend)?;
+        }
+
+        Ok(())
+    }
 
+    fn reload(&self, backend: FirewallBackend) -> Result<()> {
+        match backend {
+            FirewallBackend::Nftables => {
+                if Command::new("systemctl").args(["is-active", "--quiet", "nftables"]).status()?.success() {
+                    let status = Command::new("nft").args(["-f", "/etc/nftables.conf"]).status()?;
+                    if !status.success() {
+                        return Err(anyhow::anyhow!("Failed to load /etc/nftables.conf"));
+                    }
+                }
+            }
+            FirewallBackend::Firewalld => {
+                // firewall-cmd يفشل إذا لم تكن الخدمة تعمل، وستقرأ الإعداد عند بدئها
+                let running = Command::new("firewall-cmd")
+                    .arg("--state")
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .map(|status| status.success())
+                    .unwrap_or(false);
+                if running && !Command::new("firewall-cmd").arg("--reload").status()?.success() {
+                    return Err(anyhow::anyhow!("Failed to reload firewalld"));
+                }
+            }
+        }
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+// قواعد لم تفحص قد تترك الجهاز دون جدار حماية بعد الإقلاع، لذلك غياب nft خطأ.
+// في نظام مثبت في مجلد آخر نستخدم nft المثبت فيه لأن نظام المثبت قد لا يحتويه
+pub fn check_nftables(ruleset: &str, root: &Path) -> Result<()> {
+    let mut command = if root == Path::new("/") {
+        Command::new("nft")
+    } else {
+        let mut command = Command::new("arch-chroot");
+        command.arg(root).arg("nft");
+        command
+    };
+
+    let mut child = match command
+        .args(["-c", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow::anyhow!("nft not found, cannot check the nftables ruleset (install nftables)"));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    child.stdin.take().unwrap().write_all(ruleset.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "nftables ruleset rejected: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+fn systemctl(root: &Path, args: &[&str]) -> Result<()> {
+    let mut command = Command::new("systemctl");
+    if root != Path::new("/") {
+        command.arg(format!("--root={}", root.display()));
+    }
+
+    let status = command.args(args).status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("systemctl {} failed", args.join(" ")));
+    }
+    Ok(())
+}
+
+fn set_firewalld_default_zone(conf_path: &Path, zone: &str) -> Result<()> {
+    let content = fs::read_to_string(conf_path).unwrap_or_default();
+    let mut found = false;
+
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with("DefaultZone=") {
+                found = true;
+                format!("DefaultZone={}", zone)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+
+    if !found {
+        lines.push(format!("DefaultZone={}", zone));
+    }
+
+    fs::write(conf_path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+fn parse_network(source: &str) -> Option<IpAddr> {
+    let (address, prefix) = match source.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (source, None),
+    };
+
+    let address: IpAddr = address.parse().ok()?;
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    match prefix {
+        Some(prefix) if prefix.parse::<u8>().ok()? > max => None,
+        _ => Some(address),
+    }
+}
+
+fn split_families(sources: &[String]) -> Vec<(&'static str, Vec<String>)> {
+    let (v4, v6): (Vec<&String>, Vec<&String>) = sources
+        .iter()
+        .partition(|source| parse_network(source).is_some_and(|a| a.is_ipv4()));
+
+    let mut families = Vec::new();
+    if !v4.is_empty() {
+        families.push(("ip", v4.into_iter().cloned().collect()));
+    }
+    if !v6.is_empty() {
+        families.push(("ip6", v6.into_iter().cloned().collect()));
+    }
+    families
+}
+
+fn port_range(port: &PortRule) -> String {
+    match port.to_port {
+        Some(end) if end != port.port => format!("{}-{}", port.port, end),
+        _ => port.port.to_string(),
+    }
+}
+
+fn nft_limit(limit: &RateLimit) -> String {
+    let unit = match limit.per {
+        RateUnit::Second => "second",
+        RateUnit::Minute => "minute",
+        RateUnit::Hour => "hour",
+    };
+    match limit.burst {
+        Some(burst) => format!("limit rate {}/{} burst {} packets ", limit.rate, unit, burst),
+        None => format!("limit rate {}/{} ", limit.rate, unit),
+    }
+}
+
+fn firewalld_limit(limit: &RateLimit) -> String {
+    let unit = match limit.per {
+        RateUnit::Second => "s",
+        RateUnit::Minute => "m",
+        RateUnit::Hour => "h",
+    };
+    format!("{}/{}", limit.rate, unit)
+}
+
+fn push_nft_rule(out: &mut String, protocol: Protocol, ports: &str, sources: &[String], limit: Option<&RateLimit>, comment: &str) {
+    let protocol = match protocol {
+        Protocol::Tcp => "tcp",
+        Protocol::Udp => "udp",
+    };
+    let limit = limit.map(nft_limit).unwrap_or_default();
+
+    if sources.is_empty() {
+        out.push_str(&format!("        {} dport {} {}accept comment \"{}\"\n", protocol, ports, limit, comment));
+        return;
+    }
+
+    for (family, addresses) in split_families(sources) {
+        out.push_str(&format!(
+            "        {} saddr {{ {} }} {} dport {} {}accept comment \"{}\"\n",
+            family,
+            addresses.join(", "),
+            protocol,
+            ports,
+            limit,
+            comment
+        ));
+    }
+}
+
+fn push_firewalld_rule(xml: &mut String, element: &str, sources: &[String], limit: Option<&RateLimit>) {
+    if sources.is_empty() && limit.is_none() {
+        xml.push_str(&format!("  {}\n", element));
+        return;
+    }
+
+    let accept = match limit {
+        Some(limit) => format!("<accept>\n      <limit value=\"{}\"/>\n    </accept>", firewalld_limit(limit)),
+        None => "<accept/>".to_string(),
+    };
+
+    if sources.is_empty() {
+        xml.push_str(&format!("  <rule>\n    {}\n    {}\n  </rule>\n", element, accept));
+        return;
+    }
+
+    for source in sources {
+        let family = if parse_network(source).is_some_and(|a| a.is_ipv4()) { "ipv4" } else { "ipv6" };
+        xml.push_str(&format!(
+            "  <rule family=\"{}\">\n    <source address=\"{}\"/>\n    {}\n    {}\n  </rule>\n",
+            family, source, element, accept
+        ));
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
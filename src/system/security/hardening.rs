@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const SYSCTL_NAME: &str = "90-xbitos-hardening.conf";
+const MODPROBE_NAME: &str = "xbitos-blacklist.conf";
+
+// بروتوكولات شبكية نادرة الاستخدام وكثيرة الثغرات
+const RARE_PROTOCOLS: [&str; 4] = ["dccp", "sctp", "rds", "tipc"];
+
+const HARDENED_MODULES: [&str; 14] = [
+    "n-hdlc", "ax25", "netrom", "x25", "rose", "decnet", "econet", "af_802154",
+    "ipx", "appletalk", "psnap", "p8022", "can", "atm",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum KernelProfile {
+    Performance,
+    #[default]
+    Balanced,
+    Hardened,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct ProfileSettings {
+    #[serde(default)]
+    profile: KernelProfile,
+}
+
+impl KernelProfile {
+    pub const ALL: [KernelProfile; 3] = [KernelProfile::Performance, KernelProfile::Balanced, KernelProfile::Hardened];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KernelProfile::Performance => "performance",
+            KernelProfile::Balanced => "balanced",
+            KernelProfile::Hardened => "hardened",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.name() == name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown kernel profile: {} (performance, balanced, hardened)", name))
+    }
+
+    // كل الملفات تضبط معلمات الإقلاع نفسها حتى يستبدل التبديل بينها القيم القديمة
+    pub fn cmdline(&self) -> Vec<&'static str> {
+        match self {
+            KernelProfile::Performance => vec![
+                "mitigations=off",
+                "intel_iommu=on",
+                "iommu=pt",
+                "iommu.strict=0",
+                "init_on_alloc=0",
+                "init_on_free=0",
+                "page_alloc.shuffle=0",
+                "randomize_kstack_offset=off",
+                "vsyscall=xonly",
+                "debugfs=on",
+            ],
+            KernelProfile::Balanced => vec![
+                "mitigations=auto",
+                "intel_iommu=on",
+                "iommu=pt",
+                "iommu.strict=1",
+                "init_on_alloc=1",
+                "init_on_free=0",
+                "page_alloc.shuffle=1",
+                "randomize_kstack_offset=on",
+                "vsyscall=xonly",
+                "debugfs=on",
+            ],
+            KernelProfile::Hardened => vec![
+                "mitigations=auto,nosmt",
+                "intel_iommu=on",
+                "iommu=nopt",
+                "iommu.strict=1",
+                "init_on_alloc=1",
+                "init_on_free=1",
+                "page_alloc.shuffle=1",
+                "randomize_kstack_offset=on",
+                "vsyscall=none",
+                "debugfs=off",
+            ],
+        }
+    }
+
+    // كل الملفات تكتب المفاتيح نفسها عدا kexec_load_disabled التي لا تقبل إلا 1،
+    // والقيم في balanced و performance هي الافتراضية في النواة و systemd
+    pub fn sysctl(&self) -> Vec<(&'static str, &'static str)> {
+        match self {
+            KernelProfile::Performance => vec![
+                ("kernel.kptr_restrict", "0"),
+                ("kernel.dmesg_restrict", "0"),
+                ("kernel.unprivileged_bpf_disabled", "2"),
+                ("net.core.bpf_jit_harden", "0"),
+                ("kernel.yama.ptrace_scope", "0"),
+                ("kernel.perf_event_paranoid", "1"),
+                ("dev.tty.ldisc_autoload", "1"),
+                ("fs.protected_fifos", "1"),
+                ("fs.protected_regular", "1"),
+                ("fs.protected_symlinks", "1"),
+                ("fs.protected_hardlinks", "1"),
+                ("net.ipv4.conf.all.rp_filter", "2"),
+                ("net.ipv4.conf.all.accept_redirects", "1"),
+                ("net.ipv6.conf.all.accept_redirects", "1"),
+                ("net.ipv4.conf.all.send_redirects", "1"),
+            ],
+            KernelProfile::Balanced => vec![
+                ("kernel.kptr_restrict", "1"),
+                ("kernel.dmesg_restrict", "1"),
+                ("kernel.unprivileged_bpf_disabled", "2"),
+                ("net.core.bpf_jit_harden", "1"),
+                ("kernel.yama.ptrace_scope", "1"),
+                ("kernel.perf_event_paranoid", "2"),
+                ("dev.tty.ldisc_autoload", "1"),
+                ("fs.protected_fifos", "1"),
+                ("fs.protected_regular", "1"),
+                ("fs.protected_symlinks", "1"),
+                ("fs.protected_hardlinks", "1"),
+                ("net.ipv4.conf.all.rp_filter", "2"),
+                ("net.ipv4.conf.all.accept_redirects", "1"),
+                ("net.ipv6.conf.all.accept_redirects", "1"),
+                ("net.ipv4.conf.all.send_redirects", "1"),
+            ],
+            // القيمة 1 في unprivileged_bpf_disabled و kexec_load_disabled لا يمكن التراجع عنها حتى إعادة التشغيل
+            KernelProfile::Hardened => vec![
+                ("kernel.kptr_restrict", "2"),
+                ("kernel.dmesg_restrict", "1"),
+                ("kernel.unprivileged_bpf_disabled", "1"),
+                ("net.core.bpf_jit_harden", "2"),
+                ("kernel.yama.ptrace_scope", "2"),
+                ("kernel.perf_event_paranoid", "3"),
+                ("dev.tty.ldisc_autoload", "0"),
+                ("fs.protected_fifos", "2"),
+                ("fs.protected_regular", "2"),
+                ("fs.protected_symlinks", "1"),
+                ("fs.protected_hardlinks", "1"),
+                ("net.ipv4.conf.all.rp_filter", "1"),
+                ("net.ipv4.conf.all.accept_redirects", "0"),
+                ("net.ipv6.conf.all.accept_redirects", "0"),
+                ("net.ipv4.conf.all.send_redirects", "0"),
+                ("kernel.kexec_load_disabled", "1"),
+            ],
+        }
+    }
+
+    pub fn blacklisted_modules(&self) -> Vec<&'static str> {
+        match self {
+            KernelProfile::Performance => Vec::new(),
+            KernelProfile::Balanced => RARE_PROTOCOLS.to_vec(),
+            KernelProfile::Hardened => RARE_PROTOCOLS.iter().chain(HARDENED_MODULES.iter()).copied().collect(),
+        }
+    }
+
+    pub fn render_sysctl(&self) -> String {
+        let mut out = format!("# Generated by xBitOS for the {} kernel profile\n", self.name());
+        for (key, value) in self.sysctl() {
+            out.push_str(&format!("{} = {}\n", key, value));
+        }
+        out
+    }
+
+    pub fn render_modprobe(&self) -> String {
+        let mut out = format!("# Generated by xBitOS for the {} kernel profile\n", self.name());
+        // blacklist يمنع التحميل التلقائي فقط، و install يمنع التحميل اليدوي أيضاً
+        for module in self.blacklisted_modules() {
+            out.push_str(&format!("blacklist {}\ninstall {} /bin/false\n", module, module));
+        }
+        out
+    }
+}
+
+pub struct HardeningManager {
+    root: PathBuf,
+}
+
+impl HardeningManager {
+    pub fn new() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+        }
+    }
+
+    // إعداد نظام مثبت في مجلد آخر، مثلاً من المثبت
+    pub fn with_root(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        self.root == Path::new("/")
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    pub fn current_profile(&self) -> Result<KernelProfile> {
+        load_profile(&self.path("etc/xbitos/kernel.toml"))
+    }
+
+    pub fn apply(&self, profile: KernelProfile) -> Result<()> {
+        info!("Applying {} kernel profile...", profile.name());
+        let previous = self.current_profile()?;
+
+        let sysctl_dir = self.path("etc/sysctl.d");
+        fs::create_dir_all(&sysctl_dir)?;
+        fs::write(sysctl_dir.join(SYSCTL_NAME), profile.render_sysctl())?;
+
+        let modprobe_dir = self.path("etc/modprobe.d");
+        fs::create_dir_all(&modprobe_dir)?;
+        fs::write(modprobe_dir.join(MODPROBE_NAME), profile.render_modprobe())?;
+
+        let settings_path = self.path("etc/xbitos/kernel.toml");
+        if let Some(parent) = settings_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&settings_path, toml::to_string_pretty(&ProfileSettings { profile })?)?;
+
+        // في المثبت تكتب معلمات النواة عند إنشاء إدخال الإقلاع
+        if self.is_live() {
+            // معلمات النواة قبل sysctl حتى لا يبقى التبديل نصف مطبق إذا فشل
+            let boot_manager = crate::system::bootloader::BootManager::new();
+            for parameter in profile.cmdline() {
+                boot_manager.set_kernel_parameter(parameter)?;
+            }
+            info!("Kernel command line changes take effect after reboot");
+
+            let output = Command::new("sysctl")
+                .arg("--system")
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::piped())
+                .output()?;
+            if !output.status.success() {
+                let errors = String::from_utf8_lossy(&output.stderr);
+                // بعد الملف hardened تبقى unprivileged_bpf_disabled مقفلة على 1 حتى إعادة التشغيل
+                let bpf_locked = fs::read_to_string("/proc/sys/kernel/unprivileged_bpf_disabled")
+                    .is_ok_and(|value| value.trim() == "1");
+                let only_bpf = errors
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .all(|line| line.contains("unprivileged_bpf_disabled"));
+
+                if !(bpf_locked && only_bpf) {
+                    return Err(anyhow::anyhow!(
+                        "The {} profile was written and the kernel command line updated, but sysctl failed: {}",
+                        profile.name(),
+                        errors.trim()
+                    ));
+                }
+            }
+
+            // المفاتيح المقفلة بعد hardened لا تعود إلا بإعادة التشغيل حتى لو نجح sysctl
+            if previous == KernelProfile::Hardened && profile != KernelProfile::Hardened {
+                warn!(
+                    "kernel.kexec_load_disabled and kernel.unprivileged_bpf_disabled stay 1 until reboot, the {} profile is fully applied after reboot",
+                    profile.name()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn load_profile(path: &Path) -> Result<KernelProfile> {
+    if !path.exists() {
+        return Ok(KernelProfile::default());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let settings: ProfileSettings =
+        toml::from_str(&content).with_context(|| format!("Invalid kernel profile settings: {}", path.display()))?;
+    Ok(settings.profile)
+}
@@ -0,0 +1,398 @@
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::system::local_db::{self, LocalDatabase, LocalPackage};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum IssueKind {
+    Missing,
+    Modified,
+    PermissionsChanged,
+    OwnershipChanged,
+    TypeChanged,
+    SymlinkChanged,
+    ModificationTimeChanged,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileIssue {
+    pub path: PathBuf,
+    pub kind: IssueKind,
+    pub details: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackageIntegrity {
+    pub package: String,
+    pub version: String,
+    pub files_checked: usize,
+    pub issues: Vec<FileIssue>,
+    // ما تعذر فحصه، مثل غياب mtree أو ملف لا يمكن قراءته، كتحذيرات pacman -Qkk
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl PackageIntegrity {
+    // تغير وقت التعديل وحده لا يعني أن الحزمة تالفة
+    pub fn is_damaged(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.kind != IssueKind::ModificationTimeChanged)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MtreeEntry {
+    path: String,
+    entry_type: String,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    mode: Option<u32>,
+    size: Option<u64>,
+    time: Option<i64>,
+    sha256: Option<String>,
+    link: Option<String>,
+}
+
+pub struct IntegrityChecker {
+    root: PathBuf,
+    db_path: PathBuf,
+}
+
+impl IntegrityChecker {
+    pub fn new() -> Self {
+        Self::with_root(Path::new("/"))
+    }
+
+    pub fn with_root(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            db_path: root.join("var/lib/pacman/local"),
+        }
+    }
+
+    pub fn check_all(&self) -> Result<Vec<PackageIntegrity>> {
+        info!("Verifying installed packages in {}", self.root.display());
+
+        let db = LocalDatabase::open(&self.db_path)?;
+        let mut packages: Vec<&LocalPackage> = db.packages().collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut reports = Vec::new();
+        for package in packages {
+            let report = self.check_package(package);
+            for warning in &report.warnings {
+                warn!("{}: {}", package.name, warning);
+            }
+            reports.push(report);
+        }
+
+        let damaged = reports.iter().filter(|r| r.is_damaged()).count();
+        info!("Checked {} packages, {} with problems", reports.len(), damaged);
+
+        Ok(reports)
+    }
+
+    pub fn check(&self, package_name: &str) -> Result<PackageIntegrity> {
+        let db = LocalDatabase::open(&self.db_path)?;
+        let package = db
+            .get(package_name)
+            .ok_or_else(|| anyhow::anyhow!("Package not installed: {}", package_name))?;
+
+        Ok(self.check_package(package))
+    }
+
+    // الأخطاء هنا لا توقف الفحص، بل تسجل تحذيرات للحزمة
+    fn check_package(&self, package: &LocalPackage) -> PackageIntegrity {
+        let mut report = PackageIntegrity {
+            package: package.name.clone(),
+            version: package.version.clone(),
+            files_checked: 0,
+            issues: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let entries = match read_mtree(&package.db_dir.join("mtree")) {
+            Ok(entries) => entries,
+            Err(e) => {
+                report.warnings.push(format!("cannot read mtree: {:#}", e));
+                return report;
+            }
+        };
+        let backup_files = read_backup_files(&package.db_dir).unwrap_or_else(|e| {
+            report.warnings.push(format!("cannot read backup list: {:#}", e));
+            HashSet::new()
+        });
+
+        for entry in &entries {
+            // تجاهل ملفات البيانات الوصفية مثل .PKGINFO
+            if entry.path.starts_with('.') {
+                continue;
+            }
+
+            report.files_checked += 1;
+            let is_backup = backup_files.contains(&entry.path);
+            match self.check_entry(entry, is_backup) {
+                Ok(issues) => report.issues.extend(issues),
+                Err(e) => report.warnings.push(format!("/{}: {:#}", entry.path, e)),
+            }
+        }
+
+        report
+    }
+
+    fn check_entry(&self, entry: &MtreeEntry, is_backup: bool) -> Result<Vec<FileIssue>> {
+        let path = self.root.join(&entry.path);
+        let display_path = PathBuf::from("/").join(&entry.path);
+        let mut issues = Vec::new();
+
+        let mut issue = |kind: IssueKind, details: String| {
+            issues.push(FileIssue {
+                path: display_path.clone(),
+                kind,
+                details,
+            });
+        };
+
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                issue(IssueKind::Missing, "file does not exist".to_string());
+                return Ok(issues);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let actual_type = if metadata.file_type().is_symlink() {
+            "link"
+        } else if metadata.is_dir() {
+            "dir"
+        } else {
+            "file"
+        };
+
+        if actual_type != entry.entry_type {
+            issue(
+                IssueKind::TypeChanged,
+                format!("expected {}, found {}", entry.entry_type, actual_type),
+            );
+            return Ok(issues);
+        }
+
+        if actual_type == "link" {
+            if let Some(expected) = &entry.link {
+                let target = fs::read_link(&path)?;
+                if target != Path::new(expected) {
+                    issue(
+                        IssueKind::SymlinkChanged,
+                        format!("points to {} instead of {}", target.display(), expected),
+                    );
+                }
+            }
+            return Ok(issues);
+        }
+
+        if let Some(mode) = entry.mode {
+            let actual = metadata.permissions().mode() & 0o7777;
+            if actual != mode {
+                issue(
+                    IssueKind::PermissionsChanged,
+                    format!("mode {:o} instead of {:o}", actual, mode),
+                );
+            }
+        }
+
+        if entry.uid.is_some_and(|uid| uid != metadata.uid())
+            || entry.gid.is_some_and(|gid| gid != metadata.gid())
+        {
+            issue(
+                IssueKind::OwnershipChanged,
+                format!(
+                    "owner {}:{} instead of {}:{}",
+                    metadata.uid(),
+                    metadata.gid(),
+                    entry.uid.unwrap_or(0),
+                    entry.gid.unwrap_or(0),
+                ),
+            );
+        }
+
+        // ملفات الإعداد (backup) يتوقع أن يعدلها المستخدم
+        if actual_type != "file" || is_backup {
+            return Ok(issues);
+        }
+
+        if let Some(time) = entry.time {
+            if metadata.mtime() != time {
+                issue(
+                    IssueKind::ModificationTimeChanged,
+                    format!("mtime {} instead of {}", metadata.mtime(), time),
+                );
+            }
+        }
+
+        if let Some(size) = entry.size {
+            if metadata.len() != size {
+                issue(
+                    IssueKind::Modified,
+                    format!("size {} instead of {}", metadata.len(), size),
+                );
+                return Ok(issues);
+            }
+        }
+
+        if let Some(expected) = &entry.sha256 {
+            let actual = sha256_file(&path)?;
+            if &actual != expected {
+                issue(IssueKind::Modified, "sha256 checksum mismatch".to_string());
+            }
+        }
+
+        Ok(issues)
+    }
+
+    pub fn reinstall_damaged(&self, reports: &[PackageIntegrity]) -> Result<Vec<String>> {
+        let damaged: Vec<String> = reports
+            .iter()
+            .filter(|r| r.is_damaged())
+            .map(|r| r.package.clone())
+            .collect();
+
+        if damaged.is_empty() {
+            return Ok(damaged);
+        }
+
+        warn!("Reinstalling damaged packages: {}", damaged.join(" "));
+
+        let mut command = Command::new("pacman");
+        command.args(["-S", "--noconfirm"]);
+        if self.root != Path::new("/") {
+            command.arg("--sysroot").arg(&self.root);
+        }
+
+        let status = command.args(&damaged).status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to reinstall damaged packages"));
+        }
+
+        Ok(damaged)
+    }
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn read_backup_files(db_dir: &Path) -> Result<HashSet<String>> {
+    let files_path = db_dir.join("files");
+    if !files_path.exists() {
+        return Ok(HashSet::new());
+    }
+
+    let content = fs::read_to_string(files_path)?;
+    Ok(local_db::sections(&content)
+        .remove("BACKUP")
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|line| line.split('\t').next().map(|f| f.to_string()))
+        .collect())
+}
+
+fn read_mtree(path: &Path) -> Result<Vec<MtreeEntry>> {
+    let mut content = String::new();
+    GzDecoder::new(fs::File::open(path)?).read_to_string(&mut content)?;
+    Ok(parse_mtree(&content))
+}
+
+fn parse_mtree(content: &str) -> Vec<MtreeEntry> {
+    let mut defaults: HashMap<String, String> = HashMap::new();
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let Some(first) = fields.next() else {
+            continue;
+        };
+
+        match first {
+            "/set" => {
+                for field in fields {
+                    if let Some((key, value)) = field.split_once('=') {
+                        defaults.insert(key.to_string(), value.to_string());
+                    }
+                }
+            }
+            "/unset" => {
+                for key in fields {
+                    defaults.remove(key);
+                }
+            }
+            path => {
+                let mut keywords = defaults.clone();
+                for field in fields {
+                    if let Some((key, value)) = field.split_once('=') {
+                        keywords.insert(key.to_string(), value.to_string());
+                    }
+                }
+
+                let path = unescape_mtree(path.trim_start_matches("./"));
+                entries.push(MtreeEntry {
+                    path,
+                    entry_type: keywords.get("type").cloned().unwrap_or_else(|| "file".to_string()),
+                    uid: keywords.get("uid").and_then(|v| v.parse().ok()),
+                    gid: keywords.get("gid").and_then(|v| v.parse().ok()),
+                    mode: keywords.get("mode").and_then(|v| u32::from_str_radix(v, 8).ok()),
+                    size: keywords.get("size").and_then(|v| v.parse().ok()),
+                    // الوقت بصيغة ثوانٍ.نانوثانية
+                    time: keywords
+                        .get("time")
+                        .and_then(|v| v.split('.').next())
+                        .and_then(|v| v.parse().ok()),
+                    sha256: keywords.get("sha256digest").cloned(),
+                    link: keywords.get("link").map(|v| unescape_mtree(v)),
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+// mtree يرمّز المسافات والمحارف الخاصة بصيغة ثمانية مثل \040
+fn unescape_mtree(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(digits, 8) {
+                result.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&result).to_string()
+}
@@ -1,6 +1,14 @@
 pub mod firewall;
 pub mod apparmor;
 pub mod package_verifier;
+pub mod integrity;
+pub mod audit;
+pub mod advisories;
+pub mod secure_boot;
+pub mod ssh;
+pub mod sudo;
+pub mod password;
+pub mod hardening;
 
 use anyhow::Result;
 use log::{info, error};
@@ -9,6 +17,9 @@ pub struct SecurityManager {
     firewall: firewall::FirewallManager,
     apparmor: apparmor::AppArmorManager,
     package_verifier: package_verifier::PackageVerifier,
+    integrity: integrity::IntegrityChecker,
+    ssh: ssh::SshManager,
+    sudo: sudo::SudoManager,
 }
 
 impl SecurityManager {
@@ -17,6 +28,9 @@ impl SecurityManager {
             firewall: firewall::FirewallManager::new(),
             apparmor: apparmor::AppArmorManager::new(),
             package_verifier: package_verifier::PackageVerifier::new(),
+            integrity: integrity::IntegrityChecker::new(),
+            ssh: ssh::SshManager::new(),
+            sudo: sudo::SudoManager::new(),
         }
     }
 
@@ -29,9 +43,43 @@ impl SecurityManager {
         // إعداد AppArmor
         self.apparmor.setup()?;
 
+        // تقوية خادم SSH
+        self.ssh.setup()?;
+
+        // صلاحيات sudo و polkit
+        self.sudo.setup()?;
+
         // إعداد التحقق من الحزم
         self.package_verifier.setup()?;
 
         Ok(())
     }
+
+    pub fn audit(&self) -> audit::AuditReport {
+        info!("Auditing system security...");
+        audit::SecurityAuditor::new().run()
+    }
+
+    // تدقيق نظام مثبت في مجلد آخر دون تشغيله
+    pub fn audit_root(&self, root: &std::path::Path) -> audit::AuditReport {
+        info!("Auditing system security in {}...", root.display());
+        audit::SecurityAuditor::with_root(root).run()
+    }
+
+    pub fn verify_installed_packages(&self, reinstall: bool) -> Result<Vec<integrity::PackageIntegrity>> {
+        info!("Verifying installed package files...");
+
+        let reports = self.integrity.check_all()?;
+
+        for report in reports.iter().filter(|r| r.is_damaged()) {
+            error!("{} {}: {} problems", report.package, report.version, report.issues.len());
+        }
+
+        // إعادة تثبيت الحزم المتضررة عند الطلب
+        if reinstall {
+            self.integrity.reinstall_damaged(&reports)?;
+        }
+
+        Ok(reports)
+    }
 } 
\ No newline at end of file
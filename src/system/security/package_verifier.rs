@@ -1,11 +1,90 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use base64::Engine;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use crate::system::local_db;
+use crate::system::packaging::repo_db::{read_package_info, RepoDatabase};
+use crate::system::packaging::signing::{signature_path, KeyManager};
+use super::integrity::sha256_file;
 
-pub struct PackageVerifier;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum Verdict {
+    Trusted { fingerprint: String },
+    UntrustedKey { fingerprint: String },
+    Unsigned,
+    UnknownKey { key_id: String },
+    BadSignature { reason: String },
+    HashMismatch { expected: String, actual: String },
+}
+
+impl Verdict {
+    pub fn is_trusted(&self) -> bool {
+        matches!(self, Verdict::Trusted { .. })
+    }
+
+    // بدون مفتاح توقيع يبقى المستودع "Optional TrustAll" كما في pacman.conf،
+    // فتقبل الحزم غير الموقعة أو الموقعة بأي مفتاح ما دامت تجزئتها مطابقة
+    pub fn is_acceptable(&self, signature_required: bool) -> bool {
+        match self {
+            Verdict::Trusted { .. } => true,
+            Verdict::Unsigned | Verdict::UntrustedKey { .. } => !signature_required,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackageVerification {
+    pub package: String,
+    pub file: PathBuf,
+    pub verdict: Verdict,
+}
+
+pub struct PackageVerifier {
+    keyring_path: PathBuf,
+    repo_path: PathBuf,
+    repo_name: String,
+    signing_key: Option<String>,
+}
 
 impl PackageVerifier {
     pub fn new() -> Self {
-        Self
+        Self {
+            keyring_path: PathBuf::from("/etc/pacman.d/gnupg"),
+            repo_path: PathBuf::from("/var/lib/xbitos/repo"),
+            repo_name: "xbitos".to_string(),
+            signing_key: KeyManager::new()
+                .get_config()
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring signing config: {}", e);
+                    None
+                })
+                .map(|config| config.key_id),
+        }
+    }
+
+    // حلقة مفاتيح ومستودع مختلفان، مثلاً للاختبار بمفاتيح مؤقتة
+    pub fn with_paths(keyring_path: &Path, repo_path: &Path) -> Self {
+        Self {
+            keyring_path: keyring_path.to_path_buf(),
+            repo_path: repo_path.to_path_buf(),
+            repo_name: "xbitos".to_string(),
+            signing_key: None,
+        }
+    }
+
+    // المفتاح الذي يوقع المستودع، وعند تحديده يصبح التوقيع إلزامياً
+    pub fn with_signing_key(mut self, fingerprint: Option<&str>) -> Self {
+        self.signing_key = fingerprint.map(|f| f.to_uppercase());
+        self
+    }
+
+    fn signature_required(&self) -> bool {
+        self.signing_key.is_some()
     }
 
     pub fn setup(&self) -> Result<()> {
@@ -17,16 +96,188 @@ impl PackageVerifier {
 
     fn setup_package_signing(&self) -> Result<()> {
         // إعداد مفاتيح التوقيع للمستودع
+        if !self.keyring_path.join("pubring.gpg").exists() && !self.keyring_path.join("pubring.kbx").exists() {
+            info!("Initializing package keyring in {}", self.keyring_path.display());
+            let status = Command::new("pacman-key").arg("--init").status()?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to initialize pacman keyring"));
+            }
+        }
         Ok(())
     }
 
     fn setup_checksum_verification(&self) -> Result<()> {
         // إعداد التحقق من التجزئات
+        if !self.repo_path.join(format!("{}.db", self.repo_name)).exists() {
+            warn!("Local repository database not found in {}", self.repo_path.display());
+        }
         Ok(())
     }
 
-    pub fn verify_package(&self, package_path: &str) -> Result<bool> {
-        // التحقق من توقيع الحزمة وتجزئتها
-        Ok(true)
+    pub fn verify_package(&self, package_path: &Path) -> Result<PackageVerification> {
+        let info = read_package_info(package_path)?;
+        let database = RepoDatabase::open(&self.repo_path, &self.repo_name)?;
+        let desc = database
+            .get_desc(&info.name)
+            .ok_or_else(|| anyhow::anyhow!("{} is not in the {} repository", info.name, self.repo_name))?;
+
+        let mut fields = local_db::sections(desc);
+        let mut single = |key: &str| fields.remove(key).and_then(|v| v.into_iter().next());
+        let expected_hash = single("SHA256SUM")
+            .ok_or_else(|| anyhow::anyhow!("No SHA256SUM for {} in repository database", info.name))?;
+        let embedded_signature = single("PGPSIG");
+
+        let verdict = self.check(package_path, &expected_hash, embedded_signature.as_deref())?;
+        Ok(PackageVerification {
+            package: info.name,
+            file: package_path.to_path_buf(),
+            verdict,
+        })
+    }
+
+    fn check(&self, package_path: &Path, expected_hash: &str, embedded_signature: Option<&str>) -> Result<Verdict> {
+        // التجزئة أولاً: حزمة مختلفة عن المسجلة لا معنى للتحقق من توقيعها
+        let actual = sha256_file(package_path)?;
+        if actual != expected_hash {
+            return Ok(Verdict::HashMismatch {
+                expected: expected_hash.to_string(),
+                actual,
+            });
+        }
+
+        let sidecar = signature_path(package_path);
+        if sidecar.exists() {
+            return self.verify_signature(package_path, &sidecar);
+        }
+
+        let Some(encoded) = embedded_signature else {
+            return Ok(Verdict::Unsigned);
+        };
+
+        // التوقيع المضمن في قاعدة البيانات بصيغة base64
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .context("Invalid PGPSIG in repository database")?;
+        let mut temporary = tempfile::NamedTempFile::new()?;
+        temporary.write_all(&decoded)?;
+        temporary.flush()?;
+
+        self.verify_signature(package_path, temporary.path())
+    }
+
+    fn verify_signature(&self, package_path: &Path, signature: &Path) -> Result<Verdict> {
+        let output = Command::new("gpg")
+            .arg("--homedir")
+            .arg(&self.keyring_path)
+            .args(["--batch", "--no-tty", "--status-fd", "1", "--verify"])
+            .arg(signature)
+            .arg(package_path)
+            .output()
+            .context("Failed to run gpg")?;
+
+        Ok(parse_status(&String::from_utf8_lossy(&output.stdout), self.signing_key.as_deref()))
+    }
+
+    // التحقق من الحزم الموجودة في المستودع المحلي قبل تثبيتها، مع الاعتماديات التي ستسحب منه
+    pub fn verify_repo_packages(&self, packages: &[&str]) -> Result<Vec<PackageVerification>> {
+        if !self.repo_path.join(format!("{}.db", self.repo_name)).exists() {
+            return Ok(Vec::new());
+        }
+
+        let database = RepoDatabase::open(&self.repo_path, &self.repo_name)?;
+        let mut results = Vec::new();
+
+        for package in self.local_targets(packages)? {
+            let Some(filename) = database.get_filename(&package) else {
+                continue;
+            };
+
+            let result = self.verify_package(&self.repo_path.join(filename))?;
+            if !result.verdict.is_acceptable(self.signature_required()) {
+                return Err(anyhow::anyhow!("Refusing to install {}: {:?}", package, result.verdict));
+            }
+
+            match &result.verdict {
+                Verdict::Trusted { .. } => info!("Verified {} from local repository", package),
+                verdict => warn!("Installing {} from local repository without a trusted signature: {:?}", package, verdict),
+            }
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    // pacman يحدد المستودع الذي ستأتي منه كل حزمة، بما فيها الاعتماديات غير المثبتة
+    fn local_targets(&self, packages: &[&str]) -> Result<Vec<String>> {
+        if packages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let output = Command::new("pacman")
+            .args(["-Sp", "--print-format", "%r %n"])
+            .args(packages)
+            .output()
+            .context("Failed to run pacman")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to resolve install targets: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .filter(|(repo, _)| *repo == self.repo_name)
+            .map(|(_, name)| name.to_string())
+            .collect())
+    }
+}
+
+fn parse_status(status: &str, signing_key: Option<&str>) -> Verdict {
+    let mut verdict = None;
+    let mut valid_fingerprint = None;
+    let mut fully_trusted = false;
+
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let keyword = fields.first().copied().unwrap_or("");
+        let argument = fields.get(1).copied().unwrap_or("").to_string();
+
+        match keyword {
+            // الحقل الأخير بصمة المفتاح الرئيسي إذا كان التوقيع من مفتاح فرعي
+            "VALIDSIG" => valid_fingerprint = Some(fields.get(10).copied().unwrap_or(&argument).to_string()),
+            "TRUST_FULLY" | "TRUST_ULTIMATE" => fully_trusted = true,
+            "BADSIG" => verdict = Some(Verdict::BadSignature { reason: format!("bad signature from {}", argument) }),
+            "EXPKEYSIG" => verdict = Some(Verdict::BadSignature { reason: format!("key {} has expired", argument) }),
+            "REVKEYSIG" => verdict = Some(Verdict::BadSignature { reason: format!("key {} has been revoked", argument) }),
+            "ERRSIG" | "NO_PUBKEY" => {
+                verdict.get_or_insert(Verdict::UnknownKey { key_id: argument });
+            }
+            "NODATA" => {
+                verdict.get_or_insert(Verdict::BadSignature { reason: "signature data is invalid".to_string() });
+            }
+            _ => {}
+        }
+    }
+
+    match (verdict, valid_fingerprint) {
+        (Some(verdict), _) => verdict,
+        // توقيع صحيح رياضياً لا يكفي، فأي مفتاح في الحلقة قد يكون مستورداً دون ثقة
+        (None, Some(fingerprint)) => {
+            let is_signing_key = signing_key.is_some_and(|key| key.eq_ignore_ascii_case(&fingerprint));
+            if fully_trusted || is_signing_key {
+                Verdict::Trusted { fingerprint }
+            } else {
+                Verdict::UntrustedKey { fingerprint }
+            }
+        }
+        (None, None) => Verdict::BadSignature {
+            reason: "gpg did not report a valid signature".to_string(),
+        },
     }
-} 
\ No newline at end of file
+}
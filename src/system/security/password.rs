@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const CRYPT_ALPHABET: &str = "./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// كلمة مرور نصية لا تظهر في السجلات ولا تحفظ مع الإعدادات
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: &str) -> Self {
+        Self(value.to_string())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashScheme {
+    Yescrypt,
+    Sha512,
+}
+
+// تجزئة بصيغة crypt كما في /etc/shadow
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PasswordHash(String);
+
+impl PasswordHash {
+    pub fn parse(value: &str) -> Result<Self> {
+        scheme_of(value)?;
+        Ok(Self(value.to_string()))
+    }
+
+    // yescrypt غير متوفر في Rust، لذلك نستخدم SHA-512 عند التجزئة محلياً
+    pub fn from_secret(secret: &Secret) -> Result<Self> {
+        if secret.expose().is_empty() {
+            return Err(anyhow::anyhow!("Password must not be empty"));
+        }
+        let hash = pwhash::sha512_crypt::hash(secret.expose()).context("Failed to hash password")?;
+        Ok(Self(hash))
+    }
+
+    pub fn scheme(&self) -> HashScheme {
+        scheme_of(&self.0).unwrap_or(HashScheme::Sha512)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for PasswordHash {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<PasswordHash> for String {
+    fn from(hash: PasswordHash) -> Self {
+        hash.0
+    }
+}
+
+impl fmt::Debug for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PasswordHash({:?}, <redacted>)", self.scheme())
+    }
+}
+
+fn scheme_of(value: &str) -> Result<HashScheme> {
+    let fields: Vec<&str> = value.split('$').collect();
+    let valid = |field: &str| !field.is_empty() && field.chars().all(|c| CRYPT_ALPHABET.contains(c));
+
+    match fields.as_slice() {
+        // $y$params$salt$hash
+        ["", "y", params, salt, hash] if valid(params) && valid(salt) && valid(hash) => Ok(HashScheme::Yescrypt),
+        // $6$salt$hash أو $6$rounds=N$salt$hash
+        ["", "6", salt, hash] if valid(salt) && hash.len() == 86 && valid(hash) => Ok(HashScheme::Sha512),
+        ["", "6", rounds, salt, hash]
+            if rounds.strip_prefix("rounds=").is_some_and(|n| n.parse::<u32>().is_ok())
+                && valid(salt)
+                && hash.len() == 86
+                && valid(hash) =>
+        {
+            Ok(HashScheme::Sha512)
+        }
+        _ => Err(anyhow::anyhow!("Password hash must be a yescrypt ($y$) or SHA-512 ($6$) crypt string")),
+    }
+}
+
+// التجزئة تمرر عبر stdin حتى لا تظهر في قائمة العمليات
+pub fn set_password_hash(root: &Path, username: &str, hash: &PasswordHash) -> Result<()> {
+    if username.is_empty() || username.contains([':', '\n']) {
+        return Err(anyhow::anyhow!("Invalid user name: {:?}", username));
+    }
+
+    let mut command = if root == Path::new("/") {
+        Command::new("chpasswd")
+    } else {
+        let mut command = Command::new("arch-chroot");
+        command.arg(root).arg("chpasswd");
+        command
+    };
+
+    let mut child = command
+        .arg("-e")
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run chpasswd")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{}:{}", username, hash.as_str())?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Failed to set password for {}", username));
+    }
+
+    Ok(())
+}
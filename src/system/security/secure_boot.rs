@@ -0,0 +1,367 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// متغيرات EFI العامة لمعرفة حالة Secure Boot
+const EFI_GLOBAL_GUID: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+// المفتاح الأعلى يوقع ما تحته: PK يوقع KEK و KEK يوقع db
+const KEY_CHAIN: [(&str, &str, Option<&str>); 3] = [
+    ("PK", "Platform Key", None),
+    ("KEK", "Key Exchange Key", Some("PK")),
+    ("db", "Signature Database Key", Some("KEK")),
+];
+
+const SYSTEMD_BOOT: &str = "/usr/lib/systemd/boot/efi/systemd-bootx64.efi";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SecureBootStatus {
+    pub efi: bool,
+    pub enabled: bool,
+    pub setup_mode: bool,
+    pub keys_present: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SignedFile {
+    pub path: PathBuf,
+    // الملف كان موقعاً بمفتاحنا مسبقاً فلم نعد توقيعه
+    pub already_signed: bool,
+}
+
+pub struct SecureBootManager {
+    keys_path: PathBuf,
+    esp_path: PathBuf,
+    boot_path: PathBuf,
+    hooks_path: PathBuf,
+}
+
+impl SecureBootManager {
+    pub fn new() -> Self {
+        Self {
+            keys_path: PathBuf::from("/etc/xbitos/secureboot"),
+            esp_path: PathBuf::from("/boot/efi"),
+            boot_path: PathBuf::from("/boot"),
+            hooks_path: PathBuf::from("/etc/pacman.d/hooks"),
+        }
+    }
+
+    // مجلد مفاتيح آخر، مثلاً لتجربة التوقيع على ملفات في مجلد مؤقت
+    pub fn with_keys_path(mut self, keys_path: &Path) -> Self {
+        self.keys_path = keys_path.to_path_buf();
+        self
+    }
+
+    pub fn with_esp_path(mut self, esp_path: &Path, boot_path: &Path) -> Self {
+        self.esp_path = esp_path.to_path_buf();
+        self.boot_path = boot_path.to_path_buf();
+        self
+    }
+
+    pub fn get_keys_path(&self) -> &PathBuf {
+        &self.keys_path
+    }
+
+    fn key(&self, name: &str) -> PathBuf {
+        self.keys_path.join(format!("{}.key", name))
+    }
+
+    fn cert(&self, name: &str) -> PathBuf {
+        self.keys_path.join(format!("{}.crt", name))
+    }
+
+    pub fn has_keys(&self) -> bool {
+        KEY_CHAIN
+            .iter()
+            .all(|(name, _, _)| self.key(name).exists() && self.cert(name).exists())
+    }
+
+    pub fn status(&self) -> SecureBootStatus {
+        let efivars = Path::new("/sys/firmware/efi/efivars");
+        SecureBootStatus {
+            efi: Path::new("/sys/firmware/efi").exists(),
+            enabled: read_efi_flag(&efivars.join(format!("SecureBoot-{}", EFI_GLOBAL_GUID))),
+            setup_mode: read_efi_flag(&efivars.join(format!("SetupMode-{}", EFI_GLOBAL_GUID))),
+            keys_present: self.has_keys(),
+        }
+    }
+
+    pub fn generate_keys(&self, common_name: &str) -> Result<()> {
+        if self.has_keys() {
+            return Err(anyhow::anyhow!(
+                "Secure Boot keys already exist in {}",
+                self.keys_path.display()
+            ));
+        }
+
+        info!("Generating Secure Boot keys in {}", self.keys_path.display());
+        fs::create_dir_all(&self.keys_path)?;
+        set_mode(&self.keys_path, 0o700)?;
+
+        let guid = uuid::Uuid::new_v4().to_string();
+        fs::write(self.keys_path.join("GUID"), format!("{}\n", guid))?;
+
+        for (name, description, _) in KEY_CHAIN {
+            let status = Command::new("openssl")
+                .args(["req", "-new", "-x509", "-newkey", "rsa:4096", "-nodes", "-sha256", "-days", "3650"])
+                .args(["-subj", &format!("/CN={} {}/", common_name, description)])
+                .arg("-keyout")
+                .arg(self.key(name))
+                .arg("-out")
+                .arg(self.cert(name))
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .context("Failed to run openssl")?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to generate {} key", name));
+            }
+            set_mode(&self.key(name), 0o600)?;
+
+            // صيغة DER لتسجيل المفاتيح من واجهة البرنامج الثابت
+            let status = Command::new("openssl")
+                .args(["x509", "-outform", "DER", "-in"])
+                .arg(self.cert(name))
+                .arg("-out")
+                .arg(self.keys_path.join(format!("{}.cer", name)))
+                .status()?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to convert {} certificate", name));
+            }
+        }
+
+        if let Err(e) = self.create_signature_lists(&guid) {
+            warn!("{}, only .cer files are available for enrollment", e);
+        }
+
+        Ok(())
+    }
+
+    // ملفات .auth لتسجيل المفاتيح بـ efi-updatevar أو KeyTool
+    fn create_signature_lists(&self, guid: &str) -> Result<()> {
+        for (name, _, signer) in KEY_CHAIN {
+            let esl = self.keys_path.join(format!("{}.esl", name));
+            let status = Command::new("cert-to-efi-sig-list")
+                .args(["-g", guid])
+                .arg(self.cert(name))
+                .arg(&esl)
+                .status()
+                .context("efitools is not installed")?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to create {} signature list", name));
+            }
+
+            // PK يوقع نفسه
+            let signer = signer.unwrap_or(name);
+            let status = Command::new("sign-efi-sig-list")
+                .args(["-g", guid, "-k"])
+                .arg(self.key(signer))
+                .arg("-c")
+                .arg(self.cert(signer))
+                .arg(name)
+                .arg(&esl)
+                .arg(self.keys_path.join(format!("{}.auth", name)))
+                .stdout(Stdio::null())
+                .status()?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to sign {} signature list", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn require_keys(&self) -> Result<()> {
+        if !self.has_keys() {
+            return Err(anyhow::anyhow!(
+                "No Secure Boot keys in {}, run `xbitos secureboot keys NAME` first",
+                self.keys_path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn verify(&self, path: &Path) -> Result<bool> {
+        self.require_keys()?;
+        let status = Command::new("sbverify")
+            .arg("--cert")
+            .arg(self.cert("db"))
+            .arg(path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to run sbverify, is sbsigntools installed?")?;
+        Ok(status.success())
+    }
+
+    // يعمل على أي ملف EFI، ويكتب في output أو فوق الملف نفسه
+    pub fn sign(&self, path: &Path, output: Option<&Path>) -> Result<SignedFile> {
+        self.require_keys()?;
+        let target = output.unwrap_or(path).to_path_buf();
+
+        // نسخة output قد تكون من إصدار أقدم للملف فنعيد توقيعها دائماً
+        if output.is_none() && self.verify(path)? {
+            return Ok(SignedFile { path: target, already_signed: true });
+        }
+
+        // الكتابة إلى ملف مؤقت حتى لا يبقى ملف إقلاع نصف مكتوب
+        let mut temporary = target.clone().into_os_string();
+        temporary.push(".xbitos-signing");
+        let temporary = PathBuf::from(temporary);
+
+        let output = Command::new("sbsign")
+            .arg("--key")
+            .arg(self.key("db"))
+            .arg("--cert")
+            .arg(self.cert("db"))
+            .arg("--output")
+            .arg(&temporary)
+            .arg(path)
+            .output()
+            .context("Failed to run sbsign, is sbsigntools installed?")?;
+
+        if !output.status.success() {
+            let _ = fs::remove_file(&temporary);
+            return Err(anyhow::anyhow!(
+                "Failed to sign {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        fs::rename(&temporary, &target)?;
+        if !self.verify(&target)? {
+            return Err(anyhow::anyhow!("Signature of {} does not verify", target.display()));
+        }
+
+        info!("Signed {}", target.display());
+        Ok(SignedFile { path: target, already_signed: false })
+    }
+
+    // كل ما يحمله البرنامج الثابت أو systemd-boot
+    pub fn boot_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+
+        for loader in ["EFI/systemd/systemd-bootx64.efi", "EFI/BOOT/BOOTX64.EFI"] {
+            let path = self.esp_path.join(loader);
+            if path.exists() {
+                files.push(path);
+            }
+        }
+
+        files.extend(list_files(&self.esp_path.join("EFI/Linux"), |name| name.ends_with(".efi")));
+        files.extend(list_files(&self.esp_path, |name| name.starts_with("vmlinuz-")));
+        if self.boot_path != self.esp_path {
+            files.extend(list_files(&self.boot_path, |name| name.starts_with("vmlinuz-")));
+        }
+
+        files
+    }
+
+    pub fn sign_all(&self) -> Result<Vec<SignedFile>> {
+        self.require_keys()?;
+        let mut signed = Vec::new();
+
+        // bootctl install و update يفضلان نسخة .signed إن وجدت
+        let systemd_boot = Path::new(SYSTEMD_BOOT);
+        if systemd_boot.exists() {
+            let output = PathBuf::from(format!("{}.signed", SYSTEMD_BOOT));
+            signed.push(self.sign(systemd_boot, Some(&output))?);
+        }
+
+        for file in self.boot_files() {
+            signed.push(self.sign(&file, None)?);
+        }
+
+        Ok(signed)
+    }
+
+    pub fn install_hook(&self) -> Result<()> {
+        fs::create_dir_all(&self.hooks_path)?;
+
+        // يعمل بعد 90-mkinitcpio-install الذي ينسخ النواة إلى /boot
+        let hook = r#"[Trigger]
+Operation = Install
+Operation = Upgrade
+Type = Path
+Target = usr/lib/modules/*/vmlinuz
+Target = usr/lib/initcpio/*
+Target = usr/lib/systemd/boot/efi/systemd-boot*.efi
+
+[Action]
+Description = Signing EFI binaries for Secure Boot...
+When = PostTransaction
+Exec = /usr/bin/xbitos secureboot sign-all
+Depends = sbsigntools
+"#;
+
+        fs::write(self.hooks_path.join("95-xbitos-secureboot.hook"), hook)?;
+        info!("Installed Secure Boot signing hook");
+        Ok(())
+    }
+
+    pub fn enrollment_instructions(&self) -> String {
+        let keys = self.keys_path.display();
+        format!(
+            r#"Enrolling the xBitOS Secure Boot keys
+
+Your keys are in {keys}. Keep PK.key and KEK.key safe: anyone holding them
+can sign code that your firmware will trust.
+
+1. Sign the boot files first and check them:
+     xbitos secureboot sign-all
+     xbitos secureboot verify {esp}/EFI/systemd/systemd-bootx64.efi
+
+2. Reboot into the firmware setup, clear the Secure Boot keys or
+   choose "Reset to Setup Mode", and boot xBitOS again. Check with:
+     xbitos secureboot status      (setup mode must be "yes")
+
+3. Enroll the keys, PK last because it leaves setup mode:
+     efi-updatevar -f {keys}/db.auth db
+     efi-updatevar -f {keys}/KEK.auth KEK
+     efi-updatevar -f {keys}/PK.auth PK
+   If your firmware offers key enrollment in its menu, use the
+   db.cer, KEK.cer and PK.cer files from a FAT USB stick instead.
+
+4. Reboot into the firmware setup and enable Secure Boot.
+
+Warning: removing the vendor keys also removes trust in Microsoft
+signed option ROMs. Some graphics cards and storage controllers need
+them to work at boot. Dual booting Windows also needs the Microsoft
+keys appended to db and KEK before enrolling PK.
+"#,
+            keys = keys,
+            esp = self.esp_path.display()
+        )
+    }
+}
+
+fn read_efi_flag(path: &Path) -> bool {
+    // أول أربعة بايتات سمات المتغير ثم القيمة
+    fs::read(path)
+        .map(|data| data.get(4) == Some(&1))
+        .unwrap_or(false)
+}
+
+fn list_files(dir: &Path, filter: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(read) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = read
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.file_name().is_some_and(|name| filter(&name.to_string_lossy())))
+        .collect();
+    files.sort();
+    files
+}
+
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
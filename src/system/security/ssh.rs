@@ -0,0 +1,487 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use log::info;
+use serde::{Serialize, Deserialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const DROPIN_NAME: &str = "10-xbitos.conf";
+const INCLUDE_LINE: &str = "Include /etc/ssh/sshd_config.d/*.conf";
+// وجود هذا الملف يطلب إعادة إنشاء مفاتيح المضيف في الإقلاع التالي
+const REGENERATE_FLAG: &str = "etc/ssh/.xbitos-regenerate-host-keys";
+
+const KEY_TYPES: [&str; 7] = [
+    "ssh-ed25519",
+    "ssh-rsa",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "sk-ssh-ed25519@openssh.com",
+    "sk-ecdsa-sha2-nistp256@openssh.com",
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SshConfig {
+    // الخادم لا يعمل إلا إذا طلب المسؤول ذلك
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub allow_users: Vec<String>,
+    #[serde(default)]
+    pub allow_groups: Vec<String>,
+    #[serde(default = "default_max_auth_tries")]
+    pub max_auth_tries: u32,
+    #[serde(default = "default_kex")]
+    pub kex_algorithms: Vec<String>,
+    #[serde(default = "default_ciphers")]
+    pub ciphers: Vec<String>,
+    #[serde(default = "default_macs")]
+    pub macs: Vec<String>,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+fn default_max_auth_tries() -> u32 {
+    3
+}
+
+fn default_kex() -> Vec<String> {
+    ["sntrup761x25519-sha512@openssh.com", "curve25519-sha256", "curve25519-sha256@libssh.org"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_ciphers() -> Vec<String> {
+    ["chacha20-poly1305@openssh.com", "aes256-gcm@openssh.com", "aes128-gcm@openssh.com"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_macs() -> Vec<String> {
+    ["hmac-sha2-512-etm@openssh.com", "hmac-sha2-256-etm@openssh.com"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_port(),
+            allow_users: Vec::new(),
+            allow_groups: Vec::new(),
+            max_auth_tries: default_max_auth_tries(),
+            kex_algorithms: default_kex(),
+            ciphers: default_ciphers(),
+            macs: default_macs(),
+        }
+    }
+}
+
+impl SshConfig {
+    pub fn render(&self) -> String {
+        let mut out = String::from("# Generated by xBitOS, edit /etc/xbitos/ssh.toml instead\n\n");
+
+        out.push_str(&format!("Port {}\n\n", self.port));
+
+        // المصادقة بالمفاتيح فقط
+        out.push_str("PermitRootLogin no\n");
+        out.push_str("PubkeyAuthentication yes\n");
+        out.push_str("AuthenticationMethods publickey\n");
+        out.push_str("PasswordAuthentication no\n");
+        out.push_str("KbdInteractiveAuthentication no\n");
+        out.push_str("PermitEmptyPasswords no\n");
+        out.push_str(&format!("MaxAuthTries {}\n", self.max_auth_tries));
+        out.push_str("LoginGraceTime 30\n");
+        if !self.allow_users.is_empty() {
+            out.push_str(&format!("AllowUsers {}\n", self.allow_users.join(" ")));
+        }
+        if !self.allow_groups.is_empty() {
+            out.push_str(&format!("AllowGroups {}\n", self.allow_groups.join(" ")));
+        }
+
+        out.push_str("\nHostKey /etc/ssh/ssh_host_ed25519_key\n");
+        out.push_str("HostKey /etc/ssh/ssh_host_rsa_key\n");
+        out.push_str("HostKeyAlgorithms ssh-ed25519,rsa-sha2-512,rsa-sha2-256\n");
+        out.push_str(&format!("KexAlgorithms {}\n", self.kex_algorithms.join(",")));
+        out.push_str(&format!("Ciphers {}\n", self.ciphers.join(",")));
+        out.push_str(&format!("MACs {}\n", self.macs.join(",")));
+
+        out.push_str("\nX11Forwarding no\n");
+        out.push_str("AllowAgentForwarding no\n");
+        out.push_str("ClientAliveInterval 300\n");
+        out.push_str("ClientAliveCountMax 2\n");
+
+        out
+    }
+}
+
+pub struct SshManager {
+    root: PathBuf,
+    config_path: PathBuf,
+}
+
+impl SshManager {
+    pub fn new() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+            config_path: PathBuf::from("/etc/xbitos/ssh.toml"),
+        }
+    }
+
+    // إعداد نظام مثبت في مجلد آخر، مثلاً من المثبت
+    pub fn with_root(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            config_path: root.join("etc/xbitos/ssh.toml"),
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        self.root == Path::new("/")
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    pub fn load_config(&self) -> Result<SshConfig> {
+        if !self.config_path.exists() {
+            return Ok(SshConfig::default());
+        }
+
+        let content = fs::read_to_string(&self.config_path)?;
+        toml::from_str(&content).with_context(|| format!("Invalid SSH config: {}", self.config_path.display()))
+    }
+
+    pub fn setup(&self) -> Result<()> {
+        // كتابة الإعداد الافتراضي ليعدله المسؤول لاحقاً
+        if !self.config_path.exists() {
+            if let Some(parent) = self.config_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&self.config_path, toml::to_string_pretty(&SshConfig::default())?)?;
+        }
+
+        let config = self.load_config()?;
+
+        // في المثبت تنصب الحزمة بـ pacstrap قبل استدعاء هذه الدالة
+        if self.is_live() {
+            let pkg_manager = crate::system::package_manager::PackageManager::new();
+            pkg_manager.install_packages(&["openssh"])?;
+        }
+
+        self.apply(&config)?;
+        self.install_host_key_service()?;
+
+        if config.enabled {
+            self.systemctl(&["enable", "sshd.service"])?;
+            if self.is_live() {
+                let service_manager = crate::system::services::ServiceManager::new();
+                service_manager.start_service("sshd")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn apply(&self, config: &SshConfig) -> Result<()> {
+        let main_config = self.path("etc/ssh/sshd_config");
+        let dropin_dir = self.path("etc/ssh/sshd_config.d");
+        let rendered = config.render();
+        let current = fs::read_to_string(&main_config).unwrap_or_default();
+
+        // الإعداد الكامل كما سيقرؤه sshd يفحص في نسخة مؤقتة قبل تغيير أي ملف
+        check_combined(&current, &dropin_dir, &rendered)?;
+
+        fs::create_dir_all(&dropin_dir)?;
+        let dropin = dropin_dir.join(DROPIN_NAME);
+        let temporary = dropin.with_extension("conf.new");
+        fs::write(&temporary, &rendered)?;
+        fs::rename(&temporary, &dropin)?;
+
+        let updated = include_first(&current, INCLUDE_LINE);
+        if updated != current {
+            fs::write(&main_config, updated)?;
+        }
+
+        if self.is_live() {
+            let active = Command::new("systemctl")
+                .args(["is-active", "--quiet", "sshd"])
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(false);
+            if active {
+                self.systemctl(&["reload", "sshd"])?;
+            }
+        }
+
+        info!("Wrote SSH server configuration to {}", dropin.display());
+        Ok(())
+    }
+
+    fn user_home(&self, username: &str) -> Result<(PathBuf, u32, u32)> {
+        let passwd = fs::read_to_string(self.path("etc/passwd"))?;
+
+        for line in passwd.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() >= 6 && fields[0] == username {
+                let uid = fields[2].parse().context("Invalid uid in /etc/passwd")?;
+                let gid = fields[3].parse().context("Invalid gid in /etc/passwd")?;
+                return Ok((self.path(fields[5]), uid, gid));
+            }
+        }
+
+        Err(anyhow::anyhow!("User {} does not exist", username))
+    }
+
+    // إضافة المفاتيح العامة دون تكرار ما هو موجود
+    pub fn import_authorized_keys(&self, username: &str, keys: &[String]) -> Result<usize> {
+        let (home, uid, gid) = self.user_home(username)?;
+
+        let mut valid = Vec::new();
+        for key in keys.iter().flat_map(|k| k.lines()).map(|k| k.trim()) {
+            if key.is_empty() || key.starts_with('#') {
+                continue;
+            }
+            validate_public_key(key)?;
+            valid.push(key.to_string());
+        }
+
+        // المجلد يملكه المستخدم، فرابط رمزي مثل ‎.ssh -> /etc قد يجعلنا نكتب ملفات النظام
+        // بصلاحيات الجذر، لذلك لا نتبع الروابط ونعمل على المقابض المفتوحة فقط
+        let ssh_dir = home.join(".ssh");
+        match fs::DirBuilder::new().mode(0o700).create(&ssh_dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e.into()),
+            _ => {}
+        }
+        let dir = fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_DIRECTORY | libc::O_NOFOLLOW)
+            .open(&ssh_dir)
+            .with_context(|| format!("{} is not a directory", ssh_dir.display()))?;
+
+        let mut file = open_in_dir(&dir, c"authorized_keys")
+            .with_context(|| format!("Failed to open {}/authorized_keys", ssh_dir.display()))?;
+        let metadata = file.metadata()?;
+        // رابط صلب إلى ملف آخر يعني أننا سنعدل ذلك الملف
+        if !metadata.is_file() || metadata.nlink() != 1 {
+            return Err(anyhow::anyhow!("{}/authorized_keys is not a regular file", ssh_dir.display()));
+        }
+
+        let mut existing = String::new();
+        file.read_to_string(&mut existing)?;
+        let known: Vec<String> = existing.lines().filter_map(key_blob).collect();
+
+        let mut content = existing.clone();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+
+        let mut added = 0;
+        for key in valid {
+            if key_blob(&key).is_some_and(|blob| known.contains(&blob) || content.contains(&blob)) {
+                continue;
+            }
+            content.push_str(&key);
+            content.push('\n');
+            added += 1;
+        }
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(content.as_bytes())?;
+
+        // sshd يرفض الملفات التي يمكن لغير المالك الكتابة فيها
+        dir.set_permissions(fs::Permissions::from_mode(0o700))?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        std::os::unix::fs::fchown(&dir, Some(uid), Some(gid))?;
+        std::os::unix::fs::fchown(&file, Some(uid), Some(gid))?;
+
+        info!("Added {} authorized keys for {}", added, username);
+        Ok(added)
+    }
+
+    // مفاتيح المضيف المنسوخة من صورة التثبيت يجب ألا تتكرر بين الأجهزة
+    pub fn schedule_host_key_regeneration(&self) -> Result<()> {
+        self.install_host_key_service()?;
+
+        let flag = self.path(REGENERATE_FLAG);
+        if let Some(parent) = flag.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&flag, "")?;
+
+        info!("SSH host keys will be regenerated on next boot");
+        Ok(())
+    }
+
+    fn install_host_key_service(&self) -> Result<()> {
+        let unit = format!(
+            r#"[Unit]
+Description=Regenerate SSH host keys on first boot
+ConditionPathExists=/{flag}
+Before=sshd.service
+
+[Service]
+Type=oneshot
+ExecStart=/bin/sh -c 'rm -f /etc/ssh/ssh_host_*_key /etc/ssh/ssh_host_*_key.pub'
+ExecStart=/usr/bin/ssh-keygen -q -t ed25519 -N "" -f /etc/ssh/ssh_host_ed25519_key
+ExecStart=/usr/bin/ssh-keygen -q -t rsa -b 4096 -N "" -f /etc/ssh/ssh_host_rsa_key
+ExecStartPost=/usr/bin/rm -f /{flag}
+
+[Install]
+WantedBy=multi-user.target
+"#,
+            flag = REGENERATE_FLAG
+        );
+
+        let units_dir = self.path("etc/systemd/system");
+        fs::create_dir_all(&units_dir)?;
+        fs::write(units_dir.join("xbitos-ssh-hostkeys.service"), unit)?;
+
+        self.systemctl(&["enable", "xbitos-ssh-hostkeys.service"])
+    }
+
+    fn systemctl(&self, args: &[&str]) -> Result<()> {
+        let mut command = Command::new("systemctl");
+        if !self.is_live() {
+            command.arg(format!("--root={}", self.root.display()));
+        }
+        let status = command.args(args).status()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("systemctl {} failed", args.join(" ")));
+        }
+        Ok(())
+    }
+}
+
+// فتح ملف داخل مجلد مفتوح دون اتباع رابط رمزي في اسمه
+fn open_in_dir(dir: &File, name: &std::ffi::CStr) -> std::io::Result<File> {
+    let flags = libc::O_RDWR | libc::O_CREAT | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+    // openat لا يوجد في std، والواصف الناتج يملكه File وحده
+    let fd = unsafe { libc::openat(dir.as_raw_fd(), name.as_ptr(), flags, 0o600) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+pub fn validate_public_key(key: &str) -> Result<()> {
+    // الخيارات مثل from="..." قد تسبق نوع المفتاح
+    let fields: Vec<&str> = key.split_whitespace().collect();
+    let position = fields
+        .iter()
+        .position(|field| KEY_TYPES.contains(field))
+        .ok_or_else(|| anyhow::anyhow!("Unsupported SSH key type in: {}", key))?;
+
+    let blob = fields
+        .get(position + 1)
+        .ok_or_else(|| anyhow::anyhow!("SSH key has no data: {}", key))?;
+    base64::engine::general_purpose::STANDARD
+        .decode(blob)
+        .with_context(|| format!("Invalid SSH key data: {}", key))?;
+
+    Ok(())
+}
+
+fn key_blob(key: &str) -> Option<String> {
+    let fields: Vec<&str> = key.split_whitespace().collect();
+    let position = fields.iter().position(|field| KEY_TYPES.contains(field))?;
+    fields.get(position + 1).map(|blob| blob.to_string())
+}
+
+// sshd يأخذ أول قيمة لكل خيار، لذلك يجب أن يكون Include في أول الملف
+fn include_first(content: &str, include: &str) -> String {
+    let first_directive = content
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'));
+    if first_directive == Some(include) {
+        return content.to_string();
+    }
+
+    let rest: Vec<&str> = content
+        .lines()
+        .filter(|line| line.trim() != INCLUDE_LINE && line.trim() != include)
+        .collect();
+    format!("{}
+
+{}
+", include, rest.join("
+"))
+}
+
+// نسخة من sshd_config ومن الملفات الإضافية الأخرى مع الملف الجديد، والـ Include يشير إليها
+fn check_combined(main_config: &str, dropin_dir: &Path, rendered: &str) -> Result<()> {
+    let check_dir = tempfile::Builder::new().prefix("xbitos-sshd-config-").tempdir()?;
+    let check_dropins = check_dir.path().join("sshd_config.d");
+    fs::create_dir(&check_dropins)?;
+
+    if let Ok(read) = fs::read_dir(dropin_dir) {
+        for entry in read.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name();
+            if name.to_string_lossy().ends_with(".conf") && name != DROPIN_NAME {
+                fs::copy(entry.path(), check_dropins.join(&name))?;
+            }
+        }
+    }
+    fs::write(check_dropins.join(DROPIN_NAME), rendered)?;
+
+    let check_config = check_dir.path().join("sshd_config");
+    let include = format!("Include {}/*.conf", check_dropins.display());
+    fs::write(&check_config, include_first(main_config, &include))?;
+
+    validate_config(&check_config)
+}
+
+// sshd -t يحتاج مفتاح مضيف، ونستخدم مفتاحاً مؤقتاً إن لم توجد مفاتيح بعد
+pub fn validate_config(config: &Path) -> Result<()> {
+    // مجلد جديد باسم غير متوقع حتى لا يسبقنا إليه مستخدم آخر في /tmp
+    let check_dir = tempfile::Builder::new().prefix("xbitos-sshd-check-").tempdir()?;
+    let host_key = check_dir.path().join("host_key");
+
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(&host_key)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .context("Failed to run ssh-keygen")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Failed to create temporary host key"));
+    }
+
+    let output = match Command::new("sshd").arg("-t").arg("-f").arg(config).arg("-h").arg(&host_key).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow::anyhow!(
+                "sshd not found, cannot check {} (install openssh)",
+                config.display()
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "sshd rejected {}: {}",
+            config.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
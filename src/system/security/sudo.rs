@@ -0,0 +1,360 @@
+use anyhow::{Context, Result};
+use log::info;
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// sudo يتجاهل ملفات sudoers.d التي تحتوي على نقطة، لذلك الملف المؤقت لا يقرأ قبل فحصه
+const SUDOERS_NAME: &str = "10-xbitos";
+const POLKIT_NAME: &str = "49-xbitos-admin.rules";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Lecture {
+    Always,
+    #[default]
+    Once,
+    Never,
+}
+
+impl Lecture {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Lecture::Always => "always",
+            Lecture::Once => "once",
+            Lecture::Never => "never",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CommandAlias {
+    pub name: String,
+    pub commands: Vec<String>,
+}
+
+// القاعدة تخص مستخدماً واحداً أو مجموعة واحدة
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SudoRule {
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default = "default_all")]
+    pub run_as: String,
+    #[serde(default = "default_all_commands")]
+    pub commands: Vec<String>,
+    #[serde(default)]
+    pub nopasswd: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SudoPolicy {
+    // بالدقائق، والصفر يطلب كلمة المرور في كل مرة
+    #[serde(default = "default_timestamp_timeout")]
+    pub timestamp_timeout: i32,
+    #[serde(default)]
+    pub lecture: Lecture,
+    #[serde(default)]
+    pub command_aliases: Vec<CommandAlias>,
+    #[serde(default)]
+    pub rules: Vec<SudoRule>,
+    // المجموعات التي تعد مشرفة في polkit
+    #[serde(default = "default_admin_groups")]
+    pub polkit_admin_groups: Vec<String>,
+}
+
+fn default_all() -> String {
+    "ALL".to_string()
+}
+
+fn default_all_commands() -> Vec<String> {
+    vec![default_all()]
+}
+
+fn default_timestamp_timeout() -> i32 {
+    5
+}
+
+fn default_admin_groups() -> Vec<String> {
+    vec!["wheel".to_string()]
+}
+
+impl Default for SudoPolicy {
+    fn default() -> Self {
+        Self {
+            timestamp_timeout: default_timestamp_timeout(),
+            lecture: Lecture::default(),
+            command_aliases: Vec::new(),
+            rules: vec![SudoRule {
+                user: None,
+                group: Some("wheel".to_string()),
+                run_as: default_all(),
+                commands: default_all_commands(),
+                nopasswd: false,
+            }],
+            polkit_admin_groups: default_admin_groups(),
+        }
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+        && !name.starts_with('-')
+}
+
+fn valid_alias(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && name != "ALL"
+}
+
+impl SudoPolicy {
+    pub fn validate(&self) -> Result<()> {
+        if self.timestamp_timeout < -1 {
+            return Err(anyhow::anyhow!("timestamp_timeout must be -1 or more"));
+        }
+
+        let mut aliases = Vec::new();
+        for alias in &self.command_aliases {
+            if !valid_alias(&alias.name) {
+                return Err(anyhow::anyhow!("Invalid command alias name: {}", alias.name));
+            }
+            if aliases.contains(&alias.name.as_str()) {
+                return Err(anyhow::anyhow!("Duplicate command alias: {}", alias.name));
+            }
+            if alias.commands.is_empty() {
+                return Err(anyhow::anyhow!("Command alias {} has no commands", alias.name));
+            }
+            for command in &alias.commands {
+                validate_command(command)?;
+            }
+            aliases.push(alias.name.as_str());
+        }
+
+        for rule in &self.rules {
+            let principal = match (&rule.user, &rule.group) {
+                (Some(user), None) => user,
+                (None, Some(group)) => group,
+                _ => return Err(anyhow::anyhow!("Each sudo rule needs exactly one of user or group")),
+            };
+            if !valid_name(principal) {
+                return Err(anyhow::anyhow!("Invalid user or group name: {}", principal));
+            }
+            if rule.run_as != "ALL" && !valid_name(&rule.run_as) {
+                return Err(anyhow::anyhow!("Invalid run_as user: {}", rule.run_as));
+            }
+            if rule.commands.is_empty() {
+                return Err(anyhow::anyhow!("Sudo rule for {} has no commands", principal));
+            }
+            for command in &rule.commands {
+                if command != "ALL" && valid_alias(command) {
+                    if !aliases.contains(&command.as_str()) {
+                        return Err(anyhow::anyhow!("Unknown command alias: {}", command));
+                    }
+                } else {
+                    validate_command(command)?;
+                }
+            }
+        }
+
+        for group in &self.polkit_admin_groups {
+            if !valid_name(group) {
+                return Err(anyhow::anyhow!("Invalid polkit admin group: {}", group));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn render_sudoers(&self) -> Result<String> {
+        self.validate()?;
+
+        let mut out = String::from("# Generated by xBitOS, edit /etc/xbitos/sudo.toml instead\n\n");
+
+        out.push_str(&format!("Defaults timestamp_timeout={}\n", self.timestamp_timeout));
+        out.push_str(&format!("Defaults lecture={}\n", self.lecture.as_str()));
+
+        if !self.command_aliases.is_empty() {
+            out.push('\n');
+            for alias in &self.command_aliases {
+                out.push_str(&format!("Cmnd_Alias {} = {}\n", alias.name, escape_commands(&alias.commands)));
+            }
+        }
+
+        out.push('\n');
+        for rule in &self.rules {
+            let principal = match (&rule.user, &rule.group) {
+                (Some(user), _) => user.clone(),
+                (_, Some(group)) => format!("%{}", group),
+                _ => unreachable!(),
+            };
+            let tag = if rule.nopasswd { "NOPASSWD: " } else { "" };
+            out.push_str(&format!(
+                "{} ALL=({}) {}{}\n",
+                principal,
+                rule.run_as,
+                tag,
+                escape_commands(&rule.commands)
+            ));
+        }
+
+        Ok(out)
+    }
+
+    pub fn render_polkit(&self) -> Result<String> {
+        self.validate()?;
+
+        let groups: Vec<String> = self
+            .polkit_admin_groups
+            .iter()
+            .map(|group| format!("\"unix-group:{}\"", group))
+            .collect();
+
+        Ok(format!(
+            "// Generated by xBitOS, edit /etc/xbitos/sudo.toml instead\n\
+             polkit.addAdminRule(function(action, subject) {{\n    return [{}];\n}});\n",
+            groups.join(", ")
+        ))
+    }
+}
+
+fn validate_command(command: &str) -> Result<()> {
+    if command == "ALL" {
+        return Ok(());
+    }
+    if !command.starts_with('/') {
+        return Err(anyhow::anyhow!("Sudo commands need an absolute path: {}", command));
+    }
+    if command.contains('\n') {
+        return Err(anyhow::anyhow!("Sudo command contains a newline: {}", command));
+    }
+    Ok(())
+}
+
+// الفاصلة والنقطتان و = لها معنى خاص في sudoers، والشرطة المائلة تهرب أولاً حتى لا تتضاعف
+fn escape_commands(commands: &[String]) -> String {
+    commands
+        .iter()
+        .map(|command| {
+            command
+                .replace('\\', "\\\\")
+                .replace(',', "\\,")
+                .replace(':', "\\:")
+                .replace('=', "\\=")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub struct SudoManager {
+    root: PathBuf,
+    policy_path: PathBuf,
+}
+
+impl SudoManager {
+    pub fn new() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+            policy_path: PathBuf::from("/etc/xbitos/sudo.toml"),
+        }
+    }
+
+    // إعداد نظام مثبت في مجلد آخر، مثلاً من المثبت
+    pub fn with_root(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            policy_path: root.join("etc/xbitos/sudo.toml"),
+        }
+    }
+
+    pub fn with_policy_path(mut self, policy_path: &Path) -> Self {
+        self.policy_path = policy_path.to_path_buf();
+        self
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    pub fn load_policy(&self) -> Result<SudoPolicy> {
+        if !self.policy_path.exists() {
+            return Ok(SudoPolicy::default());
+        }
+
+        let content = fs::read_to_string(&self.policy_path)?;
+        toml::from_str(&content).with_context(|| format!("Invalid sudo policy: {}", self.policy_path.display()))
+    }
+
+    pub fn setup(&self) -> Result<()> {
+        // كتابة السياسة الافتراضية ليعدلها المسؤول لاحقاً
+        if !self.policy_path.exists() {
+            if let Some(parent) = self.policy_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&self.policy_path, toml::to_string_pretty(&SudoPolicy::default())?)?;
+        }
+
+        let policy = self.load_policy()?;
+        self.apply(&policy)
+    }
+
+    pub fn apply(&self, policy: &SudoPolicy) -> Result<()> {
+        let sudoers = policy.render_sudoers()?;
+        let polkit = policy.render_polkit()?;
+
+        let sudoers_dir = self.path("etc/sudoers.d");
+        fs::create_dir_all(&sudoers_dir)?;
+        fs::set_permissions(&sudoers_dir, fs::Permissions::from_mode(0o750))?;
+
+        let target = sudoers_dir.join(SUDOERS_NAME);
+        let temporary = sudoers_dir.join(format!("{}.new", SUDOERS_NAME));
+        fs::write(&temporary, sudoers)?;
+        fs::set_permissions(&temporary, fs::Permissions::from_mode(0o440))?;
+
+        // ملف sudoers خاطئ قد يمنع أي وصول إداري، فلا يستبدل القديم إلا بعد الفحص
+        if let Err(e) = check_sudoers(&temporary) {
+            let _ = fs::remove_file(&temporary);
+            return Err(e);
+        }
+        fs::rename(&temporary, &target)?;
+
+        let rules_dir = self.path("etc/polkit-1/rules.d");
+        fs::create_dir_all(&rules_dir)?;
+        let rules = rules_dir.join(POLKIT_NAME);
+        fs::write(&rules, polkit)?;
+        fs::set_permissions(&rules, fs::Permissions::from_mode(0o644))?;
+
+        info!("Wrote sudo policy to {} and {}", target.display(), rules.display());
+        Ok(())
+    }
+}
+
+pub fn check_sudoers(path: &Path) -> Result<()> {
+    let output = match Command::new("visudo").arg("-cf").arg(path).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow::anyhow!(
+                "visudo not found, cannot check {} (install sudo)",
+                path.display()
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "visudo rejected {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
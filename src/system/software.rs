@@ -1,35 +1,47 @@
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use crate::system::collections::{AppCollection, CollectionLoader};
+use crate::system::security::advisories::{AdvisoryFeed, AffectedPackage};
+use crate::system::security::package_verifier::PackageVerifier;
+use crate::system::local_db::{DependencyTree, LocalDatabase, OptionalDependency, PackageDetails};
+use crate::system::transactions::{diff_versions, ChangeKind, PackageChange, Transaction, TransactionHistory, TransactionSource};
 
 #[derive(Serialize, Deserialize)]
 pub struct SoftwarePackage {
-    name: String,
-    description: String,
-    version: String,
-    category: String,
-    dependencies: Vec<String>,
-    optional_deps: Vec<String>,
-    size: u64,
-    installed: bool,
+    pub name: String,
+    pub description: String,
+    pub version: String,
+    pub category: String,
+    pub dependencies: Vec<String>,
+    pub optional_deps: Vec<String>,
+    pub size: u64,
+    pub installed: bool,
 }
 
 pub struct SoftwareCenter {
     db_path: PathBuf,
     cache_path: PathBuf,
     packages: HashMap<String, SoftwarePackage>,
+    history: TransactionHistory,
+    collections: Vec<AppCollection>,
 }
 
 impl SoftwareCenter {
     pub fn new() -> Result<Self> {
+        let db_path = PathBuf::from("/var/lib/xbitos/software");
+        let history = TransactionHistory::new(&db_path.join("history.json"))?;
+
         let mut instance = Self {
-            db_path: PathBuf::from("/var/lib/xbitos/software"),
+            db_path,
             cache_path: PathBuf::from("/var/cache/xbitos/packages"),
             packages: HashMap::new(),
+            history,
+            collections: Vec::new(),
         };
 
         instance.initialize()?;
@@ -40,6 +52,12 @@ impl SoftwareCenter {
         fs::create_dir_all(&self.db_path)?;
         fs::create_dir_all(&self.cache_path)?;
         
+        // تحميل مجموعات التطبيقات المختارة
+        self.collections = CollectionLoader::new().load().unwrap_or_else(|e| {
+            warn!("Failed to load application collections: {:#}", e);
+            Vec::new()
+        });
+
         // تحديث قاعدة البيانات
         self.update_database()?;
 
@@ -64,10 +82,39 @@ impl SoftwareCenter {
             return Err(anyhow::anyhow!("Package not found"));
         }
 
-        Command::new("pacman")
+        let before = installed_versions()?;
+
+        // حزم المستودع المحلي لا تثبت قبل التحقق من تجزئتها وتوقيعها
+        PackageVerifier::new().verify_repo_packages(&[package_name])?;
+
+        let status = Command::new("pacman")
             .args(["-S", "--noconfirm", package_name])
             .status()?;
 
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to install package: {}", package_name));
+        }
+
+        // تسجيل العملية في سجل المعاملات مع الاعتماديات المسحوبة
+        let after = installed_versions()?;
+        let mut changes = diff_versions(&before, &after);
+        if changes.iter().all(|c| c.name != package_name) {
+            if let Some(version) = after.get(package_name) {
+                changes.push(PackageChange {
+                    name: package_name.to_string(),
+                    kind: ChangeKind::Reinstalled,
+                    old_version: Some(version.clone()),
+                    new_version: Some(version.clone()),
+                });
+            }
+        }
+
+        self.history.record(
+            TransactionSource::SoftwareCenter,
+            Some(format!("pacman -S {}", package_name)),
+            changes,
+        )?;
+
         self.sync_local_database()?;
         Ok(())
     }
@@ -75,14 +122,131 @@ impl SoftwareCenter {
     pub fn remove_package(&mut self, package_name: &str) -> Result<()> {
         info!("Removing package: {}", package_name);
 
-        Command::new("pacman")
+        let before = installed_versions()?;
+
+        let status = Command::new("pacman")
             .args(["-R", "--noconfirm", package_name])
             .status()?;
 
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to remove package: {}", package_name));
+        }
+
+        self.history.record(
+            TransactionSource::SoftwareCenter,
+            Some(format!("pacman -R {}", package_name)),
+            diff_versions(&before, &installed_versions()?),
+        )?;
+
+        if let Some(pkg) = self.packages.get_mut(package_name) {
+            pkg.installed = false;
+        }
+
+        self.sync_local_database()?;
+        Ok(())
+    }
+
+    pub fn get_collections(&self) -> &[AppCollection] {
+        &self.collections
+    }
+
+    pub fn get_collection_status(&self, collection_id: &str) -> Result<Vec<(String, bool)>> {
+        let collection = self.find_collection(collection_id)?;
+        let db = LocalDatabase::new()?;
+
+        Ok(collection.packages
+            .iter()
+            .map(|pkg| (pkg.clone(), db.is_installed(pkg)))
+            .collect())
+    }
+
+    pub fn install_collection(&mut self, collection_id: &str) -> Result<()> {
+        let collection = self.find_collection(collection_id)?.clone();
+        info!("Installing collection: {}", collection.name);
+
+        let before = installed_versions()?;
+
+        let names: Vec<&str> = collection.packages.iter().map(|p| p.as_str()).collect();
+        PackageVerifier::new().verify_repo_packages(&names)?;
+
+        // تثبيت المجموعة كاملة في معاملة واحدة مع تجاهل المثبت مسبقاً
+        let status = Command::new("pacman")
+            .args(["-S", "--needed", "--noconfirm"])
+            .args(&collection.packages)
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Failed to install collection: {}", collection.id));
+        }
+
+        let changes = diff_versions(&before, &installed_versions()?);
+
+        if !changes.is_empty() {
+            self.history.record(
+                TransactionSource::SoftwareCenter,
+                Some(format!("install collection {}", collection.id)),
+                changes,
+            )?;
+        }
+
         self.sync_local_database()?;
         Ok(())
     }
 
+    fn find_collection(&self, collection_id: &str) -> Result<&AppCollection> {
+        self.collections
+            .iter()
+            .find(|c| c.id == collection_id)
+            .ok_or_else(|| anyhow::anyhow!("Collection not found: {}", collection_id))
+    }
+
+    // الحزم المثبتة المتأثرة بثغرات معروفة
+    pub fn get_security_advisories(&self, refresh: bool) -> Result<Vec<AffectedPackage>> {
+        AdvisoryFeed::new().check_installed(refresh)
+    }
+
+    pub fn get_package_details(&self, package_name: &str) -> Result<PackageDetails> {
+        LocalDatabase::new()?.get_details(package_name)
+    }
+
+    pub fn get_package_files(&self, package_name: &str) -> Result<Vec<PathBuf>> {
+        LocalDatabase::new()?.get_files(package_name)
+    }
+
+    pub fn get_reverse_dependencies(&self, package_name: &str) -> Result<DependencyTree> {
+        LocalDatabase::new()?.reverse_dependency_tree(package_name)
+    }
+
+    pub fn explain_installation(&self, package_name: &str) -> Result<Vec<String>> {
+        LocalDatabase::new()?.explain_installation(package_name)
+    }
+
+    pub fn get_optional_dependencies(&self, package_name: &str) -> Result<Vec<OptionalDependency>> {
+        LocalDatabase::new()?.optional_dependencies(package_name)
+    }
+
+    pub fn find_file_owner(&self, path: &Path) -> Result<Option<String>> {
+        LocalDatabase::new()?.find_owner(path)
+    }
+
+    pub fn get_history(&self) -> &[Transaction] {
+        self.history.transactions()
+    }
+
+    pub fn import_history(&mut self, log_path: &Path) -> Result<usize> {
+        self.history.import_pacman_log(log_path)
+    }
+
+    pub fn import_pacman_log(&mut self) -> Result<usize> {
+        self.import_history(Path::new("/var/log/pacman.log"))
+    }
+
+    pub fn revert_transaction(&mut self, transaction_id: &str) -> Result<String> {
+        let revert_id = self.history.revert(transaction_id)?;
+        self.sync_local_database()?;
+        Ok(revert_id)
+    }
+
     pub fn search_packages(&self, query: &str) -> Vec<&SoftwarePackage> {
         self.packages
             .values()
@@ -121,4 +285,12 @@ impl SoftwareCenter {
 
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+
+// إصدارات كل الحزم المثبتة من قاعدة pacman المحلية
+fn installed_versions() -> Result<HashMap<String, String>> {
+    Ok(LocalDatabase::new()?
+        .packages()
+        .map(|package| (package.name.clone(), package.version.clone()))
+        .collect())
+}
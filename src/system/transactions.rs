@@ -0,0 +1,433 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::system::packaging::repo_db::vercmp;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Installed,
+    Removed,
+    Upgraded,
+    Downgraded,
+    Reinstalled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PackageChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TransactionSource {
+    SoftwareCenter,
+    PacmanLog,
+    Revert,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Transaction {
+    pub id: String,
+    pub timestamp: DateTime<Local>,
+    pub command: Option<String>,
+    pub source: TransactionSource,
+    pub changes: Vec<PackageChange>,
+    pub reverted_by: Option<String>,
+}
+
+pub struct TransactionHistory {
+    history_file: PathBuf,
+    pkg_cache: PathBuf,
+    transactions: Vec<Transaction>,
+}
+
+impl TransactionHistory {
+    pub fn new(history_file: &Path) -> Result<Self> {
+        let transactions = if history_file.exists() {
+            let content = fs::read_to_string(history_file)?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid history file: {}", history_file.display()))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            history_file: history_file.to_path_buf(),
+            pkg_cache: PathBuf::from("/var/cache/pacman/pkg"),
+            transactions,
+        })
+    }
+
+    pub fn with_package_cache(mut self, pkg_cache: &Path) -> Self {
+        self.pkg_cache = pkg_cache.to_path_buf();
+        self
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Transaction> {
+        self.transactions.iter().find(|t| t.id == id)
+    }
+
+    pub fn between(&self, from: DateTime<Local>, to: DateTime<Local>) -> Vec<&Transaction> {
+        self.transactions
+            .iter()
+            .filter(|t| t.timestamp >= from && t.timestamp < to)
+            .collect()
+    }
+
+    pub fn touching(&self, package_name: &str) -> Vec<&Transaction> {
+        self.transactions
+            .iter()
+            .filter(|t| t.changes.iter().any(|c| c.name == package_name))
+            .collect()
+    }
+
+    pub fn record(&mut self, source: TransactionSource, command: Option<String>, changes: Vec<PackageChange>) -> Result<String> {
+        let transaction = Transaction {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Local::now(),
+            command,
+            source,
+            changes,
+            reverted_by: None,
+        };
+        let id = transaction.id.clone();
+
+        self.transactions.push(transaction);
+        self.save()?;
+        Ok(id)
+    }
+
+    pub fn import_pacman_log(&mut self, log_path: &Path) -> Result<usize> {
+        info!("Importing transactions from {}", log_path.display());
+
+        let content = fs::read_to_string(log_path)
+            .with_context(|| format!("Failed to read {}", log_path.display()))?;
+
+        // المقارنة مع ما كان موجوداً قبل الاستيراد فقط، فالسجلات القديمة بدقة الدقيقة
+        // قد تحتوي على معاملتين مختلفتين بالتوقيت نفسه
+        let existing = self.transactions.len();
+
+        let mut imported = 0;
+        for transaction in parse_pacman_log(&content) {
+            // تجاهل المعاملات التي استوردت سابقاً
+            let exists = self.transactions[..existing].iter().any(|t| {
+                t.source == TransactionSource::PacmanLog
+                    && t.timestamp == transaction.timestamp
+                    && t.changes == transaction.changes
+            });
+            if !exists {
+                self.transactions.push(transaction);
+                imported += 1;
+            }
+        }
+
+        self.transactions.sort_by_key(|t| t.timestamp);
+        self.save()?;
+
+        info!("Imported {} transactions", imported);
+        Ok(imported)
+    }
+
+    pub fn revert(&mut self, id: &str) -> Result<String> {
+        let transaction = self.get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found: {}", id))?;
+
+        if let Some(reverted_by) = &transaction.reverted_by {
+            return Err(anyhow::anyhow!("Transaction {} was already reverted by {}", id, reverted_by));
+        }
+
+        info!("Reverting transaction: {}", id);
+
+        let mut to_remove = Vec::new();
+        let mut to_install_files = Vec::new();
+        let mut to_install_repo = Vec::new();
+        let mut changes = Vec::new();
+
+        for change in &transaction.changes {
+            match change.kind {
+                ChangeKind::Installed => {
+                    to_remove.push(change.name.clone());
+                    changes.push(PackageChange {
+                        name: change.name.clone(),
+                        kind: ChangeKind::Removed,
+                        old_version: change.new_version.clone(),
+                        new_version: None,
+                    });
+                }
+                ChangeKind::Removed | ChangeKind::Upgraded | ChangeKind::Downgraded => {
+                    let version = change.old_version.as_deref();
+
+                    // نفضل النسخة المخزنة مؤقتاً لاستعادة الإصدار نفسه
+                    match version.and_then(|v| self.find_cached_package(&change.name, v)) {
+                        Some(file) => to_install_files.push(file),
+                        None if change.kind == ChangeKind::Removed => {
+                            warn!("No cached archive for {}, installing from repositories", change.name);
+                            to_install_repo.push(change.name.clone());
+                        }
+                        None => {
+                            return Err(anyhow::anyhow!(
+                                "Cannot restore {} {}: not found in package cache",
+                                change.name,
+                                version.unwrap_or("?"),
+                            ));
+                        }
+                    }
+
+                    // التراجع عن تخفيض إصدار ترقية، لذلك يحدد النوع بمقارنة الإصدارين
+                    let kind = match (&change.old_version, &change.new_version) {
+                        _ if change.kind == ChangeKind::Removed => ChangeKind::Installed,
+                        (Some(restored), Some(current)) => match vercmp(restored, current) {
+                            Ordering::Greater => ChangeKind::Upgraded,
+                            Ordering::Less => ChangeKind::Downgraded,
+                            Ordering::Equal => ChangeKind::Reinstalled,
+                        },
+                        _ => ChangeKind::Downgraded,
+                    };
+
+                    changes.push(PackageChange {
+                        name: change.name.clone(),
+                        kind,
+                        old_version: change.new_version.clone(),
+                        new_version: change.old_version.clone(),
+                    });
+                }
+                ChangeKind::Reinstalled => {}
+            }
+        }
+
+        if !to_remove.is_empty() {
+            run_pacman(&["-R", "--noconfirm"], &to_remove)?;
+        }
+
+        if !to_install_files.is_empty() {
+            let files: Vec<String> = to_install_files
+                .iter()
+                .map(|f| f.to_string_lossy().to_string())
+                .collect();
+            run_pacman(&["-U", "--noconfirm"], &files)?;
+        }
+
+        if !to_install_repo.is_empty() {
+            run_pacman(&["-S", "--noconfirm"], &to_install_repo)?;
+        }
+
+        let revert_id = self.record(
+            TransactionSource::Revert,
+            Some(format!("revert {}", id)),
+            changes,
+        )?;
+
+        if let Some(original) = self.transactions.iter_mut().find(|t| t.id == id) {
+            original.reverted_by = Some(revert_id.clone());
+        }
+        self.save()?;
+
+        Ok(revert_id)
+    }
+
+    fn find_cached_package(&self, name: &str, version: &str) -> Option<PathBuf> {
+        let prefix = format!("{}-{}-", name, version);
+
+        fs::read_dir(&self.pkg_cache)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                // يجب أن يتبع البادئة اسم المعمارية فقط وليس جزءاً من الإصدار
+                file_name.starts_with(&prefix)
+                    && !file_name[prefix.len()..].contains('-')
+                    && file_name.contains(".pkg.tar")
+                    && !file_name.ends_with(".sig")
+            })
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.history_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&self.history_file, serde_json::to_string_pretty(&self.transactions)?)?;
+        Ok(())
+    }
+}
+
+fn run_pacman(args: &[&str], packages: &[String]) -> Result<()> {
+    let status = Command::new("pacman")
+        .args(args)
+        .args(packages)
+        .status()?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!("pacman {} failed", args.join(" ")));
+    }
+
+    Ok(())
+}
+
+pub fn parse_pacman_log(content: &str) -> Vec<Transaction> {
+    let mut transactions = Vec::new();
+    let mut current: Option<Transaction> = None;
+    let mut last_command: Option<String> = None;
+
+    for line in content.lines() {
+        let Some((timestamp, rest)) = split_log_line(line) else {
+            continue;
+        };
+
+        if let Some(command) = rest.strip_prefix("[PACMAN] Running ") {
+            // في السجلات القديمة بداية أمر جديد هي نهاية المعاملة السابقة
+            if let Some(transaction) = current.take() {
+                if !transaction.changes.is_empty() {
+                    transactions.push(transaction);
+                }
+            }
+            last_command = Some(command.trim_matches('\'').to_string());
+            continue;
+        }
+
+        let Some(message) = rest.strip_prefix("[ALPM] ") else {
+            continue;
+        };
+
+        match message {
+            "transaction started" => {
+                current = Some(Transaction {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    timestamp,
+                    command: last_command.take(),
+                    source: TransactionSource::PacmanLog,
+                    changes: Vec::new(),
+                    reverted_by: None,
+                });
+            }
+            "transaction completed" | "transaction failed" | "transaction interrupted" => {
+                if let Some(transaction) = current.take() {
+                    if !transaction.changes.is_empty() {
+                        transactions.push(transaction);
+                    }
+                }
+            }
+            _ => {
+                if let Some(change) = parse_change(message) {
+                    // السجلات القديمة لا تحتوي على علامات بداية المعاملة
+                    let transaction = current.get_or_insert_with(|| Transaction {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        timestamp,
+                        command: last_command.take(),
+                        source: TransactionSource::PacmanLog,
+                        changes: Vec::new(),
+                        reverted_by: None,
+                    });
+                    transaction.changes.push(change);
+                }
+            }
+        }
+    }
+
+    if let Some(transaction) = current {
+        if !transaction.changes.is_empty() {
+            transactions.push(transaction);
+        }
+    }
+
+    transactions
+}
+
+fn split_log_line(line: &str) -> Option<(DateTime<Local>, &str)> {
+    let line = line.strip_prefix('[')?;
+    let (stamp, rest) = line.split_once("] ")?;
+    Some((parse_log_timestamp(stamp)?, rest))
+}
+
+fn parse_log_timestamp(stamp: &str) -> Option<DateTime<Local>> {
+    // الصيغة الحديثة: 2024-01-16T10:22:33+0100
+    if let Ok(time) = DateTime::<FixedOffset>::parse_from_str(stamp, "%Y-%m-%dT%H:%M:%S%z") {
+        return Some(time.with_timezone(&Local));
+    }
+
+    // الصيغة القديمة: 2019-01-16 10:22
+    let naive = NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M").ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+fn parse_change(message: &str) -> Option<PackageChange> {
+    let (action, rest) = message.split_once(' ')?;
+    let kind = match action {
+        "installed" => ChangeKind::Installed,
+        "removed" => ChangeKind::Removed,
+        "upgraded" => ChangeKind::Upgraded,
+        "downgraded" => ChangeKind::Downgraded,
+        "reinstalled" => ChangeKind::Reinstalled,
+        _ => return None,
+    };
+
+    let (name, versions) = rest.split_once(" (")?;
+    let versions = versions.strip_suffix(')')?;
+
+    let (old_version, new_version) = match kind {
+        ChangeKind::Installed => (None, Some(versions.to_string())),
+        ChangeKind::Removed => (Some(versions.to_string()), None),
+        ChangeKind::Reinstalled => (Some(versions.to_string()), Some(versions.to_string())),
+        ChangeKind::Upgraded | ChangeKind::Downgraded => {
+            let (old, new) = versions.split_once(" -> ")?;
+            (Some(old.to_string()), Some(new.to_string()))
+        }
+    };
+
+    Some(PackageChange {
+        name: name.to_string(),
+        kind,
+        old_version,
+        new_version,
+    })
+}
+
+// الفرق بين حالتين لقاعدة الحزم المحلية، ويشمل الاعتماديات التي سحبها pacman
+pub fn diff_versions(before: &HashMap<String, String>, after: &HashMap<String, String>) -> Vec<PackageChange> {
+    let mut changes = Vec::new();
+
+    for (name, new_version) in after {
+        let kind = match before.get(name) {
+            None => ChangeKind::Installed,
+            Some(old_version) => match vercmp(new_version, old_version) {
+                Ordering::Greater => ChangeKind::Upgraded,
+                Ordering::Less => ChangeKind::Downgraded,
+                Ordering::Equal => continue,
+            },
+        };
+        changes.push(PackageChange {
+            name: name.clone(),
+            kind,
+            old_version: before.get(name).cloned(),
+            new_version: Some(new_version.clone()),
+        });
+    }
+
+    for (name, old_version) in before {
+        if !after.contains_key(name) {
+            changes.push(PackageChange {
+                name: name.clone(),
+                kind: ChangeKind::Removed,
+                old_version: Some(old_version.clone()),
+                new_version: None,
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
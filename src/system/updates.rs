@@ -1,7 +1,8 @@
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
 use std::fs;
 use std::path::PathBuf;
+use crate::system::security::advisories::{log_affected, AdvisoryFeed, AffectedPackage};
 
 pub struct UpdateManager {
     config_path: PathBuf,
@@ -39,7 +40,7 @@ Wants=network-online.target
 
 [Service]
 Type=oneshot
-ExecStart=/usr/bin/pacman -Syu --noconfirm
+ExecStart=/usr/bin/xbitos update
 ExecStartPost=/usr/bin/snapper create -c timeline -d "Auto Update"
 
 [Install]
@@ -75,4 +76,33 @@ WantedBy=timers.target
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    // تحديث النظام مع عرض الثغرات قبل التحديث وما يبقى منها بعده
+    pub fn update_system(&self) -> Result<()> {
+        // تعذر قراءة الإرشادات لا يمنع التحديث نفسه
+        if let Err(e) = self.check_advisories(true) {
+            warn!("Failed to check security advisories: {:#}", e);
+        }
+
+        crate::system::package_manager::PackageManager::new().update_system()?;
+
+        if let Err(e) = self.check_advisories(false) {
+            warn!("Failed to check security advisories: {:#}", e);
+        }
+        Ok(())
+    }
+
+    pub fn check_advisories(&self, refresh: bool) -> Result<Vec<AffectedPackage>> {
+        let affected = AdvisoryFeed::new().check_installed(refresh)?;
+
+        if affected.is_empty() {
+            info!("No installed package is affected by known vulnerabilities");
+        } else {
+            let fixable = affected.iter().filter(|p| p.has_fix()).count();
+            warn!("{} installed packages have known vulnerabilities, {} fixed by updating", affected.len(), fixable);
+            log_affected(&affected);
+        }
+
+        Ok(affected)
+    }
+}
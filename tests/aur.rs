@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use xbitos::system::packaging::aur::AurClient;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@xbitos.invalid"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn srcinfo(base: &str, version: &str, depends: &[&str]) -> String {
+    let mut out = format!("pkgbase = {}\n\tpkgver = {}\n\tpkgrel = 1\n\tarch = any\n", base, version);
+    for dependency in depends {
+        out.push_str(&format!("\tdepends = {}\n", dependency));
+    }
+    out.push_str(&format!("\npkgname = {}\n", base));
+    out
+}
+
+// مستودع git عاري بالاسم الذي يستخدمه AUR، أي pkgbase.git
+fn publish(mirror: &Path, work: &Path, base: &str, version: &str, depends: &[&str]) {
+    let checkout = work.join(base);
+    if !checkout.exists() {
+        let bare = mirror.join(format!("{}.git", base));
+        fs::create_dir_all(&bare).unwrap();
+        git(&bare, &["init", "--quiet", "--bare", "--initial-branch=master"]);
+        fs::create_dir_all(&checkout).unwrap();
+        git(&checkout, &["init", "--quiet", "--initial-branch=master"]);
+        git(&checkout, &["remote", "add", "origin", bare.to_str().unwrap()]);
+    }
+
+    fs::write(checkout.join(".SRCINFO"), srcinfo(base, version, depends)).unwrap();
+    fs::write(checkout.join("PKGBUILD"), format!("pkgname={}\npkgver={}\npkgrel=1\n", base, version)).unwrap();
+    git(&checkout, &["add", "."]);
+    git(&checkout, &["commit", "--quiet", "-m", version]);
+    git(&checkout, &["push", "--quiet", "origin", "master"]);
+}
+
+fn git_available() -> bool {
+    Command::new("git").arg("--version").output().is_ok()
+}
+
+#[test]
+fn resolve_builds_dependencies_first() {
+    if !git_available() {
+        eprintln!("git not found, skipping");
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = dir.path().join("aur");
+    let work = dir.path().join("work");
+    publish(&mirror, &work, "libfoo", "1.0", &[]);
+    publish(&mirror, &work, "foo", "2.0", &["libfoo>=1.0", "xbitos-not-in-any-repo"]);
+
+    let aur = AurClient::new(&dir.path().join("cache")).with_base_url(mirror.to_str().unwrap());
+    assert_eq!(aur.package_base("foo").unwrap().as_deref(), Some("foo"));
+    assert!(aur.package_base("missing").unwrap().is_none());
+
+    let bases: Vec<String> = aur.resolve("foo").unwrap().into_iter().map(|package| package.base).collect();
+    assert_eq!(bases, ["libfoo", "foo"]);
+
+    assert!(aur.resolve("missing").is_err());
+}
+
+#[test]
+fn resolve_reports_cycles() {
+    if !git_available() {
+        eprintln!("git not found, skipping");
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = dir.path().join("aur");
+    let work = dir.path().join("work");
+    publish(&mirror, &work, "first", "1.0", &["second"]);
+    publish(&mirror, &work, "second", "1.0", &["first"]);
+
+    let aur = AurClient::new(&dir.path().join("cache")).with_base_url(mirror.to_str().unwrap());
+    let error = aur.resolve("first").unwrap_err().to_string();
+    assert!(error.contains("first -> second -> first"), "{}", error);
+}
+
+#[test]
+fn review_shows_only_new_changes() {
+    if !git_available() {
+        eprintln!("git not found, skipping");
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = dir.path().join("aur");
+    let work = dir.path().join("work");
+    publish(&mirror, &work, "foo", "1.0", &[]);
+
+    let aur = AurClient::new(&dir.path().join("cache")).with_base_url(mirror.to_str().unwrap());
+    aur.fetch("foo").unwrap();
+
+    let diff = aur.review_diff("foo").unwrap();
+    assert!(!diff.is_reviewed());
+    assert!(diff.reviewed.is_none());
+    assert!(diff.diff.contains("+pkgver=1.0"));
+    assert!(!diff.diff.contains(".SRCINFO"));
+
+    let reviewed = aur.mark_reviewed("foo").unwrap();
+    assert!(aur.review_diff("foo").unwrap().is_reviewed());
+
+    // تحديث في المرآة يحتاج مراجعة جديدة ويعرض الفرق منذ آخر مراجعة فقط
+    publish(&mirror, &work, "foo", "1.1", &[]);
+    aur.fetch("foo").unwrap();
+
+    let diff = aur.review_diff("foo").unwrap();
+    assert!(!diff.is_reviewed());
+    assert_eq!(diff.reviewed.as_deref(), Some(reviewed.as_str()));
+    assert!(diff.diff.contains("-pkgver=1.0"));
+    assert!(diff.diff.contains("+pkgver=1.1"));
+}
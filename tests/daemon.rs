@@ -0,0 +1,114 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use xbitos::system::daemon::{self, BusAddress, XbitosDaemonProxy};
+
+// يوقف العملية عند انتهاء الاختبار حتى لو فشل
+struct ChildGuard(Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// الملف يظهر عند bind قبل أن يبدأ dbus-daemon بقبول الاتصالات
+fn wait_for(path: &Path) {
+    for _ in 0..100 {
+        if UnixStream::connect(path).is_ok() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("{} did not appear", path.display());
+}
+
+#[tokio::test]
+async fn daemon_on_private_bus() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("bus");
+    let address = format!("unix:path={}", socket.display());
+
+    let _bus = match Command::new("dbus-daemon")
+        .args(["--session", "--nofork", &format!("--address={}", address)])
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => ChildGuard(child),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("dbus-daemon not found, skipping");
+            return;
+        }
+        Err(e) => panic!("{}", e),
+    };
+    wait_for(&socket);
+
+    // borg وهمي يفشل دائماً للتأكد من وصول الخطأ إلى العميل
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    let borg = bin.join("borg");
+    fs::write(&borg, "#!/bin/sh\nexit 2\n").unwrap();
+    fs::set_permissions(&borg, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+
+    let _service = ChildGuard(
+        Command::new(env!("CARGO_BIN_EXE_xbitos-daemon"))
+            .args(["--address", &address, "--no-polkit"])
+            .env("PATH", path)
+            .env(daemon::TEST_BUS_ENV, &address)
+            .spawn()
+            .unwrap(),
+    );
+
+    let connection = daemon::connect(&BusAddress::Custom(address)).await.unwrap();
+    let dbus = zbus::fdo::DBusProxy::new(&connection).await.unwrap();
+    let name: zbus::names::BusName = daemon::BUS_NAME.try_into().unwrap();
+    for _ in 0..100 {
+        if dbus.name_has_owner(name.clone()).await.unwrap() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let proxy = XbitosDaemonProxy::new(&connection).await.unwrap();
+    assert_eq!(proxy.version().await.unwrap(), env!("CARGO_PKG_VERSION"));
+
+    for name in ["..", ".", "-rf", "a/b", ""] {
+        let error = proxy.restore_backup(name).await.unwrap_err();
+        assert!(error.to_string().contains("InvalidArgs"), "{}: {}", name, error);
+    }
+
+    let error = proxy.install_packages(vec!["--root=/".to_string()]).await.unwrap_err();
+    assert!(error.to_string().contains("InvalidArgs"), "{}", error);
+
+    let error = proxy.create_backup().await.unwrap_err();
+    assert!(error.to_string().contains("borg create failed"), "{}", error);
+
+    let error = proxy.restore_backup("backup_1").await.unwrap_err();
+    assert!(error.to_string().contains("borg extract failed"), "{}", error);
+}
+
+#[test]
+fn no_polkit_requires_the_private_test_bus() {
+    let refused = |args: &[&str], test_bus: Option<&str>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_xbitos-daemon"));
+        command.args(args).env_remove(daemon::TEST_BUS_ENV).stderr(Stdio::null());
+        if let Some(test_bus) = test_bus {
+            command.env(daemon::TEST_BUS_ENV, test_bus);
+        }
+        !command.status().unwrap().success()
+    };
+
+    // يرفض قبل الاتصال بأي ناقل، لذلك لا يحتاج dbus-daemon
+    assert!(refused(&["--no-polkit"], None));
+    assert!(refused(&["--session", "--no-polkit"], None));
+    assert!(refused(&["--address", "unix:path=/run/dbus/system_bus_socket", "--no-polkit"], None));
+    assert!(refused(
+        &["--address", "unix:path=/run/dbus/system_bus_socket", "--no-polkit"],
+        Some("unix:path=/tmp/other-bus")
+    ));
+}
@@ -0,0 +1,97 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use xbitos::system::packaging::repo_db::RepoDatabase;
+use xbitos::system::packaging::signing::{signature_path, KeyManager};
+use xbitos::system::security::package_verifier::{PackageVerifier, Verdict};
+
+// يوقف gpg-agent الذي يشغله gpg داخل المجلدات المؤقتة
+struct AgentGuard(Vec<PathBuf>);
+
+impl Drop for AgentGuard {
+    fn drop(&mut self) {
+        for home in &self.0 {
+            let _ = Command::new("gpgconf").arg("--homedir").arg(home).args(["--kill", "gpg-agent"]).status();
+        }
+    }
+}
+
+fn write_package(dir: &Path, name: &str, description: &str) -> PathBuf {
+    let path = dir.join(format!("{}-1.0-1-any.pkg.tar.gz", name));
+    let pkginfo = format!("pkgname = {}\npkgver = 1.0-1\npkgdesc = {}\narch = any\nlicense = MIT\n", name, description);
+
+    let mut builder = tar::Builder::new(GzEncoder::new(File::create(&path).unwrap(), Compression::default()));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(pkginfo.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, ".PKGINFO", pkginfo.as_bytes()).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    path
+}
+
+#[test]
+fn verdicts_for_signed_unsigned_and_tampered_packages() {
+    if Command::new("gpg").arg("--version").output().is_err() {
+        eprintln!("gpg not found, skipping");
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let signing_home = dir.path().join("signing");
+    let keyring = dir.path().join("keyring");
+    let repo = dir.path().join("repo");
+    fs::create_dir_all(&repo).unwrap();
+    let _agents = AgentGuard(vec![signing_home.clone(), keyring.clone()]);
+
+    let keys = KeyManager::with_paths(&signing_home, &dir.path().join("signing.json"));
+    let fingerprint = keys.generate_key("xBitOS Repo", "repo@xbitos.invalid").unwrap();
+    keys.set_signing_key(&fingerprint).unwrap();
+    let signer = keys.configured_signer().unwrap();
+
+    // حلقة المفاتيح التي تتحقق تعرف المفتاح العام فقط دون أن تثق به
+    let public_key = dir.path().join("repo.asc");
+    keys.export_public_key(&fingerprint, &public_key).unwrap();
+    KeyManager::with_paths(&keyring, &dir.path().join("unused.json")).import_key(&public_key).unwrap();
+
+    let signed = write_package(&repo, "signed", "original");
+    let unsigned = write_package(&repo, "unsigned", "original");
+    let mut database = RepoDatabase::open(&repo, "xbitos").unwrap().sign_with(signer);
+    database.add(&signed).unwrap();
+    database = database.sign_with(None);
+    database.add(&unsigned).unwrap();
+    database.write().unwrap();
+    assert!(!signature_path(&unsigned).exists());
+
+    let untrusting = PackageVerifier::with_paths(&keyring, &repo);
+    let trusting = PackageVerifier::with_paths(&keyring, &repo).with_signing_key(Some(&fingerprint));
+
+    // مفتاح مستورد دون ثقة لا يكفي إلا إذا كان مفتاح المستودع المحدد
+    let verdict = untrusting.verify_package(&signed).unwrap().verdict;
+    assert_eq!(verdict, Verdict::UntrustedKey { fingerprint: fingerprint.clone() });
+    assert!(verdict.is_acceptable(false));
+    assert!(!verdict.is_acceptable(true));
+    assert_eq!(
+        trusting.verify_package(&signed).unwrap().verdict,
+        Verdict::Trusted { fingerprint: fingerprint.clone() }
+    );
+
+    // التوقيع المضمن في قاعدة البيانات يستخدم عند غياب ملف .sig
+    fs::remove_file(signature_path(&signed)).unwrap();
+    assert!(trusting.verify_package(&signed).unwrap().verdict.is_trusted());
+
+    let verdict = trusting.verify_package(&unsigned).unwrap().verdict;
+    assert_eq!(verdict, Verdict::Unsigned);
+    assert!(verdict.is_acceptable(false));
+    assert!(!verdict.is_acceptable(true));
+
+    // حزمة بنفس الاسم لكن بمحتوى مختلف عن المسجل في قاعدة البيانات
+    write_package(&repo, "unsigned", "tampered");
+    assert!(matches!(
+        trusting.verify_package(&unsigned).unwrap().verdict,
+        Verdict::HashMismatch { .. }
+    ));
+}
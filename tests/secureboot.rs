@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use xbitos::system::security::secure_boot::SecureBootManager;
+
+fn tool_available(name: &str, arg: &str) -> bool {
+    Command::new(name).arg(arg).output().is_ok()
+}
+
+// أي ملف EFI غير موقع من الحزم المثبتة يكفي للتجربة
+fn find_efi_binary() -> Option<PathBuf> {
+    [
+        "/usr/lib/systemd/boot/efi/systemd-bootx64.efi",
+        "/usr/lib/systemd/boot/efi/linuxx64.efi.stub",
+        "/usr/share/efitools/efi/HelloWorld.efi",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .find(|path| path.exists())
+}
+
+#[test]
+fn sign_and_verify_with_generated_keys() {
+    let tools = [("openssl", "version"), ("sbsign", "--version"), ("sbverify", "--version")];
+    if !tools.iter().all(|(name, arg)| tool_available(name, arg)) {
+        eprintln!("openssl or sbsigntools not found, skipping");
+        return;
+    }
+    let Some(efi) = find_efi_binary() else {
+        eprintln!("no EFI binary found, skipping");
+        return;
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let manager = SecureBootManager::new().with_keys_path(&dir.path().join("keys"));
+
+    manager.generate_keys("xBitOS Test").unwrap();
+    assert!(manager.has_keys());
+    assert!(manager.generate_keys("xBitOS Test").is_err());
+
+    let unsigned = dir.path().join("unsigned.efi");
+    let signed = dir.path().join("signed.efi");
+    fs::copy(&efi, &unsigned).unwrap();
+    assert!(!manager.verify(&unsigned).unwrap());
+
+    // التوقيع إلى ملف آخر يترك الأصل دون تغيير
+    let result = manager.sign(&unsigned, Some(&signed)).unwrap();
+    assert_eq!(result.path, signed);
+    assert!(!result.already_signed);
+    assert!(manager.verify(&signed).unwrap());
+    assert!(!manager.verify(&unsigned).unwrap());
+
+    // ملف موقع بمفتاحنا لا يعاد توقيعه
+    assert!(manager.sign(&signed, None).unwrap().already_signed);
+
+    // مفاتيح أخرى لا تقبل التوقيع
+    let other = SecureBootManager::new().with_keys_path(&dir.path().join("other"));
+    other.generate_keys("xBitOS Other").unwrap();
+    assert!(!other.verify(&signed).unwrap());
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::process::Command;
+use xbitos::system::packaging::signing::{signature_path, KeyManager};
+
+fn gpg_available() -> bool {
+    Command::new("gpg").arg("--version").output().is_ok()
+}
+
+// يوقف gpg-agent الذي يشغله gpg داخل المجلد المؤقت
+struct AgentGuard(std::path::PathBuf);
+
+impl Drop for AgentGuard {
+    fn drop(&mut self) {
+        let _ = Command::new("gpgconf")
+            .arg("--homedir")
+            .arg(&self.0)
+            .args(["--kill", "gpg-agent"])
+            .status();
+    }
+}
+
+#[test]
+fn sign_and_verify_in_temporary_keyring() {
+    if !gpg_available() {
+        eprintln!("gpg not found, skipping");
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let gnupg_home = dir.path().join("gnupg");
+    let config_path = dir.path().join("signing.json");
+    let _agent = AgentGuard(gnupg_home.clone());
+    let manager = KeyManager::with_paths(&gnupg_home, &config_path);
+
+    let fingerprint = manager.generate_key("xBitOS Test", "test@xbitos.invalid").unwrap();
+    assert_eq!(fingerprint.len(), 40);
+
+    let keys = manager.list_keys().unwrap();
+    assert!(keys.iter().any(|key| key.fingerprint == fingerprint && key.has_secret));
+
+    let config = manager.set_signing_key("test@xbitos.invalid").unwrap();
+    assert_eq!(config.key_id, fingerprint);
+    let signer = manager.configured_signer().unwrap().expect("signer configured");
+
+    let package = dir.path().join("hello-1.0-1-x86_64.pkg.tar.zst");
+    fs::write(&package, b"package contents").unwrap();
+    assert!(signer.needs_signature(&package).unwrap());
+
+    // توقيع صحيح
+    let signature = signer.sign(&package).unwrap();
+    assert_eq!(signature, signature_path(&package));
+    assert!(signer.verify(&package, &signature).unwrap());
+    assert!(!signer.needs_signature(&package).unwrap());
+
+    // توقيع لا يطابق الملف بعد تعديله
+    let tampered = dir.path().join("tampered.pkg.tar.zst");
+    fs::write(&tampered, b"other contents").unwrap();
+    assert!(!signer.verify(&tampered, &signature).unwrap());
+
+    // توقيع غير موجود
+    let unsigned = dir.path().join("unsigned.pkg.tar.zst");
+    fs::write(&unsigned, b"package contents").unwrap();
+    assert!(!signer.verify(&unsigned, &signature_path(&unsigned)).unwrap());
+}
+
+#[test]
+fn missing_config_has_no_signer() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = KeyManager::with_paths(&dir.path().join("gnupg"), &dir.path().join("signing.json"));
+
+    assert!(manager.get_config().unwrap().is_none());
+    assert!(manager.configured_signer().unwrap().is_none());
+    assert!(manager.list_keys().unwrap().is_empty());
+}
+
+#[test]
+fn signing_key_selection_is_exact() {
+    if !gpg_available() {
+        eprintln!("gpg not found, skipping");
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let gnupg_home = dir.path().join("gnupg");
+    let _agent = AgentGuard(gnupg_home.clone());
+    let manager = KeyManager::with_paths(&gnupg_home, &dir.path().join("signing.json"));
+
+    let first = manager.generate_key("Alice Build", "alice@xbitos.invalid").unwrap();
+    let second = manager.generate_key("Alice Release", "alice.release@xbitos.invalid").unwrap();
+
+    // اسم يطابق المفتاحين لا يختار أحدهما بصمت
+    let error = manager.set_signing_key("Alice").unwrap_err().to_string();
+    assert!(error.contains(&first) && error.contains(&second), "{}", error);
+
+    assert_eq!(manager.set_signing_key("alice.release@").unwrap().key_id, second);
+    assert_eq!(manager.set_signing_key(&first).unwrap().key_id, first);
+    assert_eq!(manager.set_signing_key(&second[24..].to_lowercase()).unwrap().key_id, second);
+    assert!(manager.set_signing_key(&first[32..]).is_err());
+}